@@ -0,0 +1,30 @@
+#version 330
+
+// An old curved monitor: the picture bulges out in the middle (barrel distortion), with scanlines
+// and dark corners. Anything pushed off the edge of the screen by the curve is drawn black.
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+out vec4 finalColor;
+
+const float CURVATURE = 0.12;
+
+void main()
+{
+    vec2 centered = fragTexCoord * 2.0 - 1.0;
+    centered *= 1.0 + CURVATURE * dot(centered, centered);
+    vec2 uv = centered * 0.5 + 0.5;
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        finalColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec4 texel = texture(texture0, uv) * colDiffuse * fragColor;
+    float line = mod(floor(gl_FragCoord.y / 2.0), 2.0);
+    float shade = mix(1.0, 0.75, line) * smoothstep(1.6, 0.9, length(centered));
+    finalColor = vec4(texel.rgb * shade, texel.a);
+}
@@ -0,0 +1,18 @@
+#version 330
+
+// Darkens every other pair of screen rows, like the gaps between the lines of an old TV.
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+out vec4 finalColor;
+
+void main()
+{
+    vec4 texel = texture(texture0, fragTexCoord) * colDiffuse * fragColor;
+    float line = mod(floor(gl_FragCoord.y / 2.0), 2.0);
+    finalColor = vec4(texel.rgb * mix(1.0, 0.7, line), texel.a);
+}
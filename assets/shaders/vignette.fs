@@ -0,0 +1,19 @@
+#version 330
+
+// Fades the picture towards black at the corners.
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+out vec4 finalColor;
+
+void main()
+{
+    vec4 texel = texture(texture0, fragTexCoord) * colDiffuse * fragColor;
+    float distance = length(fragTexCoord - vec2(0.5));
+    float shade = smoothstep(0.75, 0.35, distance);
+    finalColor = vec4(texel.rgb * shade, texel.a);
+}
@@ -0,0 +1,142 @@
+//! The trade offered by an altar, shown over the maze.
+//!
+//! Stepping on an altar pushes an [`AltarScene`] with three random trades. The player takes one
+//! or walks away, and either way the altar is used up.
+use raylib::prelude::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::game_data::{GameData, StatBoost};
+use crate::items::Item;
+use crate::scenes::{Scene, SceneSwitch};
+
+/// Something given up at an altar in return for something else.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trade {
+    /// lose `health` to gain `points`.
+    HealthForScore { health: i32, points: u32 },
+    /// pay `points` to skip ahead a level, which raises the level-based bonuses.
+    ScoreForLevel { points: u32 },
+    /// give up an `item` for a boost that lasts the rest of the session.
+    ItemForBoost { item: Item, boost: StatBoost },
+}
+
+impl Trade {
+    /// one trade of each kind with random amounts, in a random order.
+    pub fn random_offers(rng: &mut impl Rng) -> Vec<Trade> {
+        let health = rng.random_range(1..=2) * 20;
+        let boost = if rng.random_bool(0.5) { StatBoost::MaxHealth(20) } else { StatBoost::Armor(5) };
+        let mut offers = vec![
+            Trade::HealthForScore { health, points: health as u32 * 10 },
+            Trade::ScoreForLevel { points: rng.random_range(2..=5) * 100 },
+            Trade::ItemForBoost { item: Item::Shield, boost },
+        ];
+        offers.shuffle(rng);
+        offers
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Trade::HealthForScore { health, points } => format!("Give {} health for {} points", health, points),
+            Trade::ScoreForLevel { points } => format!("Give {} points to skip a level", points),
+            Trade::ItemForBoost { item, boost } => {
+                let boost = match boost {
+                    StatBoost::MaxHealth(amount) => format!("+{} max health", amount),
+                    StatBoost::Armor(amount) => format!("{} less damage per hit", amount),
+                };
+                format!("Give a {} for {}", item.map_kind(), boost)
+            }
+        }
+    }
+
+    /// whether the player has what the trade asks for. Health trades never leave them on zero.
+    pub fn affordable(&self, data: &GameData) -> bool {
+        match self {
+            Trade::HealthForScore { health, .. } => data.health > *health,
+            Trade::ScoreForLevel { points } => data.points >= *points,
+            Trade::ItemForBoost { item, .. } => data.item_count(*item) > 0,
+        }
+    }
+
+    pub fn apply(&self, data: &mut GameData) {
+        match *self {
+            Trade::HealthForScore { health, points } => {
+                data.health -= health;
+                data.add_points(points);
+            }
+            Trade::ScoreForLevel { points } => {
+                data.points -= points;
+                data.level += 1;
+            }
+            Trade::ItemForBoost { item, boost } => {
+                if data.take_item(item) {
+                    data.apply_boost(boost);
+                }
+            }
+        }
+    }
+}
+
+/// An altar's offers, drawn over the maze. Up/down chooses a trade, Enter takes it and Esc or
+/// Backspace walks away.
+pub struct AltarScene {
+    offers: Vec<Trade>,
+    selected: usize,
+}
+
+impl AltarScene {
+    pub fn new(offers: Vec<Trade>) -> Self {
+        Self { offers, selected: 0 }
+    }
+}
+
+impl Scene for AltarScene {
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) || _rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            return SceneSwitch::Pop;
+        }
+
+        let count = self.offers.len();
+        if _rl.is_key_pressed(KeyboardKey::KEY_DOWN) || _rl.is_key_pressed(KeyboardKey::KEY_S) {
+            self.selected = (self.selected + 1) % count;
+        }
+        if _rl.is_key_pressed(KeyboardKey::KEY_UP) || _rl.is_key_pressed(KeyboardKey::KEY_W) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if _rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            let trade = self.offers[self.selected];
+            if trade.affordable(data) {
+                trade.apply(data);
+                return SceneSwitch::Pop;
+            }
+        }
+
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.draw_rectangle(0, 0, data.screen_width, data.screen_height, Color::BLACK.alpha(0.5));
+        d.draw_rectangle(290, 250, 700, 380, Color::BLACK.alpha(0.85));
+        d.draw_rectangle_lines(290, 250, 700, 380, Color::PURPLE);
+        d.draw_text("The altar offers a trade", 330, 275, 35, Color::VIOLET);
+
+        for (i, trade) in self.offers.iter().enumerate() {
+            let y = 360 + i as i32 * 60;
+            let color = match (i == self.selected, trade.affordable(data)) {
+                (_, false) => Color::GRAY,
+                (true, true) => Color::GOLD,
+                (false, true) => Color::WHITE,
+            };
+            if i == self.selected {
+                d.draw_text(">", 320, y, 25, color);
+            }
+            d.draw_text(&trade.description(), 350, y, 25, color);
+        }
+
+        d.draw_text("Enter to trade, Esc to walk away.", 330, 590, 20, Color::LIGHTGRAY);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
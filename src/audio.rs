@@ -0,0 +1,75 @@
+//! Sounds made in code rather than loaded from files. For now that is only the wall cues: a short
+//! tone for each side of the player a wall is on, for players who find the maze hard to see.
+use raylib::prelude::*;
+
+use crate::utils::Direction;
+
+const SAMPLE_RATE: u32 = 22050;
+// seconds each cue tone lasts
+const CUE_LENGTH: f32 = 0.12;
+const CUE_VOLUME: f32 = 0.4;
+
+/// One tone for each direction on screen. Up and down are told apart by pitch, left and right by
+/// pitch and by which ear they play in.
+pub struct WallCues {
+    sounds: [(Direction, Sound<'static>); 4],
+}
+
+impl WallCues {
+    /// Open the audio device and make the tones, or `None` if there is no audio device. The
+    /// device stays open for the rest of the game once opened.
+    pub fn load() -> Option<Self> {
+        let audio: &'static RaylibAudio = Box::leak(Box::new(RaylibAudio::init_audio_device().ok()?));
+        // raylib pans from 1.0 (all left) to 0.0 (all right)
+        let tones = [
+            (Direction::North, 880.0, 0.5),
+            (Direction::South, 330.0, 0.5),
+            (Direction::East, 587.0, 0.1),
+            (Direction::West, 494.0, 0.9),
+        ];
+        let sounds = tones.map(|(direction, frequency, pan)| {
+            let wave = audio.new_wave_from_memory(".wav", &tone_wav(frequency)).ok()?;
+            let sound = audio.new_sound_from_wave(&wave).ok()?;
+            sound.set_pan(pan);
+            sound.set_volume(CUE_VOLUME);
+            Some((direction, sound))
+        });
+        let [north, south, east, west] = sounds;
+        Some(WallCues { sounds: [north?, south?, east?, west?] })
+    }
+
+    /// play the tone of each direction on screen there is a wall in.
+    pub fn play(&self, walls: &[Direction]) {
+        for (direction, sound) in &self.sounds {
+            if walls.contains(direction) {
+                sound.play();
+            }
+        }
+    }
+}
+
+// a 16-bit mono WAV file of a sine tone that fades out, so it ends without a click
+fn tone_wav(frequency: f32) -> Vec<u8> {
+    let samples = (SAMPLE_RATE as f32 * CUE_LENGTH) as u32;
+    let data_size = samples * 2;
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let fade = 1.0 - i as f32 / samples as f32;
+        let sample = (t * frequency * std::f32::consts::TAU).sin() * fade * i16::MAX as f32;
+        bytes.extend_from_slice(&(sample as i16).to_le_bytes());
+    }
+    bytes
+}
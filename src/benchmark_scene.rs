@@ -0,0 +1,110 @@
+//! A developer scene that times A* on bigger and bigger mazes.
+//!
+//! Not in the menu: Ctrl+Shift+B on the main menu opens it. Each frame it generates the next maze
+//! size, solves it [`SOLVES_PER_SIZE`] times with [`solve_astar`] and adds a row to the results.
+use std::time::Instant;
+
+use raylib::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::game_data::GameData;
+use crate::maze_gen::{CellType, generate_maze};
+use crate::pathfinding::solve_astar;
+use crate::scenes::{Scene, SceneSwitch};
+
+/// the maze sizes (width and height) tried, smallest first.
+pub const BENCHMARK_SIZES: [usize; 8] = [21, 41, 81, 161, 241, 321, 481, 641];
+/// how many times each maze is solved, the time shown is the average.
+pub const SOLVES_PER_SIZE: u32 = 20;
+
+/// One maze size and how A* did on it.
+#[derive(Clone, Copy, Debug)]
+pub struct BenchmarkResult {
+    pub size: usize,
+    pub solve_ms: f64,
+    /// cells on the route from the start to the exit, or `None` if there was none.
+    pub path_length: Option<usize>,
+}
+
+pub struct PathBenchmarkScene {
+    results: Vec<BenchmarkResult>,
+    rng: StdRng,
+}
+
+impl PathBenchmarkScene {
+    pub fn new() -> Self {
+        Self {
+            results: Vec::new(),
+            // the same mazes every run, so timings can be compared
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+
+    fn run(&mut self, size: usize) -> BenchmarkResult {
+        let grid = generate_maze(size, size, &mut self.rng);
+        let find = |wanted: CellType| {
+            grid.iter()
+                .enumerate()
+                .find_map(|(y, row)| row.iter().position(|c| *c == wanted).map(|x| (x, y)))
+        };
+        let (Some(start), Some(exit)) = (find(CellType::Start), find(CellType::Exit)) else {
+            return BenchmarkResult { size, solve_ms: 0.0, path_length: None };
+        };
+
+        let mut path_length = None;
+        let started = Instant::now();
+        for _ in 0..SOLVES_PER_SIZE {
+            path_length = solve_astar(&grid, start, exit).map(|path| path.len());
+        }
+        let solve_ms = started.elapsed().as_secs_f64() * 1000.0 / SOLVES_PER_SIZE as f64;
+        BenchmarkResult { size, solve_ms, path_length }
+    }
+}
+
+impl Default for PathBenchmarkScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for PathBenchmarkScene {
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return SceneSwitch::Pop;
+        }
+        if _rl.is_key_pressed(KeyboardKey::KEY_R) {
+            *self = Self::new();
+        }
+        SceneSwitch::None
+    }
+
+    fn update(&mut self, _dt: f32, _data: &mut GameData) -> SceneSwitch {
+        // one size per frame so the results show up as they come in
+        if let Some(&size) = BENCHMARK_SIZES.get(self.results.len()) {
+            let result = self.run(size);
+            self.results.push(result);
+        }
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, _data: &mut GameData) {
+        d.clear_background(Color::BLACK);
+        d.draw_text("A* benchmark", 80, 60, 40, Color::WHITE);
+        d.draw_text(&format!("average of {} solves per maze", SOLVES_PER_SIZE), 80, 110, 20, Color::GRAY);
+
+        d.draw_text("Maze size", 80, 170, 25, Color::LIGHTGRAY);
+        d.draw_text("Solve time", 320, 170, 25, Color::LIGHTGRAY);
+        d.draw_text("Path length", 560, 170, 25, Color::LIGHTGRAY);
+        for (i, result) in self.results.iter().enumerate() {
+            let y = 215 + i as i32 * 38;
+            let path = result.path_length.map_or("no path".to_string(), |length| length.to_string());
+            d.draw_text(&format!("{0}x{0}", result.size), 80, y, 25, Color::WHITE);
+            d.draw_text(&format!("{:.3} ms", result.solve_ms), 320, y, 25, Color::WHITE);
+            d.draw_text(&path, 560, y, 25, Color::WHITE);
+        }
+
+        let status = if self.results.len() < BENCHMARK_SIZES.len() { "Running..." } else { "Done." };
+        d.draw_text(&format!("{} R to run again, Esc to leave.", status), 80, 560, 20, Color::GRAY);
+    }
+}
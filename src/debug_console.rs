@@ -0,0 +1,156 @@
+//! A console for trying things out while playing, opened and closed with F4 or the backtick key.
+//! Each line typed is parsed into a [`ConsoleCommand`] that the maze scene carries out.
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use raylib::prelude::*;
+
+use crate::items::Item;
+
+/// how many lines of output are shown above the input box.
+pub const CONSOLE_LINES: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    /// `warp X Y` or `tp X Y`: move the player to a cell on their floor.
+    Warp(usize, usize),
+    /// `set_health N`
+    SetHealth(i32),
+    /// `give ITEM`, by the item's map name.
+    Give(Item),
+    /// `set_level N`
+    SetLevel(u32),
+    /// `toggle_fog`: hide or show the fog.
+    ToggleFog,
+    /// `kill_enemies`: remove every enemy on the player's floor.
+    KillEnemies,
+    /// `reveal`: uncover the whole floor, as a map fragment would a part of it.
+    Reveal,
+    /// `give coins N`: N more coins to spend.
+    GiveCoins(u32),
+    /// `seed N`: start over on the generated maze of that seed.
+    Seed(u64),
+    /// `regen`: start over on a new generated maze.
+    Regen,
+    /// `heatmap`: write how often each cell has been entered to a CSV file, see
+    /// [`crate::maze_scene::MazeScene::export_visit_heatmap`].
+    Heatmap,
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["warp" | "tp", x, y] => Ok(ConsoleCommand::Warp(number(x)?, number(y)?)),
+            ["set_health", health] => Ok(ConsoleCommand::SetHealth(number(health)?)),
+            ["give", "coins", count] => Ok(ConsoleCommand::GiveCoins(number(count)?)),
+            ["give", name] => Item::from_map_kind(&name.to_lowercase())
+                .map(ConsoleCommand::Give)
+                .ok_or_else(|| format!("no item called {}", name)),
+            ["set_level", level] => Ok(ConsoleCommand::SetLevel(number(level)?)),
+            ["toggle_fog"] => Ok(ConsoleCommand::ToggleFog),
+            ["kill_enemies"] => Ok(ConsoleCommand::KillEnemies),
+            ["reveal"] => Ok(ConsoleCommand::Reveal),
+            ["seed", seed] => Ok(ConsoleCommand::Seed(number(seed)?)),
+            ["regen"] => Ok(ConsoleCommand::Regen),
+            ["heatmap"] => Ok(ConsoleCommand::Heatmap),
+            _ => Err(format!("unknown command: {}", line.trim())),
+        }
+    }
+}
+
+fn number<T: FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("not a number: {}", word))
+}
+
+#[derive(Default)]
+pub struct DebugConsole {
+    pub open: bool,
+    input: String,
+    /// the latest lines of output, oldest first.
+    output: VecDeque<String>,
+}
+
+impl DebugConsole {
+    /// Type into the console. Returns the line typed once Enter is pressed, and Esc closes it.
+    pub fn handle_input(&mut self, rl: &mut RaylibHandle) -> Option<String> {
+        while let Some(c) = rl.get_char_pressed() {
+            if !c.is_control() {
+                self.input.push(c);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.input.pop();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            self.open = false;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) && !self.input.trim().is_empty() {
+            let line = std::mem::take(&mut self.input);
+            self.print(format!("> {}", line));
+            return Some(line);
+        }
+        None
+    }
+
+    /// add a line of output, dropping the oldest once there are more than [`CONSOLE_LINES`].
+    pub fn print(&mut self, line: String) {
+        self.output.push_back(line);
+        while self.output.len() > CONSOLE_LINES {
+            self.output.pop_front();
+        }
+    }
+
+    pub fn draw(&self, d: &mut impl RaylibDraw, screen_width: i32) {
+        if !self.open {
+            return;
+        }
+        let height = (CONSOLE_LINES as i32 + 1) * 24 + 16;
+        d.draw_rectangle(0, 0, screen_width, height, Color::BLACK.alpha(0.8));
+        for (i, line) in self.output.iter().enumerate() {
+            d.draw_text(line, 10, 8 + i as i32 * 24, 20, Color::LIGHTGRAY);
+        }
+        let input_y = 8 + CONSOLE_LINES as i32 * 24;
+        d.draw_text(&format!("] {}_", self.input), 10, input_y, 20, Color::GREEN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_parse_into_commands() {
+        assert_eq!(ConsoleCommand::parse("tp 3 4"), Ok(ConsoleCommand::Warp(3, 4)));
+        assert_eq!(ConsoleCommand::parse("  warp   10 2 "), Ok(ConsoleCommand::Warp(10, 2)));
+        assert_eq!(ConsoleCommand::parse("give coins 5"), Ok(ConsoleCommand::GiveCoins(5)));
+        assert_eq!(ConsoleCommand::parse("give Shield"), Ok(ConsoleCommand::Give(Item::Shield)));
+        assert_eq!(ConsoleCommand::parse("set_health -1"), Ok(ConsoleCommand::SetHealth(-1)));
+        assert_eq!(ConsoleCommand::parse("set_level 7"), Ok(ConsoleCommand::SetLevel(7)));
+        assert_eq!(ConsoleCommand::parse("seed 123"), Ok(ConsoleCommand::Seed(123)));
+        assert_eq!(ConsoleCommand::parse("reveal"), Ok(ConsoleCommand::Reveal));
+        assert_eq!(ConsoleCommand::parse("regen"), Ok(ConsoleCommand::Regen));
+        assert_eq!(ConsoleCommand::parse("toggle_fog"), Ok(ConsoleCommand::ToggleFog));
+        assert_eq!(ConsoleCommand::parse("kill_enemies"), Ok(ConsoleCommand::KillEnemies));
+        assert_eq!(ConsoleCommand::parse("heatmap"), Ok(ConsoleCommand::Heatmap));
+    }
+
+    #[test]
+    fn bad_lines_say_what_is_wrong() {
+        assert_eq!(ConsoleCommand::parse("tp 3 north"), Err("not a number: north".to_string()));
+        assert_eq!(ConsoleCommand::parse("tp -1 2"), Err("not a number: -1".to_string()));
+        assert_eq!(ConsoleCommand::parse("give wand"), Err("no item called wand".to_string()));
+        assert_eq!(ConsoleCommand::parse("tp 3"), Err("unknown command: tp 3".to_string()));
+        assert_eq!(ConsoleCommand::parse("fly"), Err("unknown command: fly".to_string()));
+    }
+
+    #[test]
+    fn the_output_keeps_the_latest_lines() {
+        let mut console = DebugConsole::default();
+        for i in 0..CONSOLE_LINES + 2 {
+            console.print(i.to_string());
+        }
+        assert_eq!(console.output.len(), CONSOLE_LINES);
+        assert_eq!(console.output.front().map(String::as_str), Some("2"));
+    }
+}
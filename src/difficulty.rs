@@ -0,0 +1,228 @@
+//! Dynamic difficulty. The size of the next generated dungeon follows how well the last run went:
+//! quick, direct runs grow the maze and runs that end in death or a lot of wandering shrink it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_data::HealthPolicy;
+use crate::maze_gen::CellWeights;
+use crate::maze_size;
+
+/// seconds per step of the shortest route a run may take and still count as quick.
+pub const PAR_SECONDS_PER_STEP: f32 = 0.6;
+
+// how much one adjustment grows or shrinks the maze, and the limits it stays within
+const SIZE_STEP: usize = 4;
+const MIN_WIDTH: usize = 15;
+const MIN_HEIGHT: usize = 11;
+const MAX_WIDTH: usize = 81;
+const MAX_HEIGHT: usize = 61;
+const _: (usize, usize) = maze_size!(MIN_WIDTH, MIN_HEIGHT);
+const _: (usize, usize) = maze_size!(MAX_WIDTH, MAX_HEIGHT);
+/// how close to the start, in steps along the grid, enemies may not appear unless a map puts them there.
+pub const SPAWN_SAFE_RADIUS: usize = 5;
+/// mazes at least this wide get a minotaur, two size steps up from the starting maze.
+const MINOTAUR_MIN_WIDTH: usize = 49;
+
+/// How hard the game is, picked in the settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// how many times the usual points reaching the exit is worth.
+    pub fn points_multiplier(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 2.0,
+        }
+    }
+
+    /// What happens to health between levels: Easy tops it back up, and on Normal and Hard there
+    /// is no free healing.
+    pub fn health_policy(&self) -> HealthPolicy {
+        match self {
+            Difficulty::Easy => HealthPolicy::Refill,
+            Difficulty::Normal | Difficulty::Hard => HealthPolicy::CarryOver,
+        }
+    }
+}
+
+/// How a finished (or failed) run went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunStats {
+    /// seconds spent in the maze.
+    pub time: f32,
+    /// cells the player moved.
+    pub steps: u32,
+    /// length of the shortest route through the maze.
+    pub par_steps: u32,
+    /// whether the run ended in a game over instead of at the exit.
+    pub died: bool,
+}
+
+impl RunStats {
+    pub fn par_time(&self) -> f32 {
+        self.par_steps as f32 * PAR_SECONDS_PER_STEP
+    }
+
+    /// par steps over steps taken: 1.0 for a perfect route, lower the more the player wandered.
+    pub fn efficiency(&self) -> f32 {
+        if self.steps == 0 {
+            return 1.0;
+        }
+        (self.par_steps as f32 / self.steps as f32).min(1.0)
+    }
+}
+
+/// The parameters of the next generated dungeon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DifficultyParams {
+    pub maze_width: usize,
+    pub maze_height: usize,
+    /// enemies never spawn within this Manhattan distance of the start cell.
+    pub spawn_safe_radius: usize,
+}
+
+impl Default for DifficultyParams {
+    fn default() -> Self {
+        Self {
+            maze_width: 41,
+            maze_height: 29,
+            spawn_safe_radius: SPAWN_SAFE_RADIUS,
+        }
+    }
+}
+
+impl DifficultyParams {
+    /// whether the next dungeon is hard enough to have a minotaur in it.
+    pub fn has_minotaur(&self) -> bool {
+        self.maze_width >= MINOTAUR_MIN_WIDTH
+    }
+
+    /// How the next dungeon's hazards and pickups are weighted: the bigger the maze, the more
+    /// traps and quicksand and the fewer hearts.
+    pub fn cell_weights(&self) -> CellWeights {
+        let hardness = self.maze_width.saturating_sub(MIN_WIDTH) as f32 / (MAX_WIDTH - MIN_WIDTH) as f32;
+        let base = CellWeights::default();
+        CellWeights {
+            trap: base.trap * (1.0 + hardness),
+            quicksand: base.quicksand * (1.0 + hardness / 2.0),
+            heart: base.heart * (1.0 - hardness / 2.0),
+            ..base
+        }
+        .normalised()
+    }
+
+    /// Multiply the width and height of the next maze by `factor`, within the usual limits. Each is
+    /// rounded to the nearest odd number, as [`maze_size!`] wants.
+    pub fn scale_size(&mut self, factor: f32) {
+        let odd = |size: usize| (((size as f32 * factor - 1.0) / 2.0).round().max(0.0) as usize) * 2 + 1;
+        self.maze_width = odd(self.maze_width).clamp(MIN_WIDTH, MAX_WIDTH);
+        self.maze_height = odd(self.maze_height).clamp(MIN_HEIGHT, MAX_HEIGHT);
+    }
+
+    /// Grow the next maze after a run that was well under par, shrink it after one the player
+    /// struggled with, and leave it alone otherwise.
+    pub fn adjust_difficulty(&mut self, last_run: RunStats) {
+        let struggled = last_run.died || last_run.efficiency() < 0.35 || last_run.time > last_run.par_time() * 3.0;
+        let cruised = last_run.efficiency() >= 0.7 && last_run.time <= last_run.par_time();
+
+        if struggled {
+            self.maze_width = self.maze_width.saturating_sub(SIZE_STEP).max(MIN_WIDTH);
+            self.maze_height = self.maze_height.saturating_sub(SIZE_STEP).max(MIN_HEIGHT);
+        } else if cruised {
+            self.maze_width = (self.maze_width + SIZE_STEP).min(MAX_WIDTH);
+            self.maze_height = (self.maze_height + SIZE_STEP).min(MAX_HEIGHT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(time: f32, steps: u32, died: bool) -> RunStats {
+        RunStats { time, steps, par_steps: 50, died }
+    }
+
+    #[test]
+    fn difficulty_round_trips_through_json() {
+        let difficulty = DifficultyParams { maze_width: 45, maze_height: 33, spawn_safe_radius: 4 };
+        let json = serde_json::to_string(&difficulty).unwrap();
+        assert_eq!(serde_json::from_str::<DifficultyParams>(&json).unwrap(), difficulty);
+        for difficulty in Difficulty::ALL {
+            let json = serde_json::to_string(&difficulty).unwrap();
+            assert_eq!(serde_json::from_str::<Difficulty>(&json).unwrap(), difficulty);
+        }
+    }
+
+    #[test]
+    fn easy_refills_health_and_hard_carries_it_over() {
+        assert_eq!(Difficulty::Easy.health_policy(), HealthPolicy::Refill);
+        assert_eq!(Difficulty::Normal.health_policy(), HealthPolicy::CarryOver);
+        assert_eq!(Difficulty::Hard.health_policy(), HealthPolicy::CarryOver);
+    }
+
+    #[test]
+    fn a_quick_clean_run_grows_the_maze() {
+        let mut params = DifficultyParams::default();
+        params.adjust_difficulty(run(20.0, 55, false));
+        assert_eq!((params.maze_width, params.maze_height), (45, 33));
+    }
+
+    #[test]
+    fn dying_or_wandering_shrinks_the_maze() {
+        let mut params = DifficultyParams::default();
+        params.adjust_difficulty(run(20.0, 55, true));
+        assert_eq!((params.maze_width, params.maze_height), (37, 25));
+        params.adjust_difficulty(run(20.0, 500, false));
+        assert_eq!((params.maze_width, params.maze_height), (33, 21));
+    }
+
+    #[test]
+    fn an_ordinary_run_leaves_it_alone() {
+        let mut params = DifficultyParams::default();
+        params.adjust_difficulty(run(45.0, 90, false));
+        assert_eq!(params, DifficultyParams::default());
+    }
+
+    #[test]
+    fn scaling_keeps_the_size_odd() {
+        let mut params = DifficultyParams::default();
+        params.scale_size(0.9);
+        assert_eq!((params.maze_width, params.maze_height), (37, 27));
+        for factor in [0.9, 1.1, 1.5, 0.75, 0.5, 2.0, 1.33] {
+            params.scale_size(factor);
+            assert_eq!(params.maze_width % 2, 1, "width {} after {}", params.maze_width, factor);
+            assert_eq!(params.maze_height % 2, 1, "height {} after {}", params.maze_height, factor);
+        }
+    }
+
+    #[test]
+    fn the_size_stays_within_its_limits() {
+        let mut params = DifficultyParams::default();
+        for _ in 0..50 {
+            params.adjust_difficulty(run(20.0, 55, false));
+        }
+        assert_eq!((params.maze_width, params.maze_height), (MAX_WIDTH, MAX_HEIGHT));
+        for _ in 0..50 {
+            params.adjust_difficulty(run(20.0, 55, true));
+        }
+        assert_eq!((params.maze_width, params.maze_height), (MIN_WIDTH, MIN_HEIGHT));
+    }
+}
@@ -0,0 +1,624 @@
+//! A map editor for making mazes by hand.
+//!
+//! The map is painted a cell at a time with the left mouse button, using the brush picked with Tab
+//! or the number keys. Brushes paint cells (walls, paths, the start, the exit, traps and gates) or
+//! place entities (treasure, items and enemies) on open cells. Before a map can be tried out with T
+//! or exported with E it is validated, and the report is shown beside the map. Exported maps are the
+//! JSON [`MapData`] the game plays, with the cells written out as they are.
+//!
+//! Right-clicking an open cell writes a message on it, shown to the player the first time they step
+//! there. Enter keeps what was typed, and keeping nothing rubs the message out.
+//!
+//! P opens the palette tab in place of the brushes: a swatch for each colour of the map's
+//! [`CustomTheme`], and RGBA sliders for the one clicked. The map is drawn in the palette as it
+//! changes, and an exported map brings it along under its `"theme"` key.
+use raylib::prelude::*;
+
+use crate::game_data::GameData;
+use crate::maze_gen::{ButtonId, CellType, ValidationReport};
+use crate::maze_scene::{save_map, validate_map, MapData, MapEntity, MapMessage, MazeScene};
+use crate::scenes::{Scene, SceneSwitch};
+use crate::theme::CustomTheme;
+
+/// where E writes the map.
+pub const EXPORT_PATH: &str = "assets/maps/custom.json";
+/// the size of a new map, in cells.
+pub const EDITOR_WIDTH: usize = 21;
+pub const EDITOR_HEIGHT: usize = 15;
+
+// the largest a cell is drawn, and the room the map has on screen
+const MAX_CELL: i32 = 40;
+const MAP_LEFT: i32 = 20;
+const MAP_TOP: i32 = 60;
+const MAP_AREA: (i32, i32) = (880, 860);
+// where the side panel starts
+const PANEL_LEFT: i32 = 930;
+// unreachable cells listed in the report before the rest are counted
+const REPORT_CELLS: usize = 5;
+// button and gate ids go from 0 up to this
+const MAX_BUTTON_ID: u8 = 9;
+// the palette's swatches, one under the other, and the sliders under them
+const SWATCH_SIZE: i32 = 30;
+const SWATCH_TOP: i32 = 90;
+const SLIDER_WIDTH: i32 = 256;
+const SLIDER_HEIGHT: i32 = 20;
+const SLIDER_TOP: i32 = SWATCH_TOP + SWATCHES.len() as i32 * (SWATCH_SIZE + 10) + 40;
+
+/// The colours of a palette, in the order their swatches are shown.
+pub const SWATCHES: [&str; 6] = ["Walls", "Floor", "Start", "Exit", "Accent", "HUD"];
+
+// the colour of a palette a swatch stands for
+fn swatch_color(theme: &mut CustomTheme, swatch: usize) -> &mut Color {
+    match swatch {
+        0 => &mut theme.wall_color,
+        1 => &mut theme.floor_color,
+        2 => &mut theme.start_color,
+        3 => &mut theme.exit_color,
+        4 => &mut theme.accent_color,
+        _ => &mut theme.hud_color,
+    }
+}
+
+// the value a slider `width` pixels wide is set to by a click `offset` pixels along it
+fn slider_value(offset: f32, width: i32) -> u8 {
+    (offset / width as f32 * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// What painting a cell does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Brush {
+    /// turns the cell into this. Buttons and gates take the editor's current id.
+    Cell(CellType),
+    /// puts an entity of this map kind on an open cell.
+    Entity(&'static str),
+}
+
+/// The brushes to pick from, in the order Tab goes through them.
+pub const BRUSHES: [(&str, Brush); 14] = [
+    ("Wall", Brush::Cell(CellType::Wall)),
+    ("Path", Brush::Cell(CellType::Path)),
+    ("Start", Brush::Cell(CellType::Start)),
+    ("Exit", Brush::Cell(CellType::Exit)),
+    ("Heart", Brush::Cell(CellType::Heart)),
+    ("Mine", Brush::Cell(CellType::Mine)),
+    ("Lava", Brush::Cell(CellType::Lava)),
+    ("Button", Brush::Cell(CellType::Button(ButtonId(0)))),
+    ("Gate", Brush::Cell(CellType::LinkedWall(ButtonId(0)))),
+    ("Treasure", Brush::Entity("treasure")),
+    ("Shield", Brush::Entity("shield")),
+    ("Tank", Brush::Entity("tank")),
+    ("Shooter", Brush::Entity("shooter")),
+    ("Interceptor", Brush::Entity("interceptor")),
+];
+
+pub struct MazeEditorScene {
+    /// the map being made. Its `cells` are always there.
+    map: MapData,
+    /// the brush in [`BRUSHES`] painting with.
+    brush: usize,
+    /// the id buttons and gates are painted with.
+    button_id: u8,
+    /// what the last validation found, shown until the map changes.
+    report: Option<ValidationReport>,
+    /// how the last test or export went.
+    notice: String,
+    /// whether the palette tab is showing instead of the brushes.
+    palette_open: bool,
+    /// the swatch in [`SWATCHES`] whose sliders are showing.
+    swatch: Option<usize>,
+    /// the cell whose message is being typed, and the text so far.
+    writing: Option<((usize, usize), String)>,
+}
+
+impl MazeEditorScene {
+    /// A new `width` x `height` map: open ground inside a wall, the start in the top left corner and
+    /// the exit in the bottom right.
+    pub fn new(width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(3), height.max(3));
+        let mut map = MapData::blank(width, height, 32);
+        let mut cells = vec![vec![CellType::Wall; width]; height];
+        for row in &mut cells[1..height - 1] {
+            row[1..width - 1].fill(CellType::Path);
+        }
+        cells[1][1] = CellType::Start;
+        cells[height - 2][width - 2] = CellType::Exit;
+        map.cells = Some(cells);
+        Self { map, brush: 0, button_id: 0, report: None, notice: String::new(), palette_open: false, swatch: None, writing: None }
+    }
+
+    pub fn map(&self) -> &MapData {
+        &self.map
+    }
+
+    fn cells(&self) -> &[Vec<CellType>] {
+        self.map.cells.as_deref().unwrap_or_default()
+    }
+
+    /// the brush painting with, with the current id on a button or gate.
+    pub fn brush(&self) -> Brush {
+        match BRUSHES[self.brush].1 {
+            Brush::Cell(CellType::Button(_)) => Brush::Cell(CellType::Button(ButtonId(self.button_id))),
+            Brush::Cell(CellType::LinkedWall(_)) => Brush::Cell(CellType::LinkedWall(ButtonId(self.button_id))),
+            brush => brush,
+        }
+    }
+
+    /// Paint the cell at (x, y) with the current brush. There is only ever one start and one exit,
+    /// so painting either turns the old one into a path. An entity only goes on an open cell, in
+    /// place of any entity already there, and a cell painted shut loses its entity.
+    pub fn paint(&mut self, x: usize, y: usize) {
+        let brush = self.brush();
+        let Some(cells) = self.map.cells.as_mut() else { return };
+        if cells.get(y).and_then(|row| row.get(x)).is_none() {
+            return;
+        }
+        match brush {
+            Brush::Cell(cell) => {
+                if matches!(cell, CellType::Start | CellType::Exit) {
+                    for old in cells.iter_mut().flatten().filter(|c| **c == cell) {
+                        *old = CellType::Path;
+                    }
+                }
+                cells[y][x] = cell;
+                if !cell.is_walkable() {
+                    self.map.entities.retain(|e| (e.x, e.y) != (x, y));
+                    self.map.messages.retain(|m| (m.x, m.y) != (x, y));
+                }
+            }
+            Brush::Entity(kind) => {
+                if !cells[y][x].is_walkable() {
+                    return;
+                }
+                self.map.entities.retain(|e| (e.x, e.y) != (x, y));
+                self.map.entities.push(MapEntity { kind: kind.to_string(), x, y, id: Default::default() });
+            }
+        }
+        self.report = None;
+    }
+
+    /// the message written on the cell at (x, y), if there is one.
+    pub fn message_at(&self, x: usize, y: usize) -> Option<&str> {
+        self.map.messages.iter().find(|m| (m.x, m.y) == (x, y)).map(|m| m.text.as_str())
+    }
+
+    /// Write `text` on the open cell at (x, y) in place of any message already there. Blank text
+    /// rubs the message out. Whether the cell could take it.
+    pub fn set_message(&mut self, x: usize, y: usize, text: &str) -> bool {
+        if !self.cells().get(y).and_then(|row| row.get(x)).is_some_and(|cell| cell.is_walkable()) {
+            return false;
+        }
+        self.map.messages.retain(|m| (m.x, m.y) != (x, y));
+        let text = text.trim();
+        if !text.is_empty() {
+            self.map.messages.push(MapMessage { x, y, text: text.to_string() });
+        }
+        true
+    }
+
+    // Type the message being written. Enter keeps it and Esc leaves the cell as it was.
+    fn handle_writing_input(&mut self, rl: &mut RaylibHandle) {
+        let Some(((x, y), text)) = self.writing.as_mut() else { return };
+        while let Some(c) = rl.get_char_pressed() {
+            if !c.is_control() {
+                text.push(c);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            text.pop();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            let (x, y, text) = (*x, *y, std::mem::take(text));
+            self.set_message(x, y, &text);
+            self.writing = None;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            self.writing = None;
+        }
+    }
+
+    /// Validate the map, keeping the report to show. Whether it can be played.
+    pub fn validate(&mut self) -> bool {
+        let report = validate_map(&self.map, self.cells());
+        let playable = report.is_playable();
+        self.report = Some(report);
+        playable
+    }
+
+    /// Validate the map and, if it can be played, write it to `path`. Whether it was written.
+    pub fn export(&mut self, path: &str) -> bool {
+        if !self.validate() {
+            self.notice = "Fix the errors before exporting.".to_string();
+            return false;
+        }
+        let written = std::path::Path::new(path)
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| save_map(path, &self.map));
+        self.notice = match &written {
+            Ok(()) => format!("Exported to {}", path),
+            Err(e) => format!("Could not write {}: {}", path, e),
+        };
+        written.is_ok()
+    }
+
+    // Validate the map and, if it can be played, play it from a file in the temp folder.
+    fn test_play(&mut self) -> SceneSwitch {
+        if !self.validate() {
+            self.notice = "Fix the errors before testing.".to_string();
+            return SceneSwitch::None;
+        }
+        let path = std::env::temp_dir().join("dungeon_diver_editor_test.json");
+        let path = path.to_string_lossy().into_owned();
+        match save_map(&path, &self.map) {
+            Ok(()) => {
+                self.notice.clear();
+                SceneSwitch::Push(Box::new(MazeScene::from_map(path)))
+            }
+            Err(e) => {
+                self.notice = format!("Could not write {}: {}", path, e);
+                SceneSwitch::None
+            }
+        }
+    }
+
+    /// Set one channel (0 to 3 for red, green, blue and alpha) of a palette colour. A map without a
+    /// palette gets one that looks like `theme` first.
+    pub fn set_channel(&mut self, swatch: usize, channel: usize, value: u8, theme: &crate::theme::Theme) {
+        let palette = self.map.theme.get_or_insert_with(|| CustomTheme::from_theme(theme));
+        let color = swatch_color(palette, swatch);
+        match channel {
+            0 => color.r = value,
+            1 => color.g = value,
+            2 => color.b = value,
+            _ => color.a = value,
+        }
+    }
+
+    // Click a swatch to show its sliders, and drag along a slider to set it.
+    fn handle_palette_input(&mut self, rl: &RaylibHandle, data: &GameData) {
+        let mouse = rl.get_mouse_position();
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+            && let Some(swatch) = (0..SWATCHES.len()).find(|&i| Self::swatch_rect(i).check_collision_point_rec(mouse))
+        {
+            self.swatch = Some(swatch);
+        }
+        let Some(swatch) = self.swatch else { return };
+        if !rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            return;
+        }
+        for channel in 0..4 {
+            let slider = Self::slider_rect(channel);
+            if slider.check_collision_point_rec(mouse) {
+                self.set_channel(swatch, channel, slider_value(mouse.x - slider.x, SLIDER_WIDTH), &data.settings.theme);
+            }
+        }
+    }
+
+    fn swatch_rect(swatch: usize) -> Rectangle {
+        Rectangle::new(PANEL_LEFT as f32, (SWATCH_TOP + swatch as i32 * (SWATCH_SIZE + 10)) as f32, SWATCH_SIZE as f32, SWATCH_SIZE as f32)
+    }
+
+    fn slider_rect(channel: usize) -> Rectangle {
+        Rectangle::new((PANEL_LEFT + 30) as f32, (SLIDER_TOP + channel as i32 * (SLIDER_HEIGHT + 15)) as f32, SLIDER_WIDTH as f32, SLIDER_HEIGHT as f32)
+    }
+
+    // The swatches, the sliders of the one picked filled in proportion to each channel, and a strip
+    // of HUD bar in its colour.
+    fn draw_palette(&self, d: &mut impl RaylibDraw, data: &GameData) {
+        let mut palette = self.map.theme.unwrap_or_else(|| CustomTheme::from_theme(&data.settings.theme));
+        d.draw_text("Palette (P to close)", PANEL_LEFT, 60, 20, Color::BLACK);
+        for (i, label) in SWATCHES.iter().enumerate() {
+            let rect = Self::swatch_rect(i);
+            let color = *swatch_color(&mut palette, i);
+            d.draw_rectangle_rec(rect, color);
+            let outline = if self.swatch == Some(i) { Color::BLACK } else { Color::LIGHTGRAY };
+            d.draw_rectangle_lines_ex(rect, 2.0, outline);
+            d.draw_text(label, PANEL_LEFT + SWATCH_SIZE + 10, rect.y as i32 + 5, 20, Color::DARKGRAY);
+        }
+
+        if let Some(swatch) = self.swatch {
+            let color = *swatch_color(&mut palette, swatch);
+            let channels = [("R", color.r, Color::RED), ("G", color.g, Color::GREEN), ("B", color.b, Color::BLUE), ("A", color.a, Color::DARKGRAY)];
+            for (i, (name, value, fill)) in channels.into_iter().enumerate() {
+                let slider = Self::slider_rect(i);
+                d.draw_text(name, PANEL_LEFT, slider.y as i32, 20, Color::BLACK);
+                d.draw_rectangle_rec(slider, Color::LIGHTGRAY);
+                d.draw_rectangle(slider.x as i32, slider.y as i32, value as i32 * SLIDER_WIDTH / 255, SLIDER_HEIGHT, fill);
+                d.draw_text(&value.to_string(), (slider.x + slider.width) as i32 + 10, slider.y as i32, 20, Color::BLACK);
+            }
+        }
+
+        let hud_top = SLIDER_TOP + 4 * (SLIDER_HEIGHT + 15) + 20;
+        d.draw_rectangle(PANEL_LEFT, hud_top, 320, 30, palette.hud_color);
+        d.draw_text("HUD", PANEL_LEFT + 10, hud_top + 5, 20, Color::WHITE);
+    }
+
+    // how big a cell is drawn: as big as fits, up to MAX_CELL
+    fn cell_size(&self) -> i32 {
+        let (width, height) = (self.map.grid_w.max(1) as i32, self.map.grid_h.max(1) as i32);
+        (MAP_AREA.0 / width).min(MAP_AREA.1 / height).clamp(1, MAX_CELL)
+    }
+
+    // the cell under a point on the screen
+    fn cell_at(&self, point: Vector2) -> Option<(usize, usize)> {
+        let size = self.cell_size() as f32;
+        let (x, y) = ((point.x - MAP_LEFT as f32) / size, (point.y - MAP_TOP as f32) / size);
+        (x >= 0.0 && y >= 0.0 && (x as usize) < self.map.grid_w && (y as usize) < self.map.grid_h).then_some((x as usize, y as usize))
+    }
+
+    // the colour a cell is drawn in, from the map's palette where it has one
+    fn cell_color(&self, cell: CellType, data: &GameData) -> Color {
+        let palette = self.map.theme;
+        match cell {
+            CellType::Wall => palette.map_or(data.settings.theme.wall, |p| p.wall_color),
+            CellType::Start => palette.map_or(Color::GREEN, |p| p.start_color),
+            CellType::Exit => palette.map_or(Color::GOLD, |p| p.exit_color),
+            CellType::Heart => Color::PINK,
+            CellType::Mine => Color::DARKGRAY,
+            CellType::Lava => Color::ORANGE,
+            CellType::Button(_) => Color::SKYBLUE,
+            CellType::LinkedWall(_) => Color::DARKBLUE,
+            _ => palette.map_or(data.settings.theme.background, |p| p.floor_color),
+        }
+    }
+
+    fn draw_map(&self, d: &mut impl RaylibDraw, data: &GameData) {
+        let size = self.cell_size();
+        let lines = self.map.theme.map_or(Color::LIGHTGRAY, |p| p.accent_color);
+        for (y, row) in self.cells().iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let (left, top) = (MAP_LEFT + x as i32 * size, MAP_TOP + y as i32 * size);
+                d.draw_rectangle(left, top, size, size, self.cell_color(*cell, data));
+                d.draw_rectangle_lines(left, top, size, size, lines);
+                if let CellType::Button(ButtonId(id)) | CellType::LinkedWall(ButtonId(id)) = cell {
+                    d.draw_text(&id.to_string(), left + size / 3, top + size / 5, size / 2, Color::WHITE);
+                }
+            }
+        }
+        for entity in &self.map.entities {
+            let center = Vector2::new((MAP_LEFT + entity.x as i32 * size + size / 2) as f32, (MAP_TOP + entity.y as i32 * size + size / 2) as f32);
+            let color = if entity.kind == "treasure" { Color::GOLD } else if entity.kind == "shield" { Color::BLUE } else { Color::RED };
+            d.draw_circle_v(center, size as f32 * 0.3, color);
+        }
+        for message in &self.map.messages {
+            let (left, top) = (MAP_LEFT + message.x as i32 * size, MAP_TOP + message.y as i32 * size);
+            d.draw_text("?", left + size * 2 / 3, top, size / 2, Color::PURPLE);
+        }
+    }
+
+    fn draw_panel(&self, d: &mut impl RaylibDraw) {
+        d.draw_text("Brushes (Tab, 1-9)", PANEL_LEFT, 60, 20, Color::BLACK);
+        for (i, (label, _)) in BRUSHES.iter().enumerate() {
+            let color = if i == self.brush { Color::DARKGREEN } else { Color::GRAY };
+            d.draw_text(label, PANEL_LEFT + 10, 90 + i as i32 * 22, 20, color);
+        }
+        let ids_top = 100 + BRUSHES.len() as i32 * 22;
+        d.draw_text(&format!("Button/gate id: {} ([ and ])", self.button_id), PANEL_LEFT, ids_top, 20, Color::BLACK);
+        d.draw_text("V validate, T test, E export, P palette", PANEL_LEFT, ids_top + 30, 20, Color::GRAY);
+        d.draw_text("Right-click a cell to write a message", PANEL_LEFT, ids_top + 55, 20, Color::GRAY);
+        d.draw_text("Esc back to the menu", PANEL_LEFT, ids_top + 80, 20, Color::GRAY);
+
+        let mut y = ids_top + 125;
+        if let Some(((x, cell_y), text)) = &self.writing {
+            d.draw_text(&format!("Message at {} {} (Enter to keep, Esc to cancel)", x, cell_y), PANEL_LEFT, y, 15, Color::BLACK);
+            d.draw_rectangle_lines(PANEL_LEFT, y + 20, 450, 30, Color::DARKGRAY);
+            d.draw_text(&format!("{}_", text), PANEL_LEFT + 5, y + 25, 20, Color::BLACK);
+            y += 65;
+        }
+        if let Some(report) = &self.report {
+            let lines = report.lines(REPORT_CELLS);
+            if lines.is_empty() {
+                d.draw_text("No problems found.", PANEL_LEFT, y, 20, Color::DARKGREEN);
+                y += 25;
+            }
+            for line in lines {
+                let color = if report.is_playable() { Color::ORANGE } else { Color::RED };
+                d.draw_text(&line, PANEL_LEFT, y, 15, color);
+                y += 20;
+            }
+        }
+        d.draw_text(&self.notice, PANEL_LEFT, y + 10, 15, Color::DARKGRAY);
+    }
+}
+
+impl Scene for MazeEditorScene {
+    fn handle_input(&mut self, rl: &mut RaylibHandle, data: &mut GameData) -> SceneSwitch {
+        // a message being written takes all of the keyboard
+        if self.writing.is_some() {
+            self.handle_writing_input(rl);
+            return SceneSwitch::None;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            self.palette_open = !self.palette_open;
+            self.swatch = None;
+        }
+        if self.palette_open {
+            if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                self.palette_open = false;
+            }
+            self.handle_palette_input(rl, data);
+            return SceneSwitch::None;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return SceneSwitch::Pop;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_TAB) {
+            self.brush = (self.brush + 1) % BRUSHES.len();
+        }
+        let number_keys = [
+            KeyboardKey::KEY_ONE,
+            KeyboardKey::KEY_TWO,
+            KeyboardKey::KEY_THREE,
+            KeyboardKey::KEY_FOUR,
+            KeyboardKey::KEY_FIVE,
+            KeyboardKey::KEY_SIX,
+            KeyboardKey::KEY_SEVEN,
+            KeyboardKey::KEY_EIGHT,
+            KeyboardKey::KEY_NINE,
+        ];
+        if let Some(i) = number_keys.iter().position(|key| rl.is_key_pressed(*key)) {
+            self.brush = i;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_LEFT_BRACKET) {
+            self.button_id = self.button_id.saturating_sub(1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT_BRACKET) {
+            self.button_id = (self.button_id + 1).min(MAX_BUTTON_ID);
+        }
+        if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT)
+            && let Some((x, y)) = self.cell_at(rl.get_mouse_position())
+        {
+            self.paint(x, y);
+        }
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT)
+            && let Some((x, y)) = self.cell_at(rl.get_mouse_position())
+            && self.cells()[y][x].is_walkable()
+        {
+            let text = self.message_at(x, y).unwrap_or_default().to_string();
+            self.writing = Some(((x, y), text));
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_V) {
+            self.validate();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_E) {
+            self.export(EXPORT_PATH);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_T) {
+            return self.test_play();
+        }
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.clear_background(Color::RAYWHITE);
+        d.draw_text("Map Editor", MAP_LEFT, 15, 30, Color::BLACK);
+        self.draw_map(d, data);
+        if self.palette_open {
+            self.draw_palette(d, data);
+        } else {
+            self.draw_panel(d);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_gen::{ValidationError, ValidationWarning};
+    use crate::maze_scene::load_map;
+    use crate::theme::Theme;
+
+    fn pick(editor: &mut MazeEditorScene, label: &str) {
+        editor.brush = BRUSHES.iter().position(|(l, _)| *l == label).unwrap();
+    }
+
+    #[test]
+    fn there_is_only_ever_one_start() {
+        let mut editor = MazeEditorScene::new(7, 5);
+        pick(&mut editor, "Start");
+        editor.paint(3, 2);
+        let starts: Vec<(usize, usize)> = (0..5).flat_map(|y| (0..7).map(move |x| (x, y))).filter(|&(x, y)| editor.cells()[y][x] == CellType::Start).collect();
+        assert_eq!(starts, vec![(3, 2)]);
+        assert_eq!(editor.cells()[1][1], CellType::Path);
+    }
+
+    #[test]
+    fn entities_only_stand_on_open_cells() {
+        let mut editor = MazeEditorScene::new(7, 5);
+        pick(&mut editor, "Treasure");
+        editor.paint(0, 0);
+        assert!(editor.map.entities.is_empty());
+        editor.paint(2, 2);
+        pick(&mut editor, "Tank");
+        editor.paint(2, 2);
+        assert_eq!(editor.map.entities.iter().map(|e| (e.kind.as_str(), e.x, e.y)).collect::<Vec<_>>(), vec![("tank", 2, 2)]);
+
+        // walling the cell up takes the tank with it
+        pick(&mut editor, "Wall");
+        editor.paint(2, 2);
+        assert!(editor.map.entities.is_empty());
+    }
+
+    #[test]
+    fn buttons_and_gates_take_the_current_id() {
+        let mut editor = MazeEditorScene::new(7, 5);
+        editor.button_id = 3;
+        pick(&mut editor, "Gate");
+        editor.paint(4, 1);
+        assert_eq!(editor.cells()[1][4], CellType::LinkedWall(ButtonId(3)));
+    }
+
+    #[test]
+    fn a_map_that_cannot_be_played_is_not_exported() {
+        let path = std::env::temp_dir().join("dungeon_diver_editor_export.json");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        // wall the start in
+        let mut editor = MazeEditorScene::new(7, 5);
+        pick(&mut editor, "Wall");
+        editor.paint(2, 1);
+        editor.paint(1, 2);
+        assert!(!editor.export(path));
+        assert!(!std::path::Path::new(path).exists());
+        let report = editor.report.clone().unwrap();
+        assert_eq!(report.errors, vec![ValidationError::Unsolvable]);
+        assert!(report.warnings.contains(&ValidationWarning::UnreachableCell(5, 3)));
+
+        pick(&mut editor, "Path");
+        editor.paint(2, 1);
+        assert!(editor.report.is_none());
+        assert!(editor.export(path));
+        assert_eq!(&load_map(path), editor.map());
+    }
+
+    #[test]
+    fn the_sliders_set_the_palette_that_is_exported() {
+        let mut editor = MazeEditorScene::new(7, 5);
+        assert!(editor.map.theme.is_none());
+        editor.set_channel(0, 0, 200, &Theme::DUNGEON);
+        editor.set_channel(SWATCHES.len() - 1, 3, slider_value(SLIDER_WIDTH as f32 / 2.0, SLIDER_WIDTH), &Theme::DUNGEON);
+        let palette = editor.map.theme.unwrap();
+        assert_eq!(palette.wall_color.r, 200);
+        assert_eq!((palette.wall_color.g, palette.wall_color.b), (Theme::DUNGEON.wall.g, Theme::DUNGEON.wall.b));
+        assert_eq!(palette.hud_color.a, 128);
+        assert_eq!(palette.floor_color.r, Theme::DUNGEON.background.r);
+
+        let path = std::env::temp_dir().join("dungeon_diver_editor_palette.json");
+        let path = path.to_str().unwrap();
+        assert!(editor.export(path));
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(json["theme"]["wall_color"][0], 200);
+        assert_eq!(load_map(path).theme, Some(palette));
+    }
+
+    #[test]
+    fn sliders_run_from_nothing_to_full() {
+        assert_eq!(slider_value(0.0, SLIDER_WIDTH), 0);
+        assert_eq!(slider_value(-5.0, SLIDER_WIDTH), 0);
+        assert_eq!(slider_value(SLIDER_WIDTH as f32, SLIDER_WIDTH), 255);
+        assert_eq!(slider_value(SLIDER_WIDTH as f32 + 9.0, SLIDER_WIDTH), 255);
+    }
+
+    #[test]
+    fn messages_are_written_on_open_cells_and_exported() {
+        let mut editor = MazeEditorScene::new(7, 5);
+        assert!(!editor.set_message(0, 0, "In the wall"));
+        assert!(editor.set_message(2, 2, "First"));
+        assert!(editor.set_message(2, 2, "  Second  "));
+        assert!(editor.set_message(3, 2, "Third"));
+        assert_eq!(editor.message_at(2, 2), Some("Second"));
+        assert!(editor.set_message(3, 2, " "));
+        assert_eq!(editor.message_at(3, 2), None);
+
+        let path = std::env::temp_dir().join("dungeon_diver_editor_messages.json");
+        let path = path.to_str().unwrap();
+        assert!(editor.export(path));
+        assert_eq!(load_map(path).messages, vec![MapMessage { x: 2, y: 2, text: "Second".to_string() }]);
+
+        // walling a cell in rubs its message out
+        pick(&mut editor, "Wall");
+        editor.paint(2, 2);
+        assert!(editor.map().messages.is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+//! The monsters that roam the maze.
+//!
+//! Enemies live on the grid like the player does and step from cell to cell on a timer.
+//! Tanks hurt the player by walking into them, shooters fire projectiles along their row or column.
+//! Interceptors head for the way out to cut the player off, see [`crate::pathfinding::intercept_step`].
+//! The minotaur is a boss that walks a patrol loop until it sees the player, then runs them down.
+
+use crate::entity::EntityId;
+
+/// The kinds of monster a map can place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnemyKind {
+    Tank,
+    Shooter,
+    /// moves to block the player's way to the exit instead of wandering.
+    Interceptor,
+    Minotaur,
+}
+
+impl EnemyKind {
+    /// the enemy a map entity spawns, if it is an enemy at all.
+    pub fn from_map_kind(kind: &str) -> Option<EnemyKind> {
+        match kind {
+            "tank" => Some(EnemyKind::Tank),
+            "shooter" => Some(EnemyKind::Shooter),
+            "interceptor" => Some(EnemyKind::Interceptor),
+            "minotaur" => Some(EnemyKind::Minotaur),
+            _ => None,
+        }
+    }
+
+    /// seconds between steps. A chasing minotaur goes faster, see [`MinotaurState::move_interval`].
+    pub fn move_interval(&self) -> f32 {
+        match self {
+            EnemyKind::Tank => 0.8,
+            EnemyKind::Shooter => 1.2,
+            EnemyKind::Interceptor => 0.9,
+            EnemyKind::Minotaur => 0.7,
+        }
+    }
+
+    pub fn max_health(&self) -> i32 {
+        match self {
+            EnemyKind::Tank => 60,
+            EnemyKind::Shooter => 30,
+            EnemyKind::Interceptor => 40,
+            EnemyKind::Minotaur => 150,
+        }
+    }
+
+    /// damage dealt by touching the player.
+    pub fn contact_damage(&self) -> i32 {
+        match self {
+            EnemyKind::Tank => 20,
+            EnemyKind::Shooter => 10,
+            EnemyKind::Interceptor => 15,
+            EnemyKind::Minotaur => 35,
+        }
+    }
+}
+
+pub struct Enemy {
+    /// the default id until the maze registers the enemy.
+    pub id: EntityId,
+    pub kind: EnemyKind,
+    pub x: usize,
+    pub y: usize,
+    pub health: i32,
+    /// counts up to the kind's move interval, then the enemy takes a step.
+    pub move_timer: f32,
+    /// counts up to [`Enemy::FIRE_INTERVAL`] for shooters.
+    pub fire_timer: f32,
+    /// what a minotaur is doing. `None` for every other kind.
+    pub minotaur: Option<Minotaur>,
+    /// whether the enemy has noticed the player: a minotaur on the chase, or a shooter that has
+    /// fired at them. A minotaur forgets again once it gives up the chase.
+    pub alerted: bool,
+}
+
+impl Enemy {
+    /// seconds a shooter waits between shots.
+    pub const FIRE_INTERVAL: f32 = 2.0;
+
+    pub fn new(kind: EnemyKind, x: usize, y: usize) -> Self {
+        Self {
+            id: EntityId::default(),
+            kind,
+            x,
+            y,
+            health: kind.max_health(),
+            move_timer: 0.0,
+            fire_timer: 0.0,
+            minotaur: None,
+            alerted: false,
+        }
+    }
+
+    /// a minotaur walking `patrol` over and over, starting on its first cell.
+    pub fn minotaur(patrol: Vec<(usize, usize)>) -> Self {
+        let (x, y) = patrol.first().copied().unwrap_or((0, 0));
+        Self {
+            minotaur: Some(Minotaur::new(patrol)),
+            ..Self::new(EnemyKind::Minotaur, x, y)
+        }
+    }
+}
+
+/// seconds a minotaur that lost sight of the player keeps searching before it goes back to its patrol.
+pub const MINOTAUR_SEARCH_TIME: f32 = 5.0;
+/// seconds between a chasing minotaur's steps, faster than any other enemy.
+pub const MINOTAUR_CHASE_INTERVAL: f32 = 0.35;
+
+/// What a minotaur is doing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MinotaurState {
+    /// walking its patrol loop, heading for `path[idx]`.
+    Patrol { path: Vec<(usize, usize)>, idx: usize },
+    /// the player is in sight and the minotaur runs at them.
+    Chase,
+    /// the player got away. The minotaur goes to where it last saw them and waits there until
+    /// `timer` runs out.
+    Search { timer: f32 },
+}
+
+impl MinotaurState {
+    /// The state after one frame of `dt` seconds, given whether the player is in sight. A search that
+    /// runs out returns to `patrol` at the loop cell nearest `position`.
+    pub fn next(self, sees_player: bool, dt: f32, patrol: &[(usize, usize)], position: (usize, usize)) -> MinotaurState {
+        if sees_player {
+            return MinotaurState::Chase;
+        }
+        match self {
+            MinotaurState::Chase => MinotaurState::Search { timer: MINOTAUR_SEARCH_TIME },
+            MinotaurState::Search { timer } if timer - dt > 0.0 => MinotaurState::Search { timer: timer - dt },
+            MinotaurState::Search { .. } => MinotaurState::Patrol {
+                path: patrol.to_vec(),
+                idx: nearest_index(patrol, position),
+            },
+            MinotaurState::Patrol { path, idx } => {
+                // reaching the cell it was heading for moves it on to the next one around the loop
+                let idx = if path.get(idx) == Some(&position) { (idx + 1) % path.len() } else { idx };
+                MinotaurState::Patrol { path, idx }
+            }
+        }
+    }
+
+    /// seconds between steps in this state.
+    pub fn move_interval(&self) -> f32 {
+        match self {
+            MinotaurState::Chase => MINOTAUR_CHASE_INTERVAL,
+            _ => EnemyKind::Minotaur.move_interval(),
+        }
+    }
+}
+
+/// A minotaur's patrol loop and what it is doing on it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Minotaur {
+    /// the cells walked in order while patrolling, wrapping around from the last to the first.
+    pub patrol: Vec<(usize, usize)>,
+    pub state: MinotaurState,
+    /// where the player was when the minotaur last saw them.
+    pub last_seen: Option<(usize, usize)>,
+}
+
+impl Minotaur {
+    pub fn new(patrol: Vec<(usize, usize)>) -> Self {
+        Self {
+            state: MinotaurState::Patrol { path: patrol.clone(), idx: 0 },
+            patrol,
+            last_seen: None,
+        }
+    }
+
+    /// Update the state for a frame of `dt` seconds. `player` is the player's cell if the minotaur
+    /// standing at `position` can see them.
+    pub fn observe(&mut self, position: (usize, usize), player: Option<(usize, usize)>, dt: f32) {
+        if player.is_some() {
+            self.last_seen = player;
+        }
+        let state = std::mem::replace(&mut self.state, MinotaurState::Chase);
+        self.state = state.next(player.is_some(), dt, &self.patrol, position);
+    }
+
+    /// the cell the minotaur is heading for, if it has anywhere to go.
+    pub fn target(&self) -> Option<(usize, usize)> {
+        match &self.state {
+            MinotaurState::Patrol { path, idx } => path.get(*idx).copied(),
+            MinotaurState::Chase | MinotaurState::Search { .. } => self.last_seen,
+        }
+    }
+}
+
+// the index of the cell in `path` closest to `position`, as the crow flies
+fn nearest_index(path: &[(usize, usize)], position: (usize, usize)) -> usize {
+    path.iter()
+        .enumerate()
+        .min_by_key(|(_, (x, y))| x.abs_diff(position.0) + y.abs_diff(position.1))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOP: [(usize, usize); 4] = [(1, 1), (3, 1), (3, 3), (1, 3)];
+
+    #[test]
+    fn a_patrol_moves_on_around_its_loop() {
+        let mut minotaur = Minotaur::new(LOOP.to_vec());
+        minotaur.observe((1, 1), None, 0.1);
+        assert_eq!(minotaur.target(), Some((3, 1)));
+        minotaur.observe((2, 1), None, 0.1);
+        assert_eq!(minotaur.target(), Some((3, 1)));
+        for &cell in &LOOP[1..] {
+            minotaur.observe(cell, None, 0.1);
+        }
+        assert_eq!(minotaur.target(), Some((1, 1)));
+    }
+
+    #[test]
+    fn seeing_the_player_starts_a_chase_and_losing_them_a_search() {
+        let mut minotaur = Minotaur::new(LOOP.to_vec());
+        minotaur.observe((1, 1), Some((5, 1)), 0.1);
+        assert_eq!(minotaur.state, MinotaurState::Chase);
+        assert_eq!(minotaur.target(), Some((5, 1)));
+        minotaur.observe((2, 1), Some((5, 2)), 0.1);
+        assert_eq!(minotaur.state, MinotaurState::Chase);
+
+        minotaur.observe((3, 1), None, 0.1);
+        assert_eq!(minotaur.state, MinotaurState::Search { timer: MINOTAUR_SEARCH_TIME });
+        assert_eq!(minotaur.target(), Some((5, 2)));
+
+        // spotting them again mid-search goes straight back to the chase
+        minotaur.observe((4, 1), Some((5, 3)), 1.0);
+        assert_eq!(minotaur.state, MinotaurState::Chase);
+    }
+
+    #[test]
+    fn a_search_that_runs_out_returns_to_the_nearest_patrol_cell() {
+        let mut minotaur = Minotaur::new(LOOP.to_vec());
+        minotaur.observe((3, 2), Some((5, 3)), 0.1);
+        minotaur.observe((3, 3), None, 0.1);
+        for _ in 0..4 {
+            minotaur.observe((4, 3), None, 1.0);
+            assert!(matches!(minotaur.state, MinotaurState::Search { .. }));
+        }
+        minotaur.observe((4, 3), None, 1.0);
+        assert_eq!(minotaur.state, MinotaurState::Patrol { path: LOOP.to_vec(), idx: 2 });
+    }
+
+    #[test]
+    fn only_a_chase_speeds_it_up() {
+        assert_eq!(MinotaurState::Chase.move_interval(), MINOTAUR_CHASE_INTERVAL);
+        assert_eq!(MinotaurState::Search { timer: 1.0 }.move_interval(), EnemyKind::Minotaur.move_interval());
+    }
+}
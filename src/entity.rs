@@ -0,0 +1,83 @@
+//! Stable identifiers for the things that come and go in a maze.
+//!
+//! Enemies, projectiles and collectibles each live in their own `Vec` and move around in it as
+//! others are removed. An [`EntityId`] keeps naming the same one, and the [`EntityRegistry`]
+//! knows where it currently is.
+use std::collections::HashMap;
+
+/// Names one enemy, projectile or collectible for as long as it exists. The default id is never
+/// handed out by a registry, so it marks something that has not been registered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EntityId(u32);
+
+/// Where a registered entity is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityKind {
+    /// an index into the enemies on the player's floor.
+    Enemy(usize),
+    /// an index into the enemies waiting on the other floor.
+    FloorEnemy(usize),
+    Projectile(usize),
+    /// an index into the map's entities, for a treasure or item lying in the maze.
+    Collectible(usize),
+}
+
+pub struct EntityRegistry {
+    next_id: u32,
+    entities: HashMap<EntityId, EntityKind>,
+}
+
+impl Default for EntityRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntityRegistry {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            entities: HashMap::new(),
+        }
+    }
+
+    /// register a new entity stored at `kind`, returning its id.
+    pub fn create(&mut self, kind: EntityKind) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, kind);
+        id
+    }
+
+    /// forget an entity that has been removed. Returns where it was stored, if it was registered.
+    pub fn destroy(&mut self, id: EntityId) -> Option<EntityKind> {
+        self.entities.remove(&id)
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<EntityKind> {
+        self.entities.get(&id).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// forget every entity. Ids already handed out are not reused.
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+
+    /// Point each registered id in `ids` at its position in the list, stored as `kind`. Called with
+    /// the ids of a whole `Vec` in order after entities in it were removed or moved around.
+    pub fn reindex(&mut self, ids: impl IntoIterator<Item = EntityId>, kind: fn(usize) -> EntityKind) {
+        for (index, id) in ids.into_iter().enumerate() {
+            if let Some(stored) = self.entities.get_mut(&id) {
+                *stored = kind(index);
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Rare scripted events that can shake up a run mid-maze.
+//!
+//! Every minute spent in a maze the scene rolls once against the combined probability of all
+//! its events. When the roll hits, one event is picked (weighted by its own probability) and its
+//! effect is applied to the whole maze.
+use std::time::Duration;
+
+/// seconds of play between two rolls for a random event.
+pub const EVENT_ROLL_INTERVAL: f32 = 60.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventType {
+    /// a pack of tanks appears around the maze.
+    EnemyRushWave,
+    /// every enemy drops a treasure on the cell it stands on.
+    TreasureDropAll,
+    /// the player can see nothing but their own cell for a while.
+    LightsOut(Duration),
+    /// enemies and projectiles move twice as fast for a while.
+    SpeedBoostAll,
+    /// enemies stop moving and firing for a while.
+    FreezeEnemies(Duration),
+}
+
+impl EventType {
+    /// the text shown in the banner when the event fires.
+    pub fn announcement(&self) -> &'static str {
+        match self {
+            EventType::EnemyRushWave => "Enemy rush! More monsters have appeared.",
+            EventType::TreasureDropAll => "Treasure! Every monster dropped its loot.",
+            EventType::LightsOut(_) => "Lights out!",
+            EventType::SpeedBoostAll => "Everything speeds up!",
+            EventType::FreezeEnemies(_) => "The monsters are frozen in place.",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RandomEvent {
+    /// chance of this event firing on any one roll, between 0 and 1.
+    pub trigger_probability_per_minute: f32,
+    pub event_type: EventType,
+}
+
+impl RandomEvent {
+    /// the events every maze can roll for.
+    pub fn defaults() -> Vec<RandomEvent> {
+        vec![
+            RandomEvent { trigger_probability_per_minute: 0.10, event_type: EventType::EnemyRushWave },
+            RandomEvent { trigger_probability_per_minute: 0.10, event_type: EventType::TreasureDropAll },
+            RandomEvent { trigger_probability_per_minute: 0.10, event_type: EventType::LightsOut(Duration::from_secs(10)) },
+            RandomEvent { trigger_probability_per_minute: 0.05, event_type: EventType::SpeedBoostAll },
+            RandomEvent { trigger_probability_per_minute: 0.05, event_type: EventType::FreezeEnemies(Duration::from_secs(8)) },
+        ]
+    }
+}
+
+/// Pick the event a roll in `0.0..1.0` lands on. Each event owns a slice of the range as wide as its
+/// probability, so a roll past the sum of all probabilities fires nothing.
+pub fn pick_event(events: &[RandomEvent], roll: f32) -> Option<EventType> {
+    let mut threshold = 0.0;
+    for event in events {
+        threshold += event.trigger_probability_per_minute;
+        if roll < threshold {
+            return Some(event.event_type);
+        }
+    }
+    None
+}
@@ -3,10 +3,165 @@
 //! You could also store data associated with each human player here.
 //! We could also store the player's gamepad_id here.
 
+use std::collections::HashMap;
+
 use raylib::prelude::*;
 
+use crate::audio::WallCues;
+use crate::debug_log;
+use crate::difficulty::{DifficultyParams, RunStats};
+use crate::items::Item;
+use crate::rendering::PostProcessEffect;
+use crate::save::{SaveSlot, SAVE_PATH};
+use crate::settings::Settings;
+use crate::utils::PerformanceMonitor;
+
+/// points for finishing a level on Normal, before the win streak multiplier is applied.
+pub const LEVEL_BONUS: u32 = 100;
+/// points at the exit for each second under par time, and for leaving no treasure behind.
+pub const TIME_BONUS_PER_SECOND: f32 = 2.0;
+pub const ALL_COINS_BONUS: u32 = 50;
+/// health that one heart in the HUD stands for.
+pub const HEALTH_PER_HEART: i32 = 20;
+/// points per level for finishing it without stepping on a cell twice, before the win streak multiplier.
+pub const NO_BACKTRACK_BONUS: u32 = 500;
+/// seconds on the clock at the start of a time trial.
+pub const TIME_TRIAL_DURATION: f32 = 180.0;
+/// below this average frame rate the next maze is made smaller and the effects are turned off.
+pub const LOW_FPS: f32 = 30.0;
+/// above this average frame rate the player is offered a bigger maze.
+pub const HIGH_FPS: f32 = 90.0;
+/// how much the maze grows or shrinks for performance.
+pub const PERFORMANCE_SCALE_STEP: f32 = 0.1;
+/// how many steps a run may take, as a multiple of par, and still earn the par star.
+pub const PAR_STAR_ALLOWANCE: f32 = 1.25;
+
+/// levels in a row to finish without alerting an enemy for the Perfect Stealth achievement.
+pub const PERFECT_STEALTH_LEVELS: u32 = 5;
+
+/// How quietly a maze was finished, from how many enemies noticed the player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StealthRank {
+    S,
+    A,
+    B,
+    C,
+}
+
+impl StealthRank {
+    /// S with no enemy alerted, A for up to two, B for up to five and C for more.
+    pub fn from_alerts(enemies_alerted: u32) -> Self {
+        match enemies_alerted {
+            0 => StealthRank::S,
+            1..=2 => StealthRank::A,
+            3..=5 => StealthRank::B,
+            _ => StealthRank::C,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StealthRank::S => "S",
+            StealthRank::A => "A",
+            StealthRank::B => "B",
+            StealthRank::C => "C",
+        }
+    }
+}
+
+/// The three objectives of a maze. Each one met earns a star.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StarObjectives {
+    pub completed: bool,
+    /// the exit was reached within [`PAR_STAR_ALLOWANCE`] times the par steps.
+    pub within_par: bool,
+    /// no treasure was left lying in the maze.
+    pub all_coins: bool,
+}
+
+impl StarObjectives {
+    pub fn stars(&self) -> u8 {
+        [self.completed, self.within_par, self.all_coins].into_iter().filter(|met| *met).count() as u8
+    }
+}
+
+/// What happens to the player's health when they move on to the next level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HealthPolicy {
+    /// health stays as it was at the exit, so damage adds up over a run.
+    CarryOver,
+    /// health is topped back up to the maximum for every level.
+    Refill,
+}
+
+impl HealthPolicy {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HealthPolicy::CarryOver => "Carry over",
+            HealthPolicy::Refill => "Refill",
+        }
+    }
+}
+
+/// A lasting improvement bought at an altar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatBoost {
+    /// this much more maximum health.
+    MaxHealth(i32),
+    /// this much less damage from every hit.
+    Armor(i32),
+}
+
+/// Every stat boost the player has bought, kept for the rest of the session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PermanentBoosts {
+    pub max_health: i32,
+    pub armor: i32,
+}
+
 pub struct GameData {
     pub points: u32,
+    /// coins picked up this run and not yet spent, on top of the points they were worth.
+    pub coins: u32,
+    pub high_score: u32,
+    pub health: i32,
+    pub max_health: i32,
+    pub inventory: Vec<Item>,
+    /// boosts from altar trades. They survive [`GameData::reset`].
+    pub permanent_boosts: PermanentBoosts,
+    /// the level of the current run, starting at 1.
+    pub level: u32,
+    /// the seed every level of this run is generated from, see [`GameData::level_seed`]. The same
+    /// world seed always gives the same run of levels.
+    pub world_seed: u64,
+    /// levels completed in a row without a game over.
+    pub win_streak: u32,
+    /// the most stars earned on each level, keyed by map name or seed. Kept in the save slot.
+    pub stars: HashMap<String, u8>,
+    /// levels in a row finished without alerting an enemy. Kept in the save slot.
+    pub consecutive_stealth: u32,
+    /// whether the Perfect Stealth achievement has been earned. Kept in the save slot.
+    pub perfect_stealth: bool,
+    pub settings: Settings,
+    /// how fast everything in the game runs, as a multiple of normal speed, set from the settings
+    /// screen. The scene manager scales `dt` by it; input and drawing are not affected.
+    pub game_speed: f32,
+    /// how fast recent frames have been drawn.
+    pub performance: PerformanceMonitor,
+    /// whether the maze size and effects follow the frame rate. Turned off with `--no-perf-scale`.
+    pub performance_scaling: bool,
+    /// seconds left in the current time trial, or `None` outside of one.
+    pub time_trial: Option<f32>,
+    /// mazes finished so far in the current time trial.
+    pub mazes_cleared: u32,
+    /// deaths this run, counted in practice mode where the maze restarts instead.
+    pub deaths: u32,
+    /// set by a side maze that was cleared, for the maze with its warp to pick up once it is back on top.
+    pub side_maze_cleared: bool,
+    /// size of the next generated dungeon, adjusted after every run.
+    pub difficulty: DifficultyParams,
+    /// the wall cue tones, made the first time a maze starts with them turned on.
+    pub wall_cues: Option<WallCues>,
     pub screen_width: i32,
     pub screen_height: i32,
     pub thread: Option<RaylibThread>, 
@@ -16,18 +171,300 @@ impl GameData {
     pub fn new(width: i32, heigth: i32) -> Self {
         Self {
             points: 0,
+            coins: 0,
+            high_score: 0,
+            health: 100,
+            max_health: 100,
+            inventory: Vec::new(),
+            permanent_boosts: PermanentBoosts::default(),
+            level: 1,
+            world_seed: rand::random(),
+            win_streak: 0,
+            stars: HashMap::new(),
+            consecutive_stealth: 0,
+            perfect_stealth: false,
+            settings: Settings::default(),
+            game_speed: 1.0,
+            performance: PerformanceMonitor::default(),
+            performance_scaling: true,
+            time_trial: None,
+            mazes_cleared: 0,
+            deaths: 0,
+            side_maze_cleared: false,
+            difficulty: DifficultyParams::default(),
+            wall_cues: None,
             screen_width: width,
             screen_height: heigth,
             thread: None,
         }
     }
     
+    /// how far the game moves on over a frame of `dt` seconds of real time, at the game speed.
+    pub fn scaled_dt(&self, dt: f32) -> f32 {
+        dt * self.game_speed
+    }
+
     pub fn set_thread(&mut self, thread: RaylibThread) {
         self.thread = Some(thread);
     }
 
-    /// add one to the player's total points.
-    pub fn score(&mut self) {
-        self.points += 1;
+    /// start a fresh run from level 1: full health, no points or coins and an empty inventory.
+    pub fn reset(&mut self) {
+        self.level = 1;
+        self.world_seed = rand::random();
+        self.points = 0;
+        self.coins = 0;
+        self.health = self.max_health;
+        self.inventory.clear();
+        self.time_trial = None;
+        self.mazes_cleared = 0;
+        self.deaths = 0;
+    }
+
+    /// Count a death in practice mode and ready the player for another go at the same level: back to
+    /// full health, with the level, points and items kept.
+    pub fn reset_run(&mut self) {
+        self.deaths += 1;
+        self.health = self.max_health;
+    }
+
+    /// start a fresh run against the clock, see [`TIME_TRIAL_DURATION`].
+    pub fn start_time_trial(&mut self) {
+        self.reset();
+        self.time_trial = Some(TIME_TRIAL_DURATION);
+    }
+
+    /// the maze seed of the current level, worked out from the world seed.
+    pub fn level_seed(&self) -> u64 {
+        self.world_seed.wrapping_add(self.level as u64).wrapping_mul(6364136223846793005)
+    }
+
+    /// move the run on to the next level, healing the player if the difficulty's health policy says so.
+    pub fn advance_level(&mut self) {
+        self.level += 1;
+        match self.settings.difficulty.health_policy() {
+            HealthPolicy::CarryOver => {}
+            HealthPolicy::Refill => self.health = self.max_health,
+        }
+    }
+
+    /// Called after a level is won. Below [`LOW_FPS`] the next maze shrinks and the particles and
+    /// post-processing are turned off. Returns whether the frame rate is above [`HIGH_FPS`], in which
+    /// case the player can be offered a bigger maze with [`GameData::grow_for_performance`]. Whatever is
+    /// decided is printed to stdout, in release builds too.
+    pub fn scale_for_performance(&mut self) -> bool {
+        if !self.performance_scaling {
+            return false;
+        }
+        let Some(fps) = self.performance.average_fps() else {
+            return false;
+        };
+        if fps < LOW_FPS {
+            self.difficulty.scale_size(1.0 - PERFORMANCE_SCALE_STEP);
+            self.settings.particles = false;
+            self.settings.post_process = PostProcessEffect::None;
+            println!(
+                "Running at {:.0} FPS: shrinking the next maze to {}x{} and turning off particles and post-processing",
+                fps, self.difficulty.maze_width, self.difficulty.maze_height
+            );
+            return false;
+        }
+        if fps > HIGH_FPS {
+            println!("Running at {:.0} FPS: offering a bigger maze", fps);
+            return true;
+        }
+        false
+    }
+
+    /// make the next maze bigger, when the player takes up the offer from [`GameData::scale_for_performance`].
+    pub fn grow_for_performance(&mut self) {
+        self.difficulty.scale_size(1.0 + PERFORMANCE_SCALE_STEP);
+        println!("Growing the next maze to {}x{}", self.difficulty.maze_width, self.difficulty.maze_height);
+    }
+
+    /// restore the progress kept in the save slot.
+    pub fn load_progress(&mut self) {
+        self.restore(SaveSlot::load(SAVE_PATH));
+    }
+
+    /// take up the progress kept in `slot`.
+    pub fn restore(&mut self, slot: SaveSlot) {
+        self.win_streak = slot.win_streak;
+        self.stars = slot.stars;
+        self.consecutive_stealth = slot.consecutive_stealth;
+        self.perfect_stealth = slot.perfect_stealth;
+        self.high_score = slot.high_score;
+        self.difficulty = slot.difficulty;
+    }
+
+    /// the progress that should survive a restart.
+    pub fn save_slot(&self) -> SaveSlot {
+        SaveSlot {
+            win_streak: self.win_streak,
+            stars: self.stars.clone(),
+            consecutive_stealth: self.consecutive_stealth,
+            perfect_stealth: self.perfect_stealth,
+            high_score: self.high_score,
+            difficulty: self.difficulty,
+        }
+    }
+
+    /// write the progress that should survive a restart to the save slot.
+    pub fn save_progress(&self) {
+        let slot = self.save_slot();
+        if let Err(e) = slot.save(SAVE_PATH) {
+            debug_log!("Failed to write {}: {}", SAVE_PATH, e);
+        }
     }
-}
\ No newline at end of file
+
+    /// Keep `stars` as the best for `level` if it beats the one on record. Returns whether it did.
+    pub fn record_stars(&mut self, level: &str, stars: u8) -> bool {
+        let best = self.stars.entry(level.to_string()).or_insert(0);
+        if stars <= *best {
+            return false;
+        }
+        *best = stars;
+        true
+    }
+
+    /// Count a finished level towards the Perfect Stealth achievement. Returns whether this level
+    /// earned it.
+    pub fn record_stealth(&mut self, enemies_alerted: u32) -> bool {
+        if enemies_alerted > 0 {
+            self.consecutive_stealth = 0;
+            return false;
+        }
+        self.consecutive_stealth += 1;
+        if self.perfect_stealth || self.consecutive_stealth < PERFECT_STEALTH_LEVELS {
+            return false;
+        }
+        self.perfect_stealth = true;
+        true
+    }
+
+    /// the score multiplier for the current win streak: +25% per level won in a row, at most 3x.
+    pub fn streak_multiplier(&self) -> f32 {
+        (1.0 + self.win_streak as f32 * 0.25).min(3.0)
+    }
+
+    /// the level-completion bonus on the chosen difficulty, multiplied by the current win streak.
+    pub fn level_bonus(&self) -> u32 {
+        (LEVEL_BONUS as f32 * self.settings.difficulty.points_multiplier() * self.streak_multiplier()).round() as u32
+    }
+
+    /// The points for reaching the exit after `run`. The level bonus is worth more the harder the
+    /// difficulty, and shrinks towards half of that the further the steps taken go over par. Then
+    /// come points for each second under par time and for leaving no treasure behind, and the win
+    /// streak multiplies the lot.
+    pub fn score(&self, run: RunStats, all_coins: bool) -> u32 {
+        let base = LEVEL_BONUS as f32 * self.settings.difficulty.points_multiplier() * (0.5 + 0.5 * run.efficiency());
+        let time_bonus = (run.par_time() - run.time).max(0.0) * TIME_BONUS_PER_SECOND;
+        let coin_bonus = if all_coins { ALL_COINS_BONUS } else { 0 };
+        ((base + time_bonus + coin_bonus as f32) * self.streak_multiplier()).round() as u32
+    }
+
+    /// the bonus for finishing the current level without backtracking, multiplied by the current win streak.
+    pub fn no_backtrack_bonus(&self) -> u32 {
+        (NO_BACKTRACK_BONUS as f32 * self.level as f32 * self.streak_multiplier()).round() as u32
+    }
+
+    /// add to the player's points, keeping the high score up to date.
+    pub fn add_points(&mut self, amount: u32) {
+        self.points += amount;
+        self.high_score = self.high_score.max(self.points);
+    }
+
+    /// remove one of the given item from the inventory. Returns false if the player has none.
+    pub fn take_item(&mut self, item: Item) -> bool {
+        match self.inventory.iter().position(|i| *i == item) {
+            Some(index) => {
+                self.inventory.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// how many of the given item the player is carrying.
+    pub fn item_count(&self, item: Item) -> usize {
+        self.inventory.iter().filter(|i| **i == item).count()
+    }
+
+    /// take health away from the player, less their armor (but always at least 1). Health never drops below zero.
+    pub fn take_damage(&mut self, amount: i32) {
+        let amount = (amount - self.permanent_boosts.armor).max(1);
+        self.health = (self.health - amount).max(0);
+    }
+
+    /// apply a boost for the rest of the session. More maximum health comes already filled in.
+    pub fn apply_boost(&mut self, boost: StatBoost) {
+        match boost {
+            StatBoost::MaxHealth(amount) => {
+                self.permanent_boosts.max_health += amount;
+                self.max_health += amount;
+                self.heal(amount);
+            }
+            StatBoost::Armor(amount) => self.permanent_boosts.armor += amount,
+        }
+    }
+
+    /// give health back to the player, up to their maximum.
+    pub fn heal(&mut self, amount: i32) {
+        self.health = (self.health + amount).min(self.max_health);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    #[test]
+    fn streak_multiplier_grows_a_quarter_per_win_up_to_three() {
+        let mut data = GameData::new(800, 600);
+        let expected = [1.0, 1.25, 1.5, 1.75, 2.0, 2.25, 2.5, 2.75, 3.0];
+        for (streak, multiplier) in expected.into_iter().enumerate() {
+            data.win_streak = streak as u32;
+            assert_eq!(data.streak_multiplier(), multiplier, "streak {}", streak);
+        }
+        data.win_streak = 20;
+        assert_eq!(data.streak_multiplier(), 3.0);
+    }
+
+    #[test]
+    fn level_bonus_is_multiplied_by_the_streak() {
+        let mut data = GameData::new(800, 600);
+        data.win_streak = 2;
+        data.add_points(data.level_bonus());
+        assert_eq!(data.points, 150);
+        assert_eq!(data.high_score, 150);
+    }
+
+    #[test]
+    fn advancing_a_level_refills_health_only_on_easy() {
+        let mut data = GameData::new(800, 600);
+        data.settings.difficulty = Difficulty::Hard;
+        data.health = 30;
+        data.advance_level();
+        assert_eq!((data.level, data.health), (2, 30));
+
+        data.settings.difficulty = Difficulty::Easy;
+        data.advance_level();
+        assert_eq!((data.level, data.health), (3, data.max_health));
+    }
+
+    #[test]
+    fn progress_survives_a_save_slot() {
+        let mut data = GameData::new(800, 600);
+        data.win_streak = 4;
+        data.record_stars("seed 7", 2);
+        data.perfect_stealth = true;
+        data.add_points(300);
+        data.difficulty.scale_size(1.5);
+
+        let mut restarted = GameData::new(800, 600);
+        restarted.restore(data.save_slot());
+        assert_eq!(restarted.save_slot(), data.save_slot());
+        assert_eq!((restarted.high_score, restarted.difficulty), (300, data.difficulty));
+        assert_eq!(restarted.points, 0);
+    }
+}
@@ -3,6 +3,7 @@
 //! This represents the chase game. Here we store information about the game world and the player's "character".
 
 use raylib::prelude::*;
+use rand::Rng;
 
 use crate::menu_scene::{WinScene, PauseScene};
 use crate::scenes::{Scene, SceneSwitch};
@@ -17,10 +18,10 @@ pub struct GameScene {
 }
 
 impl GameScene {
-    pub fn new(n: usize, width: i32, height: i32) -> Self {
+    pub fn new(n: usize, width: i32, height: i32, rng: &mut impl Rng) -> Self {
         let mut points = Vec::new();
         for _ in 0..n {
-            points.push(random_point(width, height));
+            points.push(random_point(width, height, rng));
         }
         Self { 
             points: points,
@@ -85,11 +86,11 @@ impl Scene for GameScene {
             // remove the last point.
             if last.distance_to(self.player_position) < 25.0 {
                 self.points.pop();
-                data.score();
+                data.add_points(data.level_bonus());
             } 
         } else {
             println!("Deal with win condition, send new scene");
-            return SceneSwitch::Replace(Box::new(WinScene));
+            return SceneSwitch::Replace(Box::new(WinScene::new(false)));
         }
 
 
@@ -0,0 +1,33 @@
+//! Items the player can pick up in the maze and carry in their inventory.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Item {
+    /// absorbs the next instance of damage once it is activated.
+    Shield,
+}
+
+impl Item {
+    pub const ALL: [Item; 1] = [Item::Shield];
+
+    /// what the item costs at a vendor on level 1, in points. Prices go up with the level.
+    pub fn base_price(&self) -> u32 {
+        match self {
+            Item::Shield => 60,
+        }
+    }
+
+    /// the `kind` used for this item's pickup in a map file's entity list.
+    pub fn map_kind(&self) -> &'static str {
+        match self {
+            Item::Shield => "shield",
+        }
+    }
+
+    /// the item a map entity gives when picked up, if it is an item at all.
+    pub fn from_map_kind(kind: &str) -> Option<Item> {
+        match kind {
+            "shield" => Some(Item::Shield),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! A look over the whole maze before a level starts.
+//!
+//! The maze is shown without its fog, zoomed out, and the camera closes in on the player over a
+//! few seconds. Then the maze takes over and play begins. Enter or Space skips the look.
+use raylib::prelude::*;
+
+use crate::game_data::GameData;
+use crate::maze_scene::MazeScene;
+use crate::scenes::{Scene, SceneSwitch};
+
+/// seconds the maze is looked over for.
+pub const REVEAL_TIME: f32 = 3.0;
+/// how far out the look starts, closing in to 1.0.
+pub const START_ZOOM: f32 = 0.3;
+
+pub struct LevelIntroScene {
+    /// the maze being looked over, handed on once the look is over.
+    maze: Option<MazeScene>,
+    /// seconds left of the look.
+    reveal_timer: f32,
+}
+
+impl LevelIntroScene {
+    pub fn new(maze: MazeScene) -> Self {
+        Self { maze: Some(maze), reveal_timer: REVEAL_TIME }
+    }
+}
+
+impl Scene for LevelIntroScene {
+    fn on_enter(&mut self, rl: &mut RaylibHandle, data: &mut GameData) {
+        if let Some(maze) = self.maze.as_mut() {
+            maze.on_enter(rl, data);
+            maze.set_overview(START_ZOOM, data);
+        }
+    }
+
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ENTER) || _rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+            self.reveal_timer = 0.0;
+        }
+        SceneSwitch::None
+    }
+
+    fn update(&mut self, dt: f32, data: &mut GameData) -> SceneSwitch {
+        self.reveal_timer -= dt;
+        let progress = (1.0 - self.reveal_timer / REVEAL_TIME).min(1.0);
+        if let Some(maze) = self.maze.as_mut() {
+            maze.set_overview(START_ZOOM + (1.0 - START_ZOOM) * progress, data);
+        }
+        if self.reveal_timer > 0.0 {
+            return SceneSwitch::None;
+        }
+        match self.maze.take() {
+            Some(maze) => SceneSwitch::Replace(Box::new(maze)),
+            None => SceneSwitch::Pop,
+        }
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        if let Some(maze) = &self.maze {
+            maze.draw(d, data);
+        }
+    }
+}
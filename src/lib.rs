@@ -2,10 +2,35 @@
 //! 
 //! 
 pub mod game_data;
+pub mod altar_scene;
+pub mod audio;
+pub mod benchmark_scene;
+pub mod debug_console;
 pub mod scenes;
 pub mod game_scene;
+pub mod difficulty;
+pub mod editor_scene;
+pub mod enemy;
+pub mod entity;
+pub mod events;
+pub mod items;
+pub mod level_intro;
 pub mod menu_scene;
+pub mod maze_format;
+pub mod maze_gen;
 pub mod maze_scene;
+pub mod multi_floor;
+pub mod narrator;
+pub mod particles;
+pub mod pathfinding;
+pub mod projectile;
+pub mod rendering;
+pub mod replay;
+pub mod save;
+pub mod screenshot;
+pub mod settings;
+pub mod shop_scene;
+pub mod theme;
 pub mod utils;
 
 pub fn is_floor_tile(tile_id: i32) -> bool {
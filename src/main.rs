@@ -2,6 +2,8 @@ use raylib::prelude::*;
 
 use raylib_framework_testing::game_data::GameData;
 use raylib_framework_testing::menu_scene::TitleScene;
+#[cfg(feature = "shaders")]
+use raylib_framework_testing::rendering::PostProcess;
 use raylib_framework_testing::scenes::SceneManager;
 
 use std::time::Instant;
@@ -14,10 +16,18 @@ fn main() {
         .title("Dungeon Diver V0.1 Demo")
         .build();
 
+    // Esc is used to back out of menus, so it should not close the window.
+    rl.set_exit_key(None);
 
     // create the game data. This prepresents data associated with the human player.
     let mut game_data = GameData::new(width, height);
     game_data.set_thread(thread); 
+    game_data.load_progress();
+    game_data.performance_scaling = !std::env::args().any(|arg| arg == "--no-perf-scale");
+
+    #[cfg(feature = "shaders")]
+    let mut post_process = PostProcess::new(&mut rl, game_data.thread.as_ref().unwrap(), width, height)
+        .expect("failed to create the post-processing frame");
 
     // scene manager
     let mut scene_manager = SceneManager::new(&mut rl, Box::new(TitleScene), &mut game_data);
@@ -34,12 +44,18 @@ fn main() {
         let temp = Instant::now();
         let delta = (temp - last_time).as_secs_f32();
         last_time = temp;
+        game_data.performance.record(delta);
 
         // update and handle user input.
         scene_manager.update(&mut rl, delta, &mut game_data);
 
         // Draw
+        #[cfg(feature = "shaders")]
+        post_process.sync(&mut rl, game_data.thread.as_ref().unwrap(), &game_data.settings);
         let mut d = rl.begin_drawing(game_data.thread.as_ref().unwrap());
+        #[cfg(feature = "shaders")]
+        post_process.draw(&mut d, &mut game_data, |d, data| scene_manager.draw(d, data));
+        #[cfg(not(feature = "shaders"))]
         scene_manager.draw(&mut d, &mut game_data); 
 
     }
@@ -0,0 +1,174 @@
+//! A compact binary maze format, `.mzb`, for big mazes and level packs.
+//!
+//! Layout (all integers little-endian):
+//!
+//! | bytes | contents                                       |
+//! |-------|------------------------------------------------|
+//! | 3     | magic `MZB`                                    |
+//! | 1     | format version, currently 1                    |
+//! | 8     | width, height (`u32` each)                     |
+//! | 8     | start x, y (`u32` each, `u32::MAX` if none)    |
+//! | 8     | exit x, y (`u32` each, `u32::MAX` if none)     |
+//! | ...   | cells row after row, 2 bits each, 4 per byte   |
+//!
+//! Cells are packed lowest bits first: 0 wall, 1 path, 2 water. The start and exit are stored
+//! as paths and restored from the header.
+use std::fmt;
+
+use crate::maze_gen::{CellType, FlatGrid};
+
+pub const MAGIC: &[u8; 3] = b"MZB";
+pub const VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 8 + 8 + 8;
+const NONE: u32 = u32::MAX;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MazeFormatError {
+    /// the data does not start with `MZB`.
+    BadMagic,
+    UnsupportedVersion(u8),
+    /// the data ends before all the cells were read.
+    Truncated,
+    /// a cell type the format has no code for, e.g. a ladder.
+    UnsupportedCell(CellType),
+    /// a 2-bit code that is not a cell type.
+    BadCell(u8),
+}
+
+impl fmt::Display for MazeFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MazeFormatError::BadMagic => write!(f, "not an .mzb maze"),
+            MazeFormatError::UnsupportedVersion(v) => write!(f, "unsupported .mzb version {}", v),
+            MazeFormatError::Truncated => write!(f, ".mzb data is truncated"),
+            MazeFormatError::UnsupportedCell(cell) => write!(f, "{:?} cells cannot be stored in .mzb", cell),
+            MazeFormatError::BadCell(code) => write!(f, "invalid cell code {}", code),
+        }
+    }
+}
+
+impl std::error::Error for MazeFormatError {}
+
+fn cell_code(cell: CellType) -> Result<u8, MazeFormatError> {
+    match cell {
+        CellType::Wall => Ok(0),
+        CellType::Path | CellType::Start | CellType::Exit => Ok(1),
+        CellType::Water => Ok(2),
+        other => Err(MazeFormatError::UnsupportedCell(other)),
+    }
+}
+
+fn code_cell(code: u8) -> Result<CellType, MazeFormatError> {
+    match code {
+        0 => Ok(CellType::Wall),
+        1 => Ok(CellType::Path),
+        2 => Ok(CellType::Water),
+        other => Err(MazeFormatError::BadCell(other)),
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+impl FlatGrid {
+    /// Encode the maze as `.mzb` bytes. Fails on cells the format cannot hold.
+    pub fn to_binary(&self) -> Result<Vec<u8>, MazeFormatError> {
+        let position = |wanted: CellType| {
+            self.cells
+                .iter()
+                .position(|c| *c == wanted)
+                .map_or((NONE, NONE), |i| ((i % self.width) as u32, (i / self.width) as u32))
+        };
+        let start = position(CellType::Start);
+        let exit = position(CellType::Exit);
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.cells.len().div_ceil(4));
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        for value in [self.width as u32, self.height as u32, start.0, start.1, exit.0, exit.1] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for chunk in self.cells.chunks(4) {
+            let mut byte = 0;
+            for (i, cell) in chunk.iter().enumerate() {
+                byte |= cell_code(*cell)? << (i * 2);
+            }
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+
+    /// Decode a maze written by [`FlatGrid::to_binary`].
+    pub fn from_binary(bytes: &[u8]) -> Result<FlatGrid, MazeFormatError> {
+        if bytes.len() < 4 || &bytes[..3] != MAGIC {
+            return Err(MazeFormatError::BadMagic);
+        }
+        if bytes[3] != VERSION {
+            return Err(MazeFormatError::UnsupportedVersion(bytes[3]));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(MazeFormatError::Truncated);
+        }
+
+        let width = read_u32(bytes, 4) as usize;
+        let height = read_u32(bytes, 8) as usize;
+        let start = (read_u32(bytes, 12), read_u32(bytes, 16));
+        let exit = (read_u32(bytes, 20), read_u32(bytes, 24));
+
+        let count = width * height;
+        let packed = &bytes[HEADER_LEN..];
+        if packed.len() < count.div_ceil(4) {
+            return Err(MazeFormatError::Truncated);
+        }
+
+        let mut cells = Vec::with_capacity(count);
+        for i in 0..count {
+            let code = (packed[i / 4] >> ((i % 4) * 2)) & 0b11;
+            cells.push(code_cell(code)?);
+        }
+
+        let mut grid = FlatGrid { width, height, cells };
+        for ((x, y), cell) in [(start, CellType::Start), (exit, CellType::Exit)] {
+            if x != NONE && (x as usize) < width && (y as usize) < height {
+                grid.set(x as usize, y as usize, cell);
+            }
+        }
+        Ok(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_gen::generate_flat_maze;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn a_generated_maze_round_trips() {
+        let grid = generate_flat_maze(31, 21, &mut StdRng::seed_from_u64(7));
+        let bytes = grid.to_binary().unwrap();
+        assert_eq!(FlatGrid::from_binary(&bytes).unwrap(), grid);
+    }
+
+    #[test]
+    fn binary_is_smaller_than_text_for_a_large_maze() {
+        let grid = generate_flat_maze(201, 201, &mut StdRng::seed_from_u64(7));
+        assert!(grid.to_binary().unwrap().len() < grid.to_ascii().len());
+    }
+
+    #[test]
+    fn truncated_or_foreign_data_is_rejected() {
+        let grid = generate_flat_maze(11, 11, &mut StdRng::seed_from_u64(7));
+        let bytes = grid.to_binary().unwrap();
+        assert_eq!(FlatGrid::from_binary(&bytes[..bytes.len() - 1]), Err(MazeFormatError::Truncated));
+        assert_eq!(FlatGrid::from_binary(&bytes[..HEADER_LEN - 1]), Err(MazeFormatError::Truncated));
+
+        let mut foreign = bytes.clone();
+        foreign[..3].copy_from_slice(b"PNG");
+        assert_eq!(FlatGrid::from_binary(&foreign), Err(MazeFormatError::BadMagic));
+        assert_eq!(FlatGrid::from_binary(&[]), Err(MazeFormatError::BadMagic));
+    }
+}
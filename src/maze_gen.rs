@@ -0,0 +1,1640 @@
+//! Procedural maze generation.
+//!
+//! Mazes are grids of [`CellType`] indexed `[y][x]`. Passages are carved between the cells at odd
+//! coordinates, so the outer border is always wall and a maze should have odd dimensions.
+//!
+//! Everything here is iterative rather than recursive, so very large mazes (1001x1001 and up)
+//! can be generated without running out of stack.
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::fmt;
+
+use crate::pathfinding::ReachabilityGraph;
+use crate::utils::Direction;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LadderDirection {
+    Up,
+    Down,
+}
+
+/// Links a button to the gates it opens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ButtonId(pub u8);
+
+/// How the player gets about, changed for a while by a transformer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveMode {
+    #[default]
+    Normal,
+    /// walks through walls and gates, but cannot pick anything up.
+    Ghost,
+    /// takes up the player's cell and the three below and to the right of it, and shoves enemies
+    /// out of the way.
+    Giant,
+    /// small enough to slip under shut gates, which keep enemies out.
+    Tiny,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellType {
+    Wall,
+    Path,
+    /// where the player starts.
+    Start,
+    /// reaching this cell completes the maze.
+    Exit,
+    /// climbs to the cell at the same position on the floor above or below.
+    Ladder(LadderDirection),
+    /// a path that the rising water has flooded. Still walkable, but the water hurts as it rises.
+    Water,
+    /// a pickup that restores one heart of health.
+    Heart,
+    /// a bounce pad that launches the player this many more cells in the direction they were moving.
+    Mushroom(u8),
+    /// explodes when stepped on, hurting everything nearby and blasting nearby walls open.
+    Mine,
+    /// looks just like a wall, but the player can walk straight through it.
+    Illusion,
+    /// offers a one-time trade when stepped on, see [`crate::altar_scene`].
+    Altar,
+    /// holds the player in place for a moment when they step into it.
+    Quicksand,
+    /// sells a few items for points when stepped on, see [`crate::shop_scene`].
+    Vendor,
+    /// a scrap of map that shows the maze around it through the fog once picked up.
+    MapFragment,
+    /// whichever way the player tries to leave, the wind blows them out this way instead (when
+    /// that way is open).
+    Wind(Direction),
+    /// blocks the way like a wall, but anyone thrown into it dies on the spot.
+    Lava,
+    /// a pickup that stops time for everything but the player for a few seconds.
+    TimeStop,
+    /// fires a projectile each way along the grid when it hears the player nearby.
+    SoundTrap,
+    /// opens every gate with the same id when stepped on, and shuts them again the next time.
+    Button(ButtonId),
+    /// a shut gate, blocking the way like a wall until its button opens it.
+    LinkedWall(ButtonId),
+    /// leads into a small side maze, see [`crate::maze_scene::MazeScene::side_maze`].
+    Warp,
+    /// slides the player along this way, one cell at a time, until they are off the slope.
+    SlipperySlope(Direction),
+    /// shows a ghost of the player walking the way to the goal for a moment when stepped on.
+    Echo,
+    /// throws the player to a random path cell far away, somewhere new every time.
+    Wormhole,
+    /// holds the player for one step, then gives way and leaves a pit.
+    Crumble,
+    /// a hole left by a crumbled floor. Nobody walks into one, but a player still standing on the
+    /// floor as it gives way falls in.
+    Pit,
+    /// pushes the player one more cell this way as soon as they step on it.
+    ArrowTile(Direction),
+    /// turns the player into this for a few seconds when stepped on, then is used up.
+    Transformer(MoveMode),
+}
+
+/// One character per cell, for printing grids while debugging, see [`crate::utils::print_grid`].
+impl fmt::Display for CellType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            CellType::Wall => '#',
+            CellType::Path => '.',
+            CellType::Start => 'S',
+            CellType::Exit => 'E',
+            CellType::Ladder(LadderDirection::Up) => '<',
+            CellType::Ladder(LadderDirection::Down) => '>',
+            CellType::Water => '~',
+            CellType::Heart => 'h',
+            CellType::Mushroom(_) => 'm',
+            CellType::Mine => '!',
+            CellType::Illusion => '%',
+            CellType::Altar => 'A',
+            CellType::Quicksand => ':',
+            CellType::Vendor => '$',
+            CellType::MapFragment => '?',
+            CellType::Wind(Direction::North) => '^',
+            CellType::Wind(Direction::South) => 'v',
+            CellType::Wind(Direction::East) => ')',
+            CellType::Wind(Direction::West) => '(',
+            CellType::Lava => '&',
+            CellType::TimeStop => 't',
+            CellType::SoundTrap => 'o',
+            CellType::Button(_) => '*',
+            CellType::LinkedWall(_) => '=',
+            CellType::Warp => '@',
+            CellType::SlipperySlope(_) => '/',
+            CellType::Echo => 'e',
+            CellType::Wormhole => 'w',
+            CellType::Crumble => ',',
+            CellType::Pit => '_',
+            CellType::ArrowTile(_) => '+',
+            CellType::Transformer(_) => 'M',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+/// The ways a maze can be generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MazeAlgorithm {
+    /// a perfect maze of one-cell corridors, see [`generate_maze`].
+    Backtracker,
+    /// rectangular rooms joined by corridors, see [`generate_with_rooms`].
+    DungeonRooms,
+    /// a maze whose right half mirrors its left, see [`generate_mirror_maze`].
+    Mirror,
+}
+
+impl MazeAlgorithm {
+    pub const ALL: [MazeAlgorithm; 3] = [MazeAlgorithm::Backtracker, MazeAlgorithm::DungeonRooms, MazeAlgorithm::Mirror];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MazeAlgorithm::Backtracker => "Corridors",
+            MazeAlgorithm::DungeonRooms => "Rooms",
+            MazeAlgorithm::Mirror => "Mirror",
+        }
+    }
+}
+
+impl CellType {
+    /// whether the player can stand on this cell.
+    pub fn is_walkable(&self) -> bool {
+        !matches!(self, CellType::Wall | CellType::Lava | CellType::LinkedWall(_) | CellType::Pit)
+    }
+
+    /// whether enemies can walk through this cell. They keep off mushrooms, mines and crumbling
+    /// floor, and treat illusions as the walls they look like.
+    pub fn is_enemy_walkable(&self) -> bool {
+        self.is_walkable() && !matches!(self, CellType::Mushroom(_) | CellType::Mine | CellType::Illusion | CellType::Crumble)
+    }
+
+    /// The name and a one-sentence description of a special cell, shown in its tooltip.
+    /// Plain walls, paths and the start have none, and illusions keep their secret.
+    pub fn description(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            CellType::Exit => Some(("Exit", "Step on it to finish the maze.")),
+            CellType::Ladder(LadderDirection::Down) => Some(("Ladder", "Climbs down to the floor below.")),
+            CellType::Ladder(LadderDirection::Up) => Some(("Ladder", "Climbs back up to the floor above.")),
+            CellType::Water => Some(("Water", "Flooded, and it hurts to be in it when the water rises.")),
+            CellType::Heart => Some(("Heart", "Restores one heart of health.")),
+            CellType::Mine => Some(("Mine", "Explodes when stepped on, blasting nearby walls away.")),
+            CellType::Mushroom(_) => Some(("Mushroom", "Bounces you further along in the direction you step onto it.")),
+            CellType::Altar => Some(("Altar", "Offers a trade, once, to whoever steps on it.")),
+            CellType::Quicksand => Some(("Quicksand", "Holds you in place for a couple of seconds.")),
+            CellType::Vendor => Some(("Vendor", "Sells a few items for points, once.")),
+            CellType::MapFragment => Some(("Map fragment", "Shows the maze around it, even in the dark.")),
+            CellType::Wind(_) => Some(("Wind", "Blows you out along the arrows, whichever way you try to leave.")),
+            CellType::Lava => Some(("Lava", "You would never walk into it, but a bounce could throw you in.")),
+            CellType::TimeStop => Some(("Time stop", "Freezes enemies, projectiles and water for a few seconds.")),
+            CellType::SoundTrap => Some(("Sound trap", "Fires in every direction when it hears you. Sprinting and bouncing are loud, creeping (C) is silent.")),
+            CellType::Button(_) => Some(("Button", "Opens the gates linked to it, however far away, and shuts them the next time.")),
+            CellType::LinkedWall(_) => Some(("Gate", "Shut until a button somewhere in the maze opens it.")),
+            CellType::Warp => Some(("Warp", "Leads into a small side maze. Clear it for a reward and come back here.")),
+            CellType::Crumble => Some(("Crumbling floor", "Holds you once. Move on quickly, it gives way right after.")),
+            CellType::Pit => Some(("Pit", "Where a crumbling floor gave way.")),
+            CellType::Wormhole => Some(("Wormhole", "Throws you somewhere far across the maze. Never the same place twice.")),
+            CellType::Echo => Some(("Echo", "Shows a ghost of you walking the way on, for a couple of seconds.")),
+            CellType::SlipperySlope(_) => Some(("Slope", "Slides you down it, whichever way you step on, until you are off it.")),
+            CellType::ArrowTile(_) => Some(("Arrow", "Pushes you one more step the way it points, as soon as you step on it.")),
+            CellType::Transformer(MoveMode::Ghost) => Some(("Ghost transformer", "Lets you walk through walls for a while, but not pick anything up.")),
+            CellType::Transformer(MoveMode::Giant) => Some(("Giant transformer", "Makes you two cells big for a while, big enough to shove enemies aside.")),
+            CellType::Transformer(MoveMode::Tiny) => Some(("Tiny transformer", "Shrinks you for a while, small enough to slip under shut gates.")),
+            CellType::Transformer(MoveMode::Normal) => Some(("Transformer", "Turns you back to your usual self.")),
+            CellType::Wall | CellType::Path | CellType::Start | CellType::Illusion => None,
+        }
+    }
+}
+
+/// A maze stored as one flat vector of cells, row after row. Large mazes are generated in this
+/// form since it is a single allocation instead of one per row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlatGrid {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<CellType>,
+}
+
+impl FlatGrid {
+    pub fn new(width: usize, height: usize, fill: CellType) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    pub fn from_rows(rows: &[Vec<CellType>]) -> Self {
+        Self {
+            width: rows.first().map_or(0, |row| row.len()),
+            height: rows.len(),
+            cells: rows.concat(),
+        }
+    }
+
+    /// the grid as rows indexed `[y][x]`, the layout the maze scene plays on.
+    pub fn to_rows(&self) -> Vec<Vec<CellType>> {
+        self.cells.chunks(self.width.max(1)).map(|row| row.to_vec()).collect()
+    }
+
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> CellType {
+        self.cells[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: CellType) {
+        let i = self.index(x, y);
+        self.cells[i] = cell;
+    }
+
+    // the cell `steps` cells away in the given direction, if it is still inside the grid
+    fn step(&self, x: usize, y: usize, direction: Direction, steps: i32) -> Option<(usize, usize)> {
+        let (dx, dy) = direction.delta();
+        let nx = x as i32 + dx * steps;
+        let ny = y as i32 + dy * steps;
+        if nx < 0 || ny < 0 || nx as usize >= self.width || ny as usize >= self.height {
+            return None;
+        }
+        Some((nx as usize, ny as usize))
+    }
+
+    /// Walking distance from `start` to every cell (indexed like `cells`), or `None` for cells
+    /// that cannot be reached.
+    pub fn distances_from(&self, start: (usize, usize)) -> Vec<Option<usize>> {
+        self.distances_through(start, |cell| cell.is_walkable())
+    }
+
+    /// Like [`FlatGrid::distances_from`], only stepping on cells `passable` allows.
+    pub fn distances_through(&self, start: (usize, usize), passable: impl Fn(CellType) -> bool) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.cells.len()];
+        let mut queue = VecDeque::new();
+
+        distances[self.index(start.0, start.1)] = Some(0);
+        queue.push_back(start);
+        while let Some((x, y)) = queue.pop_front() {
+            let distance = distances[self.index(x, y)].unwrap_or(0);
+            for direction in Direction::ALL {
+                if let Some((nx, ny)) = self.step(x, y, direction, 1) {
+                    let i = self.index(nx, ny);
+                    if passable(self.cells[i]) && distances[i].is_none() {
+                        distances[i] = Some(distance + 1);
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// The cells of a shortest walk from `from` to `to` through `passable` cells, both ends included,
+    /// or `None` if `to` cannot be reached.
+    pub fn path_through(&self, from: (usize, usize), to: (usize, usize), passable: impl Fn(CellType) -> bool) -> Option<Vec<(usize, usize)>> {
+        // walk back downhill over the distances from the far end
+        let distances = self.distances_through(to, passable);
+        let mut distance = distances[self.index(from.0, from.1)]?;
+        let mut path = vec![from];
+        let mut current = from;
+        while distance > 0 {
+            current = Direction::ALL
+                .into_iter()
+                .filter_map(|direction| self.step(current.0, current.1, direction, 1))
+                .find(|&(x, y)| distances[self.index(x, y)] == Some(distance - 1))?;
+            path.push(current);
+            distance -= 1;
+        }
+        Some(path)
+    }
+
+    /// The buttons that can be pressed on the way from `start`, and the walking distance to every
+    /// cell with their gates open. An open gate can lead on to more buttons, so this goes round
+    /// until no new button comes into reach.
+    pub fn distances_with_buttons(&self, start: (usize, usize)) -> (HashSet<ButtonId>, Vec<Option<usize>>) {
+        let mut pressed = HashSet::new();
+        loop {
+            let passable = |cell: CellType| match cell {
+                CellType::LinkedWall(id) => pressed.contains(&id),
+                cell => cell.is_walkable(),
+            };
+            let distances = self.distances_through(start, passable);
+            let reached: HashSet<ButtonId> = self
+                .cells
+                .iter()
+                .zip(&distances)
+                .filter_map(|(cell, distance)| match cell {
+                    CellType::Button(id) if distance.is_some() => Some(*id),
+                    _ => None,
+                })
+                .collect();
+            if reached.is_subset(&pressed) {
+                return (pressed, distances);
+            }
+            pressed.extend(reached);
+        }
+    }
+
+    /// The reachable cell furthest (by walking distance) from `start`.
+    pub fn farthest_cell(&self, start: (usize, usize)) -> (usize, usize) {
+        let mut best = (self.index(start.0, start.1), 0);
+        for (i, distance) in self.distances_from(start).into_iter().enumerate() {
+            if let Some(d) = distance
+                && d > best.1
+            {
+                best = (i, d);
+            }
+        }
+        (best.0 % self.width, best.0 / self.width)
+    }
+
+    /// The maze as text, one line per row and one [`CellType`] character per cell.
+    pub fn to_ascii(&self) -> String {
+        self.ascii_rows(None)
+    }
+
+    /// Like [`FlatGrid::to_ascii`], with `@` on the player's cell.
+    pub fn to_ascii_with_player(&self, player: (usize, usize)) -> String {
+        self.ascii_rows(Some(player))
+    }
+
+    fn ascii_rows(&self, player: Option<(usize, usize)>) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for (i, cell) in self.cells.iter().enumerate() {
+            if player == Some((i % self.width, i / self.width)) {
+                text.push('@');
+            } else {
+                text.push_str(&cell.to_string());
+            }
+            if i % self.width == self.width - 1 {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// Steps on the shortest walk from the start to the exit, or `None` if either is missing or the
+    /// exit cannot be reached.
+    pub fn solution_length(&self) -> Option<usize> {
+        let find = |wanted: CellType| self.cells.iter().position(|c| *c == wanted);
+        let (start, exit) = (find(CellType::Start)?, find(CellType::Exit)?);
+        self.distances_from((start % self.width, start / self.width))[exit]
+    }
+
+    /// Whether the way from the start to the exit is long enough to be worth playing: at least
+    /// [`INTERESTING_PATH_FRACTION`] of the grid's diagonal. A straight shot across the maze is not.
+    pub fn is_interesting(&self) -> bool {
+        let diagonal = ((self.width * self.width + self.height * self.height) as f64).sqrt();
+        self.solution_length().is_some_and(|length| length as f64 >= diagonal * INTERESTING_PATH_FRACTION)
+    }
+}
+
+/// the smallest weight a direction gets in [`generate_biased_maze`], whatever the bias says.
+pub const MIN_BIAS_WEIGHT: f32 = 0.001;
+/// how long, as a share of the grid's diagonal, the shortest route through a maze has to be for
+/// [`FlatGrid::is_interesting`].
+pub const INTERESTING_PATH_FRACTION: f64 = 1.0;
+/// how many times [`generate`] tries for an interesting maze before settling for the last one.
+pub const MAX_GENERATION_ATTEMPTS: usize = 10;
+
+/// Generate a perfect maze (exactly one route between any two cells) with the backtracker,
+/// using an explicit stack instead of recursion. Even dimensions are rounded up to the next odd
+/// number. The start is the top-left cell and the exit is placed on the cell furthest from it.
+pub fn generate_flat_maze(width: usize, height: usize, rng: &mut impl Rng) -> FlatGrid {
+    generate_biased_maze(width, height, (1.0, 1.0), rng)
+}
+
+/// Like [`generate_flat_maze`], with the backtracker leaning towards horizontal or vertical
+/// passages. `bias` is the weight of (east and west, north and south) when picking where to carve
+/// next, so `(4.0, 1.0)` gives long corridors running across the maze. It is still a perfect maze.
+pub fn generate_biased_maze(width: usize, height: usize, bias: (f32, f32), rng: &mut impl Rng) -> FlatGrid {
+    let width = (width | 1).max(3);
+    let height = (height | 1).max(3);
+
+    let mut grid = FlatGrid::new(width, height, CellType::Wall);
+    carve_passages(&mut grid, (1, 1), bias, rng);
+
+    grid.set(1, 1, CellType::Start);
+    let (exit_x, exit_y) = grid.farthest_cell((1, 1));
+    grid.set(exit_x, exit_y, CellType::Exit);
+    grid
+}
+
+/// Like [`generate_flat_maze`], as rows indexed `[y][x]`.
+pub fn generate_maze(width: usize, height: usize, rng: &mut impl Rng) -> Vec<Vec<CellType>> {
+    generate_flat_maze(width, height, rng).to_rows()
+}
+
+/// Whether the open cells form a perfect maze: all joined up, with exactly one route between any two
+/// of them. Open cells joined as a tree have one fewer link between neighbours than there are cells.
+/// The backtracker always makes one. Room and mirror mazes are not meant to, since rooms and the
+/// gaps joining mirrored halves make loops.
+pub fn is_perfect_maze(grid: &[Vec<CellType>]) -> bool {
+    let Some(start) = first_walkable(grid) else { return false };
+    let open = |x: usize, y: usize| grid.get(y).and_then(|row| row.get(x)).is_some_and(|c| c.is_walkable());
+    let mut cells = 0;
+    let mut links = 0;
+    for (y, row) in grid.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if cell.is_walkable() {
+                cells += 1;
+                links += open(x + 1, y) as usize + open(x, y + 1) as usize;
+            }
+        }
+    }
+    let reached = distances_from(grid, start).iter().flatten().filter(|d| d.is_some()).count();
+    reached == cells && links == cells - 1
+}
+
+// From the top of the stack, knock down the wall to a random unvisited cell two steps away and push
+// it. A cell with no unvisited neighbours left is popped, which backtracks to the one before it.
+// Directions are weighted by `bias` (horizontal, vertical). Weights are kept above zero so a cell
+// with unvisited neighbours always carves on, which keeps every cell connected.
+fn carve_passages(grid: &mut FlatGrid, start: (usize, usize), bias: (f32, f32), rng: &mut impl Rng) {
+    grid.set(start.0, start.1, CellType::Path);
+    let mut stack = vec![start];
+
+    while let Some(&(x, y)) = stack.last() {
+        let unvisited: Vec<Direction> = Direction::ALL
+            .into_iter()
+            .filter(|dir| match grid.step(x, y, *dir, 2) {
+                Some((nx, ny)) => nx < grid.width - 1 && ny < grid.height - 1 && grid.get(nx, ny) == CellType::Wall,
+                None => false,
+            })
+            .collect();
+
+        // an even bias picks the same way as an unbiased maze, so seeds keep their mazes
+        let picked = if bias.0 == bias.1 {
+            unvisited.choose(rng)
+        } else {
+            let weight = |dir: &Direction| match dir {
+                Direction::East | Direction::West => bias.0.max(MIN_BIAS_WEIGHT),
+                Direction::North | Direction::South => bias.1.max(MIN_BIAS_WEIGHT),
+            };
+            unvisited.choose_weighted(rng, weight).ok()
+        };
+        match picked {
+            Some(&direction) => {
+                let (wx, wy) = grid.step(x, y, direction, 1).unwrap_or((x, y));
+                let (nx, ny) = grid.step(x, y, direction, 2).unwrap_or((x, y));
+                grid.set(wx, wy, CellType::Path);
+                grid.set(nx, ny, CellType::Path);
+                stack.push((nx, ny));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+}
+
+/// Walking distance from `start` to every cell, or `None` for cells that cannot be reached.
+pub fn distances_from(grid: &[Vec<CellType>], start: (usize, usize)) -> Vec<Vec<Option<usize>>> {
+    let flat = FlatGrid::from_rows(grid);
+    flat.distances_from(start)
+        .chunks(flat.width.max(1))
+        .map(|row| row.to_vec())
+        .collect()
+}
+
+/// The reachable cell furthest (by walking distance) from `start`.
+pub fn farthest_cell(grid: &[Vec<CellType>], start: (usize, usize)) -> (usize, usize) {
+    FlatGrid::from_rows(grid).farthest_cell(start)
+}
+
+/// Like [`FlatGrid::path_through`], on rows indexed `[y][x]`.
+pub fn path_through(grid: &[Vec<CellType>], from: (usize, usize), to: (usize, usize), passable: impl Fn(CellType) -> bool) -> Option<Vec<(usize, usize)>> {
+    FlatGrid::from_rows(grid).path_through(from, to, passable)
+}
+
+/// Whether `from` and `to` share a row or column with only walkable cells between them.
+pub fn line_of_sight(grid: &[Vec<CellType>], from: (usize, usize), to: (usize, usize)) -> bool {
+    if from.0 == to.0 {
+        let (top, bottom) = (from.1.min(to.1), from.1.max(to.1));
+        (top..=bottom).all(|y| grid[y][from.0].is_walkable())
+    } else if from.1 == to.1 {
+        let (left, right) = (from.0.min(to.0), from.0.max(to.0));
+        grid[from.1][left..=right].iter().all(|cell| cell.is_walkable())
+    } else {
+        false
+    }
+}
+
+/// A rectangle of open cells in a dungeon floor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Room {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Room {
+    pub fn center(&self) -> (usize, usize) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    /// whether the two rooms overlap or touch, counting a one-cell wall gap around each.
+    pub fn overlaps(&self, other: &Room) -> bool {
+        self.x <= other.x + other.width
+            && other.x <= self.x + self.width
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+}
+
+/// The rooms of a dungeon floor, planned before any cells are carved.
+pub struct FloorPlan {
+    pub rooms: Vec<Room>,
+}
+
+impl FloorPlan {
+    /// Place up to `room_count` non-overlapping rooms inside the border of a `width` x `height` grid.
+    /// Placements that collide are retried a limited number of times, so a crowded grid can end up
+    /// with fewer rooms than asked for.
+    pub fn plan(width: usize, height: usize, room_count: usize, min_room_size: usize, max_room_size: usize, rng: &mut impl Rng) -> Self {
+        let mut rooms: Vec<Room> = Vec::new();
+        if width < 3 || height < 3 {
+            return Self { rooms };
+        }
+
+        // rooms have to fit inside the one-cell border
+        let max_w = max_room_size.min(width - 2).max(1);
+        let max_h = max_room_size.min(height - 2).max(1);
+        let min_w = min_room_size.clamp(1, max_w);
+        let min_h = min_room_size.clamp(1, max_h);
+
+        for _ in 0..room_count * 10 {
+            if rooms.len() == room_count {
+                break;
+            }
+            let room_w = rng.random_range(min_w..=max_w);
+            let room_h = rng.random_range(min_h..=max_h);
+            let room = Room {
+                x: rng.random_range(1..=width - 1 - room_w),
+                y: rng.random_range(1..=height - 1 - room_h),
+                width: room_w,
+                height: room_h,
+            };
+            if !rooms.iter().any(|r| r.overlaps(&room)) {
+                rooms.push(room);
+            }
+        }
+        Self { rooms }
+    }
+}
+
+/// Carve a dungeon of rooms into `grid`: plan non-overlapping rooms, open them up, join the centres of
+/// consecutive rooms with L-shaped corridors, then wall off anything the first room cannot reach.
+/// Unlike the perfect-maze generators this leaves open areas and loops.
+pub fn generate_with_rooms(grid: &mut [Vec<CellType>], room_count: usize, min_room_size: usize, max_room_size: usize, rng: &mut impl Rng) {
+    let height = grid.len();
+    let width = grid.first().map_or(0, |row| row.len());
+    for row in grid.iter_mut() {
+        row.fill(CellType::Wall);
+    }
+
+    let plan = FloorPlan::plan(width, height, room_count.max(1), min_room_size, max_room_size, rng);
+    for room in &plan.rooms {
+        for row in &mut grid[room.y..room.y + room.height] {
+            row[room.x..room.x + room.width].fill(CellType::Path);
+        }
+    }
+
+    for pair in plan.rooms.windows(2) {
+        let (ax, ay) = pair[0].center();
+        let (bx, by) = pair[1].center();
+        // go along then up/down, or up/down then along
+        let corner = if rng.random_bool(0.5) { (bx, ay) } else { (ax, by) };
+        carve_line(grid, (ax, ay), corner);
+        carve_line(grid, corner, (bx, by));
+    }
+
+    if let Some(first) = plan.rooms.first() {
+        let distances = distances_from(grid, first.center());
+        for (row, row_distances) in grid.iter_mut().zip(distances) {
+            for (cell, distance) in row.iter_mut().zip(row_distances) {
+                if distance.is_none() {
+                    *cell = CellType::Wall;
+                }
+            }
+        }
+    }
+}
+
+// open every cell on a straight horizontal or vertical line between two points
+fn carve_line(grid: &mut [Vec<CellType>], from: (usize, usize), to: (usize, usize)) {
+    for row in &mut grid[from.1.min(to.1)..=from.1.max(to.1)] {
+        row[from.0.min(to.0)..=from.0.max(to.0)].fill(CellType::Path);
+    }
+}
+
+/// how many cells in from the border wall a corner region reaches.
+pub const CORNER_REGION_SIZE: usize = 3;
+
+/// Which corner region of a `width` by `height` maze the cell (x, y) is in, if any:
+/// 0 is the top left, 1 the top right, 2 the bottom left and 3 the bottom right.
+pub fn corner_region(x: usize, y: usize, width: usize, height: usize) -> Option<usize> {
+    let near = |v: usize, size: usize| {
+        if (1..=CORNER_REGION_SIZE).contains(&v) {
+            Some(0)
+        } else if v + 1 < size && v + 1 + CORNER_REGION_SIZE >= size {
+            Some(1)
+        } else {
+            None
+        }
+    };
+    Some(near(y, height)? * 2 + near(x, width)?)
+}
+
+/// For each corner region (numbered as in [`corner_region`]), whether one of its cells can be
+/// walked to from `from`.
+pub fn reachable_corners(grid: &[Vec<CellType>], from: (usize, usize)) -> [bool; 4] {
+    let (width, height) = (grid[0].len(), grid.len());
+    let mut reachable = [false; 4];
+    for (y, row) in distances_from(grid, from).iter().enumerate() {
+        for (x, distance) in row.iter().enumerate() {
+            if distance.is_some()
+                && let Some(corner) = corner_region(x, y, width, height)
+            {
+                reachable[corner] = true;
+            }
+        }
+    }
+    reachable
+}
+
+/// Carve a corridor into every corner region that cannot be reached from `from`, from the cell just
+/// inside the corner to the nearest cell that can. Only walls are opened, so nothing else is lost.
+pub fn open_corners(grid: &mut [Vec<CellType>], from: (usize, usize)) {
+    let (width, height) = (grid[0].len(), grid.len());
+    if width < 3 || height < 3 {
+        return;
+    }
+    let corners = [(1, 1), (width - 2, 1), (1, height - 2), (width - 2, height - 2)];
+    let reachable = reachable_corners(grid, from);
+    let distances = distances_from(grid, from);
+
+    for (corner, &(cx, cy)) in corners.iter().enumerate() {
+        if reachable[corner] {
+            continue;
+        }
+        let mut nearest: Option<((usize, usize), usize)> = None;
+        for (y, row) in distances.iter().enumerate() {
+            for (x, distance) in row.iter().enumerate() {
+                let gap = x.abs_diff(cx) + y.abs_diff(cy);
+                if distance.is_some() && nearest.is_none_or(|(_, best)| gap < best) {
+                    nearest = Some(((x, y), gap));
+                }
+            }
+        }
+        let Some(((tx, ty), _)) = nearest else { continue };
+
+        // along the corner's row, then down or up its column
+        let (left, right) = (cx.min(tx), cx.max(tx));
+        let (top, bottom) = (cy.min(ty), cy.max(ty));
+        let cells = (left..=right).map(|x| (x, cy)).chain((top..=bottom).map(|y| (tx, y)));
+        for (x, y) in cells {
+            if !grid[y][x].is_walkable() {
+                grid[y][x] = CellType::Path;
+            }
+        }
+    }
+}
+
+/// how many gaps [`generate_mirror_maze`] opens in the wall between its two halves.
+pub const MIRROR_CROSS_LINKS: usize = 2;
+
+/// Generate a maze that is the same either side of its middle column: one half is carved with the
+/// backtracker and copied over mirrored, then up to [`MIRROR_CROSS_LINKS`] gaps are opened in the
+/// middle wall to join the halves. The start is on the left edge and the exit at its mirror image on
+/// the right, so the way across is a choice between gaps with mirrored routes to and from them.
+/// The width is rounded up so both halves are odd.
+pub fn generate_mirror_maze(width: usize, height: usize, rng: &mut impl Rng) -> Vec<Vec<CellType>> {
+    let half_width = (width.div_ceil(2) | 1).max(3);
+    let half = generate_flat_maze(half_width, height, rng);
+    let width = half_width * 2 - 1;
+    let mut grid = vec![vec![CellType::Wall; width]; half.height];
+    for (y, row) in grid.iter_mut().enumerate() {
+        for x in 0..half_width {
+            let cell = if half.get(x, y) == CellType::Wall { CellType::Wall } else { CellType::Path };
+            row[x] = cell;
+            row[width - 1 - x] = cell;
+        }
+    }
+
+    // the middle column is the half maze's outer wall, so every odd row beside it is open
+    let middle = half_width - 1;
+    let rows: Vec<usize> = (1..half.height - 1).step_by(2).collect();
+    for &y in rows.choose_multiple(rng, MIRROR_CROSS_LINKS) {
+        grid[y][middle] = CellType::Path;
+    }
+
+    let start_y = (half.height / 2) | 1;
+    grid[start_y][1] = CellType::Start;
+    grid[start_y][width - 2] = CellType::Exit;
+    grid
+}
+
+/// Generate a maze with the given algorithm. Every algorithm marks a start and an exit. Mazes that
+/// are not [interesting](FlatGrid::is_interesting) are thrown away and generated again.
+pub fn generate(algorithm: MazeAlgorithm, width: usize, height: usize, rng: &mut impl Rng) -> Vec<Vec<CellType>> {
+    first_interesting(|| generate_once(algorithm, width, height, rng))
+}
+
+// the first interesting maze out of `attempt`, which is called up to MAX_GENERATION_ATTEMPTS times
+fn first_interesting(mut attempt: impl FnMut() -> Vec<Vec<CellType>>) -> Vec<Vec<CellType>> {
+    let mut grid = attempt();
+    for _ in 1..MAX_GENERATION_ATTEMPTS {
+        if FlatGrid::from_rows(&grid).is_interesting() {
+            break;
+        }
+        grid = attempt();
+    }
+    grid
+}
+
+// one maze from the given algorithm, interesting or not
+fn generate_once(algorithm: MazeAlgorithm, width: usize, height: usize, rng: &mut impl Rng) -> Vec<Vec<CellType>> {
+    match algorithm {
+        MazeAlgorithm::Backtracker => generate_maze(width, height, rng),
+        MazeAlgorithm::DungeonRooms => {
+            let width = width.max(5);
+            let height = height.max(5);
+            let mut grid = vec![vec![CellType::Wall; width]; height];
+            // about one room per 80 cells, at least two
+            let room_count = (width * height / 80).max(2);
+            generate_with_rooms(&mut grid, room_count, 3, 7, rng);
+
+            // start in the first open cell from the top left, exit as far away as possible
+            if let Some(start) = first_walkable(&grid) {
+                grid[start.1][start.0] = CellType::Start;
+                let (exit_x, exit_y) = farthest_cell(&grid, start);
+                grid[exit_y][exit_x] = CellType::Exit;
+            }
+            grid
+        }
+        MazeAlgorithm::Mirror => generate_mirror_maze(width, height, rng),
+    }
+}
+
+/// The first walkable cell in reading order.
+pub fn first_walkable(grid: &[Vec<CellType>]) -> Option<(usize, usize)> {
+    grid.iter()
+        .enumerate()
+        .find_map(|(y, row)| row.iter().position(|c| c.is_walkable()).map(|x| (x, y)))
+}
+
+/// Pick `count` distinct open cells reachable from `from` to use as checkpoints, ordered from nearest
+/// to furthest so visiting them in order is a natural route. Special cells are never picked.
+/// A maze with too few open cells gets fewer checkpoints.
+pub fn place_checkpoints(grid: &[Vec<CellType>], from: (usize, usize), count: usize, rng: &mut impl Rng) -> Vec<(usize, usize)> {
+    let distances = distances_from(grid, from);
+    let mut candidates: Vec<((usize, usize), usize)> = Vec::new();
+    for (y, row) in distances.iter().enumerate() {
+        for (x, distance) in row.iter().enumerate() {
+            if let Some(d) = *distance
+                && d > 0
+                && grid[y][x] == CellType::Path
+            {
+                candidates.push(((x, y), d));
+            }
+        }
+    }
+
+    let mut picked: Vec<((usize, usize), usize)> = candidates.choose_multiple(rng, count).copied().collect();
+    picked.sort_by_key(|(_, d)| *d);
+    picked.into_iter().map(|(cell, _)| cell).collect()
+}
+
+/// Length of the shortest walk from the first stop through every other one in order, or `None` if
+/// one of them cannot be reached from the one before it.
+pub fn route_length(grid: &[Vec<CellType>], stops: &[(usize, usize)]) -> Option<usize> {
+    let flat = FlatGrid::from_rows(grid);
+    stops
+        .windows(2)
+        .map(|leg| flat.distances_from(leg[0])[flat.index(leg[1].0, leg[1].1)])
+        .sum()
+}
+
+/// Move the exit to the reachable open cell nearest the top of the grid (the one furthest from
+/// `from` if several share the top row), so a rising water run has to climb upwards.
+pub fn move_exit_to_top(grid: &mut [Vec<CellType>], from: (usize, usize)) {
+    let distances = distances_from(grid, from);
+    let mut best: Option<((usize, usize), usize)> = None;
+    for (y, row) in distances.iter().enumerate() {
+        for (x, distance) in row.iter().enumerate() {
+            let open = matches!(grid[y][x], CellType::Path | CellType::Exit);
+            if let Some(d) = *distance
+                && open
+                && best.is_none_or(|((_, by), bd)| y < by || (y == by && d > bd))
+            {
+                best = Some(((x, y), d));
+            }
+        }
+    }
+
+    if let Some(((x, y), _)) = best {
+        for cell in grid.iter_mut().flatten() {
+            if *cell == CellType::Exit {
+                *cell = CellType::Path;
+            }
+        }
+        grid[y][x] = CellType::Exit;
+    }
+}
+
+/// Turn up to `count` random path cells into `cell`, e.g. to scatter pickups around a maze.
+pub fn scatter_cells(grid: &mut [Vec<CellType>], cell: CellType, count: usize, rng: &mut impl Rng) {
+    let mut paths = Vec::new();
+    for (y, row) in grid.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            if *c == CellType::Path {
+                paths.push((x, y));
+            }
+        }
+    }
+    for &(x, y) in paths.choose_multiple(rng, count) {
+        grid[y][x] = cell;
+    }
+}
+
+/// the range of bounce distances of a mushroom placed by [`scatter_weighted`].
+pub const MUSHROOM_BOUNCE: std::ops::RangeInclusive<u8> = 2..=4;
+
+/// How likely each hazard and pickup is to be picked for a cell by [`scatter_weighted`]. Only the
+/// ratios matter, see [`CellWeights::normalised`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellWeights {
+    /// sound traps.
+    pub trap: f32,
+    pub quicksand: f32,
+    pub heart: f32,
+    pub mushroom: f32,
+    pub time_stop: f32,
+}
+
+impl Default for CellWeights {
+    fn default() -> Self {
+        Self {
+            trap: 0.25,
+            quicksand: 0.25,
+            heart: 0.15,
+            mushroom: 0.25,
+            time_stop: 0.1,
+        }
+    }
+}
+
+impl CellWeights {
+    /// The same weights scaled to sum to 1.0. Negative weights count as 0.0, and weights that add
+    /// up to nothing stay all 0.0.
+    pub fn normalised(&self) -> Self {
+        let weights = [self.trap, self.quicksand, self.heart, self.mushroom, self.time_stop].map(|w| w.max(0.0));
+        let total: f32 = weights.iter().sum();
+        let share = |w: f32| if total > 0.0 { w / total } else { 0.0 };
+        Self {
+            trap: share(weights[0]),
+            quicksand: share(weights[1]),
+            heart: share(weights[2]),
+            mushroom: share(weights[3]),
+            time_stop: share(weights[4]),
+        }
+    }
+}
+
+/// Turn up to `count` random path cells into hazards and pickups, drawing the kind of each from
+/// `weights`. A kind with a weight of 0.0 is never placed.
+pub fn scatter_weighted(grid: &mut [Vec<CellType>], weights: &CellWeights, count: usize, rng: &mut impl Rng) {
+    let weights = weights.normalised();
+    let kinds = [
+        (Some(CellType::SoundTrap), weights.trap),
+        (Some(CellType::Quicksand), weights.quicksand),
+        (Some(CellType::Heart), weights.heart),
+        (None, weights.mushroom),
+        (Some(CellType::TimeStop), weights.time_stop),
+    ];
+    for _ in 0..count {
+        let Ok(&(kind, _)) = kinds.choose_weighted(rng, |(_, weight)| *weight) else { return };
+        // mushrooms each get their own bounce
+        let cell = kind.unwrap_or_else(|| CellType::Mushroom(rng.random_range(MUSHROOM_BOUNCE)));
+        scatter_cells(grid, cell, 1, rng);
+    }
+}
+
+/// Where to hang a torch for every cell that `lit` picks out: on the first wall beside it, or on
+/// the cell itself if it has no wall next to it.
+pub fn torch_spots(grid: &[Vec<CellType>], lit: impl Fn(CellType) -> bool) -> Vec<(usize, usize)> {
+    let flat = FlatGrid::from_rows(grid);
+    let mut spots = Vec::new();
+    for (i, cell) in flat.cells.iter().enumerate() {
+        if !lit(*cell) {
+            continue;
+        }
+        let (x, y) = (i % flat.width, i / flat.width);
+        let spot = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| flat.step(x, y, direction, 1))
+            .find(|&(nx, ny)| flat.get(nx, ny) == CellType::Wall)
+            .unwrap_or((x, y));
+        if !spots.contains(&spot) {
+            spots.push(spot);
+        }
+    }
+    spots
+}
+
+/// Turn up to `count` walls inside the border that stand next to an open cell into lava.
+pub fn place_lava(grid: &mut [Vec<CellType>], count: usize, rng: &mut impl Rng) {
+    let flat = FlatGrid::from_rows(grid);
+    let mut walls = Vec::new();
+    for y in 1..flat.height.saturating_sub(1) {
+        for x in 1..flat.width.saturating_sub(1) {
+            let beside_path = Direction::ALL
+                .into_iter()
+                .filter_map(|direction| flat.step(x, y, direction, 1))
+                .any(|(nx, ny)| flat.get(nx, ny).is_walkable());
+            if flat.get(x, y) == CellType::Wall && beside_path {
+                walls.push((x, y));
+            }
+        }
+    }
+    for &(x, y) in walls.choose_multiple(rng, count) {
+        grid[y][x] = CellType::Lava;
+    }
+}
+
+/// Scatter up to `count` mines over path cells without cutting the route from `from` to `to`:
+/// after each placement the route is checked with mines counted as walls, and a mine that blocks
+/// it is moved somewhere else.
+pub fn place_mines(grid: &mut [Vec<CellType>], count: usize, from: (usize, usize), to: (usize, usize), rng: &mut impl Rng) {
+    let mut flat = FlatGrid::from_rows(grid);
+    let candidates: Vec<usize> = (0..flat.cells.len()).filter(|i| flat.cells[*i] == CellType::Path).collect();
+    let target = flat.index(to.0, to.1);
+
+    let mut placed = 0;
+    for _ in 0..count * 20 {
+        if placed == count {
+            break;
+        }
+        let Some(&i) = candidates.choose(rng) else { break };
+        if flat.cells[i] != CellType::Path {
+            continue;
+        }
+        flat.cells[i] = CellType::Mine;
+        let route = flat.distances_through(from, |cell| cell.is_walkable() && cell != CellType::Mine);
+        if route[target].is_some() {
+            placed += 1;
+        } else {
+            flat.cells[i] = CellType::Path;
+        }
+    }
+
+    for (row, flat_row) in grid.iter_mut().zip(flat.cells.chunks(flat.width.max(1))) {
+        row.copy_from_slice(flat_row);
+    }
+}
+
+/// Turn up to `count` path cells into wind blowing towards an open neighbour. A wind cell only
+/// lets the player out one way, so a cell is only used if every other open cell can still be
+/// walked to from `from` without going through any wind at all.
+pub fn place_wind(grid: &mut [Vec<CellType>], count: usize, from: (usize, usize), rng: &mut impl Rng) {
+    let mut flat = FlatGrid::from_rows(grid);
+    let candidates: Vec<usize> = (0..flat.cells.len()).filter(|i| flat.cells[*i] == CellType::Path).collect();
+    let calm = |cell: CellType| cell.is_walkable() && !matches!(cell, CellType::Wind(_) | CellType::ArrowTile(_));
+
+    let mut placed = 0;
+    for _ in 0..count * 20 {
+        if placed == count {
+            break;
+        }
+        let Some(&i) = candidates.choose(rng) else { break };
+        if flat.cells[i] != CellType::Path {
+            continue;
+        }
+        let (x, y) = (i % flat.width, i / flat.width);
+        let open: Vec<Direction> = Direction::ALL
+            .into_iter()
+            .filter(|d| flat.step(x, y, *d, 1).is_some_and(|(nx, ny)| calm(flat.get(nx, ny))))
+            .collect();
+        let Some(&direction) = open.choose(rng) else { continue };
+
+        flat.cells[i] = CellType::Wind(direction);
+        let reach = flat.distances_through(from, calm);
+        let stranded = flat.cells.iter().zip(&reach).any(|(cell, distance)| calm(*cell) && distance.is_none());
+        if stranded {
+            flat.cells[i] = CellType::Path;
+        } else {
+            placed += 1;
+        }
+    }
+
+    for (row, flat_row) in grid.iter_mut().zip(flat.cells.chunks(flat.width.max(1))) {
+        row.copy_from_slice(flat_row);
+    }
+}
+
+/// Turn up to `count` path cells into arrows pointing at an open neighbour. Like wind, an arrow only
+/// lets the player out one way, so a cell is only used if every other open cell can still be walked
+/// to from `from` without stepping on an arrow or wind.
+pub fn place_arrows(grid: &mut [Vec<CellType>], count: usize, from: (usize, usize), rng: &mut impl Rng) {
+    let mut flat = FlatGrid::from_rows(grid);
+    let candidates: Vec<usize> = (0..flat.cells.len()).filter(|i| flat.cells[*i] == CellType::Path).collect();
+    let calm = |cell: CellType| cell.is_walkable() && !matches!(cell, CellType::Wind(_) | CellType::ArrowTile(_));
+
+    let mut placed = 0;
+    for _ in 0..count * 20 {
+        if placed == count {
+            break;
+        }
+        let Some(&i) = candidates.choose(rng) else { break };
+        if flat.cells[i] != CellType::Path {
+            continue;
+        }
+        let (x, y) = (i % flat.width, i / flat.width);
+        let open: Vec<Direction> = Direction::ALL
+            .into_iter()
+            .filter(|d| flat.step(x, y, *d, 1).is_some_and(|(nx, ny)| calm(flat.get(nx, ny))))
+            .collect();
+        let Some(&direction) = open.choose(rng) else { continue };
+
+        flat.cells[i] = CellType::ArrowTile(direction);
+        let reach = flat.distances_through(from, calm);
+        let stranded = flat.cells.iter().zip(&reach).any(|(cell, distance)| calm(*cell) && distance.is_none());
+        if stranded {
+            flat.cells[i] = CellType::Path;
+        } else {
+            placed += 1;
+        }
+    }
+
+    for (row, flat_row) in grid.iter_mut().zip(flat.cells.chunks(flat.width.max(1))) {
+        row.copy_from_slice(flat_row);
+    }
+}
+
+/// Turn up to `count` path cells into crumbling floor, only where the pit each leaves behind would
+/// not cut the player off from anything reachable from `from`.
+pub fn place_crumble(grid: &mut [Vec<CellType>], count: usize, from: (usize, usize), rng: &mut impl Rng) {
+    let mut flat = FlatGrid::from_rows(grid);
+    let candidates: Vec<usize> = (0..flat.cells.len()).filter(|i| flat.cells[*i] == CellType::Path).collect();
+    let solid = |cell: CellType| cell.is_walkable() && cell != CellType::Crumble;
+
+    let mut placed = 0;
+    for _ in 0..count * 20 {
+        if placed == count {
+            break;
+        }
+        let Some(&i) = candidates.choose(rng) else { break };
+        if flat.cells[i] != CellType::Path {
+            continue;
+        }
+        flat.cells[i] = CellType::Crumble;
+        let reach = flat.distances_through(from, solid);
+        let stranded = flat.cells.iter().zip(&reach).any(|(cell, distance)| solid(*cell) && distance.is_none());
+        if stranded {
+            flat.cells[i] = CellType::Path;
+        } else {
+            placed += 1;
+        }
+    }
+
+    for (row, flat_row) in grid.iter_mut().zip(flat.cells.chunks(flat.width.max(1))) {
+        row.copy_from_slice(flat_row);
+    }
+}
+
+/// Turn up to `count` straight runs of path into slopes sliding south, towards the far side of the
+/// maze from the start. Each run ends above a plain path for the slide to stop on, and a run that
+/// would cut the player off from `from` (since a slope cannot be climbed) is tried somewhere else.
+pub fn place_slopes(grid: &mut [Vec<CellType>], count: usize, from: (usize, usize), rng: &mut impl Rng) {
+    const LONGEST_SLOPE: usize = 3;
+    let mut flat = FlatGrid::from_rows(grid);
+    let candidates: Vec<usize> = (0..flat.cells.len()).filter(|i| flat.cells[*i] == CellType::Path).collect();
+    // wind and arrows only let the player out one way too, so they do not count as a way round a slope
+    let flat_ground = |cell: CellType| {
+        cell.is_walkable() && !matches!(cell, CellType::SlipperySlope(_) | CellType::Wind(_) | CellType::ArrowTile(_))
+    };
+
+    let mut placed = 0;
+    for _ in 0..count * 20 {
+        if placed == count {
+            break;
+        }
+        let Some(&i) = candidates.choose(rng) else { break };
+        // the path cells straight down from here, and the one more the slide stops on
+        let (x, y) = (i % flat.width, i / flat.width);
+        let run: Vec<(usize, usize)> = (0..=LONGEST_SLOPE as i32)
+            .map_while(|steps| flat.step(x, y, Direction::South, steps))
+            .take_while(|(x, y)| flat.get(*x, *y) == CellType::Path)
+            .collect();
+        if run.len() < 3 {
+            continue;
+        }
+        let slope = &run[..run.len() - 1];
+
+        for (x, y) in slope {
+            flat.set(*x, *y, CellType::SlipperySlope(Direction::South));
+        }
+        let reach = flat.distances_through(from, flat_ground);
+        let stranded = flat.cells.iter().zip(&reach).any(|(cell, distance)| flat_ground(*cell) && distance.is_none());
+        if stranded {
+            for (x, y) in slope {
+                flat.set(*x, *y, CellType::Path);
+            }
+        } else {
+            placed += 1;
+        }
+    }
+
+    for (row, flat_row) in grid.iter_mut().zip(flat.cells.chunks(flat.width.max(1))) {
+        row.copy_from_slice(flat_row);
+    }
+}
+
+/// Put up to `count` gates across the way from `from` to `to`, each with its button as far from the
+/// gate as the player can get from `from` with every gate still shut. Opening a gate means a detour
+/// away from it and back. A gate that would shut the player away from every place for its button, or
+/// from an earlier button, is tried somewhere else. Ids are handed out from `first_id` on; returns the
+/// next unused one.
+///
+/// What the player can reach is worked out once, as a [`ReachabilityGraph`] kept up to date as gates
+/// are tried, so checking the buttons around a gate is a lookup for each instead of a search.
+pub fn place_buttons(grid: &mut [Vec<CellType>], count: usize, first_id: u8, from: (usize, usize), to: (usize, usize), rng: &mut impl Rng) -> u8 {
+    let mut flat = FlatGrid::from_rows(grid);
+    let mut reach = ReachabilityGraph::build(grid);
+    let opened = |cell: CellType| cell.is_walkable() || matches!(cell, CellType::LinkedWall(_));
+    let mut next_id = first_id;
+
+    for _ in 0..count * 20 {
+        if next_id - first_id >= count as u8 {
+            break;
+        }
+        // the way there once every gate is open, with a gate somewhere along its middle half
+        let Some(route) = flat.path_through(from, to, opened) else { break };
+        let middle = route.iter().skip(route.len() / 4).take(route.len() / 2);
+        let spots: Vec<(usize, usize)> = middle.copied().filter(|&(x, y)| flat.get(x, y) == CellType::Path).collect();
+        let Some(&(gx, gy)) = spots.choose(rng) else { break };
+        let id = ButtonId(next_id);
+        flat.set(gx, gy, CellType::LinkedWall(id));
+        grid[gy][gx] = CellType::LinkedWall(id);
+        reach.shut(grid, (gx, gy));
+
+        let cell_at = |i: usize| (i % flat.width, i / flat.width);
+        let stranded = (0..flat.cells.len()).any(|i| matches!(flat.cells[i], CellType::Button(_)) && !reach.same_component(from, cell_at(i)));
+        let button = if stranded {
+            None
+        } else {
+            let from_gate = flat.distances_through((gx, gy), |cell| cell.is_walkable());
+            (0..flat.cells.len())
+                .filter(|&i| flat.cells[i] == CellType::Path && reach.same_component(from, cell_at(i)))
+                .filter(|&i| !route.contains(&cell_at(i)))
+                .max_by_key(|&i| from_gate[i])
+        };
+        match button {
+            Some(i) => {
+                flat.cells[i] = CellType::Button(id);
+                let (x, y) = cell_at(i);
+                grid[y][x] = CellType::Button(id);
+                next_id += 1;
+            }
+            None => {
+                flat.set(gx, gy, CellType::Path);
+                grid[gy][gx] = CellType::Path;
+                reach.open(grid, (gx, gy));
+            }
+        }
+    }
+    next_id
+}
+
+/// the share of dead-end walls that [`place_illusions`] turns into illusions.
+pub const ILLUSION_FRACTION: f64 = 0.1;
+
+/// Turn about `fraction` of the walls at the end of dead ends into illusions. Only walls with an open
+/// cell on their far side count, so every illusion is a short cut the player can find. Walls ending
+/// dead ends off the route from `from` to `to` are picked more often, leaving the way that already
+/// solves the maze mostly alone.
+pub fn place_illusions(grid: &mut [Vec<CellType>], fraction: f64, from: (usize, usize), to: (usize, usize), rng: &mut impl Rng) {
+    let flat = FlatGrid::from_rows(grid);
+    let open_neighbours = |x: usize, y: usize| {
+        Direction::ALL
+            .into_iter()
+            .filter_map(|direction| flat.step(x, y, direction, 1))
+            .filter(|&(nx, ny)| flat.get(nx, ny).is_walkable())
+            .count()
+    };
+    let route = flat.path_through(from, to, |cell| cell.is_walkable()).unwrap_or_default();
+
+    // (wall, whether the dead end it closes is on the route)
+    let mut candidates: Vec<((usize, usize), bool)> = Vec::new();
+    for y in 1..flat.height.saturating_sub(1) {
+        for x in 1..flat.width.saturating_sub(1) {
+            if flat.get(x, y) != CellType::Path || open_neighbours(x, y) != 1 {
+                continue;
+            }
+            for direction in Direction::ALL {
+                let wall = flat.step(x, y, direction, 1);
+                let beyond = flat.step(x, y, direction, 2);
+                if let (Some((wx, wy)), Some((bx, by))) = (wall, beyond)
+                    && flat.get(wx, wy) == CellType::Wall
+                    && flat.get(bx, by).is_walkable()
+                    && wx > 0
+                    && wy > 0
+                    && wx < flat.width - 1
+                    && wy < flat.height - 1
+                {
+                    candidates.push(((wx, wy), route.contains(&(x, y))));
+                }
+            }
+        }
+    }
+
+    let count = (candidates.len() as f64 * fraction).round() as usize;
+    let weight = |&(_, on_route): &((usize, usize), bool)| if on_route { 1.0 } else { 4.0 };
+    if let Ok(picked) = candidates.choose_multiple_weighted(rng, count, weight) {
+        for &((x, y), _) in picked {
+            grid[y][x] = CellType::Illusion;
+        }
+    }
+}
+
+/// A problem that stops a hand-made map from being played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    MissingStart,
+    MissingExit,
+    /// the exit cannot be walked to from the start, even through every gate whose button can be
+    /// reached first.
+    Unsolvable,
+    /// gates that stay shut, because no button with their id can be reached before them.
+    GateWithoutButton(ButtonId),
+}
+
+/// Something odd about a hand-made map that still leaves it playable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// an open cell at (x, y) that cannot be walked to from the start.
+    UnreachableCell(usize, usize),
+    NoEnemies,
+    /// no hearts or items to pick up.
+    NoCollectibles,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::MissingStart => write!(f, "the map has no start"),
+            ValidationError::MissingExit => write!(f, "the map has no exit"),
+            ValidationError::Unsolvable => write!(f, "the exit cannot be reached from the start"),
+            ValidationError::GateWithoutButton(ButtonId(id)) => write!(f, "gate {} has no button that can be reached", id),
+        }
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationWarning::UnreachableCell(x, y) => write!(f, "cell ({}, {}) cannot be reached", x, y),
+            ValidationWarning::NoEnemies => write!(f, "the map has no enemies"),
+            ValidationWarning::NoCollectibles => write!(f, "the map has nothing to pick up"),
+        }
+    }
+}
+
+/// What [`validate`] found wrong with a map.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub errors: Vec<ValidationError>,
+    pub warnings: Vec<ValidationWarning>,
+}
+
+impl ValidationReport {
+    /// whether the map can be played, warnings or not.
+    pub fn is_playable(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// A line for each problem, errors first, to show the map's maker. Unreachable cells after
+    /// the first `max_cells` are counted on one line.
+    pub fn lines(&self, max_cells: usize) -> Vec<String> {
+        let mut lines: Vec<String> = self.errors.iter().map(|e| format!("Error: {}", e)).collect();
+        let (cells, others): (Vec<&ValidationWarning>, Vec<_>) = self.warnings.iter().partition(|w| matches!(w, ValidationWarning::UnreachableCell(..)));
+        lines.extend(others.iter().map(|w| format!("Warning: {}", w)));
+        lines.extend(cells.iter().take(max_cells).map(|w| format!("Warning: {}", w)));
+        if cells.len() > max_cells {
+            lines.push(format!("Warning: {} more cells cannot be reached", cells.len() - max_cells));
+        }
+        lines
+    }
+}
+
+/// Check a hand-made map for a start, a reachable exit, gates that can be opened, cells that cannot
+/// be reached and something to pick up. A gate counts as open once its button can be walked to.
+/// Enemies are not part of the grid, so the caller adds [`ValidationWarning::NoEnemies`] itself.
+pub fn validate(grid: &[Vec<CellType>]) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let find = |wanted: CellType| grid.iter().enumerate().find_map(|(y, row)| row.iter().position(|c| *c == wanted).map(|x| (x, y)));
+    let start = find(CellType::Start);
+    let exit = find(CellType::Exit);
+
+    if start.is_none() {
+        report.errors.push(ValidationError::MissingStart);
+    }
+    if exit.is_none() {
+        report.errors.push(ValidationError::MissingExit);
+    }
+
+    if let Some(start) = start {
+        let flat = FlatGrid::from_rows(grid);
+        let (pressed, distances) = flat.distances_with_buttons(start);
+        let reached = |(x, y): (usize, usize)| distances[flat.index(x, y)].is_some();
+        if let Some(exit) = exit
+            && !reached(exit)
+        {
+            report.errors.push(ValidationError::Unsolvable);
+        }
+        let shut: BTreeSet<ButtonId> = flat
+            .cells
+            .iter()
+            .filter_map(|cell| match cell {
+                CellType::LinkedWall(id) if !pressed.contains(id) => Some(*id),
+                _ => None,
+            })
+            .collect();
+        report.errors.extend(shut.into_iter().map(ValidationError::GateWithoutButton));
+        for (y, row) in grid.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if cell.is_walkable() && !reached((x, y)) {
+                    report.warnings.push(ValidationWarning::UnreachableCell(x, y));
+                }
+            }
+        }
+    }
+
+    if !grid.iter().flatten().any(|c| *c == CellType::Heart) {
+        report.warnings.push(ValidationWarning::NoCollectibles);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn every_cell_round_trips_through_json() {
+        let mut cells = vec![
+            CellType::Wall, CellType::Path, CellType::Start, CellType::Exit, CellType::Ladder(LadderDirection::Up),
+            CellType::Ladder(LadderDirection::Down), CellType::Water, CellType::Heart, CellType::Mushroom(3), CellType::Mine,
+            CellType::Illusion, CellType::Altar, CellType::Quicksand, CellType::Vendor, CellType::MapFragment, CellType::Lava,
+            CellType::TimeStop, CellType::SoundTrap, CellType::Button(ButtonId(2)), CellType::LinkedWall(ButtonId(2)),
+            CellType::Warp, CellType::Echo, CellType::Wormhole, CellType::Crumble, CellType::Pit,
+        ];
+        for direction in Direction::ALL {
+            cells.extend([CellType::Wind(direction), CellType::SlipperySlope(direction), CellType::ArrowTile(direction)]);
+        }
+        for mode in [MoveMode::Normal, MoveMode::Ghost, MoveMode::Giant, MoveMode::Tiny] {
+            cells.push(CellType::Transformer(mode));
+        }
+        let json = serde_json::to_string(&cells).unwrap();
+        assert!(json.starts_with(r#"["Wall","Path","#), "{}", json);
+        assert!(json.contains(r#"{"Ladder":"Down"}"#), "{}", json);
+        assert_eq!(serde_json::from_str::<Vec<CellType>>(&json).unwrap(), cells);
+
+        let json = serde_json::to_string(&MazeAlgorithm::ALL).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<MazeAlgorithm>>(&json).unwrap(), MazeAlgorithm::ALL);
+    }
+
+    // slow in a debug build, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn huge_mazes_generate_and_solve() {
+        let grid = generate_flat_maze(1001, 1001, &mut StdRng::seed_from_u64(1));
+        assert_eq!((grid.width, grid.height), (1001, 1001));
+        assert!(grid.solution_length().is_some());
+    }
+
+    #[test]
+    fn mirror_mazes_are_symmetric_and_solvable() {
+        for seed in 0..20 {
+            let mut grid = generate_mirror_maze(31, 21, &mut StdRng::seed_from_u64(seed));
+            assert!(FlatGrid::from_rows(&grid).solution_length().is_some(), "seed {}", seed);
+
+            // the start and exit are each other's mirror image, as plain paths
+            for cell in grid.iter_mut().flatten() {
+                if matches!(cell, CellType::Start | CellType::Exit) {
+                    *cell = CellType::Path;
+                }
+            }
+            let w = grid[0].len();
+            for row in &grid {
+                for x in 0..w {
+                    assert_eq!(row[x], row[w - 1 - x], "seed {}", seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backtracker_mazes_are_perfect_and_room_dungeons_are_not() {
+        for seed in 0..20 {
+            assert!(is_perfect_maze(&generate_maze(31, 21, &mut StdRng::seed_from_u64(seed))), "seed {}", seed);
+
+            let mut grid = vec![vec![CellType::Wall; 41]; 29];
+            generate_with_rooms(&mut grid, 6, 3, 7, &mut StdRng::seed_from_u64(seed));
+            assert!(!is_perfect_maze(&grid), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_maze_every_time() {
+        let seeded = StdRng::seed_from_u64;
+        for seed in 0..5 {
+            for algorithm in MazeAlgorithm::ALL {
+                assert_eq!(generate(algorithm, 31, 21, &mut seeded(seed)), generate(algorithm, 31, 21, &mut seeded(seed)), "{:?} seed {}", algorithm, seed);
+            }
+            assert_eq!(generate_mirror_maze(31, 21, &mut seeded(seed)), generate_mirror_maze(31, 21, &mut seeded(seed)));
+            assert_eq!(FloorPlan::plan(41, 29, 6, 3, 7, &mut seeded(seed)).rooms, FloorPlan::plan(41, 29, 6, 3, 7, &mut seeded(seed)).rooms);
+
+            let grid = generate_maze(31, 21, &mut seeded(seed));
+            let start = first_walkable(&grid).unwrap();
+            let checkpoints = place_checkpoints(&grid, start, 3, &mut seeded(seed));
+            assert_eq!(checkpoints.len(), 3);
+            assert_eq!(checkpoints, place_checkpoints(&grid, start, 3, &mut seeded(seed)));
+        }
+        assert_ne!(generate_maze(31, 21, &mut seeded(1)), generate_maze(31, 21, &mut seeded(2)));
+    }
+
+    // rows of `#` walls, `S` the start, `E` the exit, `b`/`c` buttons 1 and 2, `=`/`+` their gates
+    // and anything else a path
+    fn rows(rows: &[&str]) -> Vec<Vec<CellType>> {
+        rows.iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| match c {
+                        '#' => CellType::Wall,
+                        'S' => CellType::Start,
+                        'E' => CellType::Exit,
+                        'b' => CellType::Button(ButtonId(1)),
+                        'c' => CellType::Button(ButtonId(2)),
+                        '=' => CellType::LinkedWall(ButtonId(1)),
+                        '+' => CellType::LinkedWall(ButtonId(2)),
+                        _ => CellType::Path,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_placed_gate_has_a_button_the_player_can_reach() {
+        for seed in 0..10 {
+            let mut grid = generate_maze(31, 21, &mut StdRng::seed_from_u64(seed));
+            let flat = FlatGrid::from_rows(&grid);
+            let at = |wanted: CellType| flat.cells.iter().position(|c| *c == wanted).map(|i| (i % flat.width, i / flat.width)).unwrap();
+            let (start, exit) = (at(CellType::Start), at(CellType::Exit));
+            let next_id = place_buttons(&mut grid, 3, 1, start, exit, &mut StdRng::seed_from_u64(seed));
+
+            let reach = ReachabilityGraph::build(&grid);
+            let cells: Vec<((usize, usize), CellType)> = grid.iter().enumerate().flat_map(|(y, row)| row.iter().enumerate().map(move |(x, c)| ((x, y), *c))).collect();
+            for id in 1..next_id {
+                let button = cells.iter().find(|(_, c)| *c == CellType::Button(ButtonId(id))).unwrap_or_else(|| panic!("seed {}: no button {}", seed, id));
+                assert!(reach.same_component(start, button.0), "seed {}: button {} is shut away", seed, id);
+                assert!(cells.iter().any(|(_, c)| *c == CellType::LinkedWall(ButtonId(id))));
+            }
+            assert_eq!(validate(&grid).errors, vec![], "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn a_gate_counts_as_open_once_its_button_can_be_reached() {
+        // the button for the gate to the exit is behind a second gate, whose button is by the start
+        let report = validate(&rows(&["########", "#Sc+b=E#", "########"]));
+        assert_eq!(report.errors, vec![]);
+        assert!(!report.warnings.iter().any(|w| matches!(w, ValidationWarning::UnreachableCell(..))), "{:?}", report.warnings);
+
+        // the only button is behind its own gate
+        let report = validate(&rows(&["######", "#S=bE#", "######"]));
+        assert_eq!(report.errors, vec![ValidationError::Unsolvable, ValidationError::GateWithoutButton(ButtonId(1))]);
+        assert!(report.warnings.contains(&ValidationWarning::UnreachableCell(3, 1)));
+
+        // a gate with no button at all, off to the side
+        let report = validate(&rows(&["#####", "#S.E#", "##+##", "#####"]));
+        assert_eq!(report.errors, vec![ValidationError::GateWithoutButton(ButtonId(2))]);
+    }
+
+    #[test]
+    fn a_report_lists_errors_first_and_sums_up_the_cells() {
+        let report = ValidationReport {
+            errors: vec![ValidationError::MissingExit],
+            warnings: (0..5).map(|x| ValidationWarning::UnreachableCell(x, 1)).chain([ValidationWarning::NoEnemies]).collect(),
+        };
+        assert_eq!(
+            report.lines(2),
+            vec![
+                "Error: the map has no exit",
+                "Warning: the map has no enemies",
+                "Warning: cell (0, 1) cannot be reached",
+                "Warning: cell (1, 1) cannot be reached",
+                "Warning: 3 more cells cannot be reached",
+            ]
+        );
+    }
+
+    // a corridor with the start at one end and the exit at the other
+    fn corridor(length: usize) -> Vec<Vec<CellType>> {
+        let mut middle = vec![CellType::Path; length];
+        middle[0] = CellType::Start;
+        middle[length - 1] = CellType::Exit;
+        vec![vec![CellType::Wall; length], middle, vec![CellType::Wall; length]]
+    }
+
+    #[test]
+    fn a_straight_corridor_is_not_interesting() {
+        assert!(!FlatGrid::from_rows(&corridor(21)).is_interesting());
+    }
+
+    #[test]
+    fn most_backtracker_mazes_are_interesting() {
+        // the path fraction should turn down the odd dull maze, not most of them
+        let interesting = (0..40)
+            .filter(|seed| FlatGrid::from_rows(&generate_maze(41, 29, &mut StdRng::seed_from_u64(*seed))).is_interesting())
+            .count();
+        assert!(interesting >= 30, "only {} of 40 were interesting", interesting);
+        assert!(FlatGrid::from_rows(&generate(MazeAlgorithm::Backtracker, 41, 29, &mut StdRng::seed_from_u64(0))).is_interesting());
+    }
+
+    #[test]
+    fn generation_gives_up_after_the_last_attempt() {
+        let mut attempts = 0;
+        let grid = first_interesting(|| {
+            attempts += 1;
+            corridor(21)
+        });
+        assert_eq!(attempts, MAX_GENERATION_ATTEMPTS);
+        assert_eq!(grid, corridor(21));
+    }
+
+    // how many passages run east-west and how many north-south: the open cells between two cells at
+    // odd coordinates
+    fn links(grid: &FlatGrid) -> (usize, usize) {
+        let mut links = (0, 0);
+        for y in 1..grid.height - 1 {
+            for x in 1..grid.width - 1 {
+                if grid.get(x, y).is_walkable() {
+                    match (x % 2, y % 2) {
+                        (0, 1) => links.0 += 1,
+                        (1, 0) => links.1 += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        links
+    }
+
+    #[test]
+    fn a_horizontal_bias_runs_more_passages_across() {
+        let (mut across, mut down) = (0, 0);
+        for seed in 0..10 {
+            let grid = generate_biased_maze(41, 41, (4.0, 1.0), &mut StdRng::seed_from_u64(seed));
+            assert!(is_perfect_maze(&grid.to_rows()), "seed {}", seed);
+            let (h, v) = links(&grid);
+            across += h;
+            down += v;
+        }
+        assert!(across > down * 2, "{} across, {} down", across, down);
+
+        let (across, down) = links(&generate_biased_maze(41, 41, (1.0, 4.0), &mut StdRng::seed_from_u64(0)));
+        assert!(down > across, "{} across, {} down", across, down);
+    }
+
+    #[test]
+    fn a_zero_weight_is_never_placed() {
+        let weights = CellWeights { trap: 1.0, quicksand: 0.0, heart: 2.0, mushroom: 0.0, time_stop: 0.0 };
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut grid = generate_maze(41, 29, &mut rng);
+            scatter_weighted(&mut grid, &weights, 100, &mut rng);
+            let cells: Vec<CellType> = grid.into_iter().flatten().collect();
+            assert!(cells.contains(&CellType::SoundTrap) && cells.contains(&CellType::Heart));
+            assert!(!cells.iter().any(|c| matches!(c, CellType::Quicksand | CellType::Mushroom(_) | CellType::TimeStop)));
+        }
+    }
+
+    #[test]
+    fn weights_normalise_to_one() {
+        let weights = CellWeights { trap: 2.0, quicksand: -1.0, heart: 1.0, mushroom: 1.0, time_stop: 0.0 }.normalised();
+        assert_eq!(weights, CellWeights { trap: 0.5, quicksand: 0.0, heart: 0.25, mushroom: 0.25, time_stop: 0.0 });
+        let none = CellWeights { trap: 0.0, quicksand: 0.0, heart: 0.0, mushroom: 0.0, time_stop: 0.0 };
+        assert_eq!(none.normalised(), none);
+    }
+}
@@ -1,27 +1,100 @@
 use raylib::prelude::*;
 
-use crate::menu_scene::WinScene;
+use crate::altar_scene::{AltarScene, Trade};
+use crate::debug_console::{ConsoleCommand, DebugConsole};
+use crate::enemy::{Enemy, EnemyKind, MinotaurState};
+use crate::events::{pick_event, EventType, RandomEvent, EVENT_ROLL_INTERVAL};
+use crate::difficulty::{RunStats, SPAWN_SAFE_RADIUS};
+use crate::maze_gen::{
+    corner_region, distances_from, open_corners, reachable_corners, line_of_sight, place_buttons, place_illusions, place_crumble, place_lava, place_slopes, place_wind, place_arrows, scatter_weighted, ILLUSION_FRACTION, move_exit_to_top, path_through, place_mines, scatter_cells, FlatGrid, place_checkpoints, route_length, torch_spots, validate, ButtonId, CellType, LadderDirection, MazeAlgorithm, MoveMode, ValidationReport, ValidationWarning,
+};
+use crate::multi_floor::{FloorConnection, MultiFloorMaze};
+use crate::narrator::Narrator;
+use crate::menu_scene::{generated_level, GameOverScene, TimeTrialSummaryScene, WinScene};
 use crate::scenes::{Scene, SceneSwitch};
-use crate::game_data::GameData;
-use crate::{is_floor_tile, is_wall_tile};
+use crate::shop_scene::{ShopItem, ShopModal};
+use crate::game_data::{GameData, StarObjectives, HEALTH_PER_HEART, PAR_STAR_ALLOWANCE};
+use crate::items::Item;
+use crate::entity::{EntityId, EntityKind, EntityRegistry};
+use crate::particles::{burst, Particle};
+use crate::pathfinding::{dijkstra_from, dijkstra_from_exit, intercept_step, solve_astar, UNREACHABLE};
+use crate::projectile::Projectile;
+use crate::replay::{save_replay, InputEvent, ReplayData, REPLAY_PATH};
+use crate::screenshot::{ScreenshotMode, NOTICE_DURATION};
+use crate::settings::CoOpMode;
+use crate::theme::CustomTheme;
+use crate::audio::WallCues;
+use crate::utils::Direction;
+use crate::{debug_log, is_floor_tile, is_wall_tile, maze_size};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Read;
-use serde::Deserialize;
+use std::io::{Read, Write};
+use std::path::Path;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MapData {
     pub grid_w: usize,
     pub grid_h: usize,
     pub tile_size_px: i32,
     pub tiles: Vec<Vec<i32>>,
     pub entities: Vec<MapEntity>,
+    /// the cells themselves, each as serde writes a [`CellType`] (`"Wall"`, `{"Ladder": "Down"}`).
+    /// When a map has them they are played as they are instead of being worked out from `tiles`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cells: Option<Vec<Vec<CellType>>>,
+    /// the map's own colours, if it has any.
+    #[serde(default)]
+    pub theme: Option<CustomTheme>,
+    /// story text shown once each, when the player first steps on its cell.
+    #[serde(default)]
+    pub messages: Vec<MapMessage>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapMessage {
+    pub x: usize,
+    pub y: usize,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MapEntity {
     pub kind: String,
     pub x: usize,
     pub y: usize,
+    /// set when the entity is a collectible registered with the maze.
+    #[serde(skip)]
+    pub id: EntityId,
+}
+
+impl MapData {
+    /// an untextured map with no tiles or entities, used under generated mazes.
+    pub fn blank(grid_w: usize, grid_h: usize, tile_size_px: i32) -> Self {
+        Self {
+            grid_w,
+            grid_h,
+            tile_size_px,
+            tiles: vec![vec![-1; grid_w]; grid_h],
+            entities: Vec::new(),
+            cells: None,
+            theme: None,
+            messages: Vec::new(),
+        }
+    }
+}
+
+/// Where a maze's layout comes from.
+pub enum MazeSource {
+    /// a hand-made map file from the MapMaker tool.
+    File(String),
+    /// a single floor stored in the compact `.mzb` format, see [`crate::maze_format`].
+    Binary(String),
+    /// generated floors, one or two linked by a ladder, see [`MultiFloorMaze`].
+    Generated { width: usize, height: usize, algorithm: MazeAlgorithm, seed: u64, floors: usize },
 }
 
 
@@ -32,208 +105,3968 @@ pub fn load_map(path: &str) -> MapData {
     serde_json::from_str(&contents).expect("Invalid map.json")
 }
 
+/// Write a map as JSON that [`load_map`] reads back.
+pub fn save_map(path: &str, map: &MapData) -> std::io::Result<()> {
+    let contents = serde_json::to_string_pretty(map)?;
+    std::fs::write(path, contents)
+}
+
+/// where F5 saves the run and F8 loads it back from.
+pub const QUICKSAVE_PATH: &str = "quicksave.json";
+
+/// Where a run through a maze has got to, so it can be saved and picked up again in the same level.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MazeState {
+    /// the [`MazeScene::level_key`] of the level it was saved in.
+    pub level: String,
+    pub on_lower_floor: bool,
+    pub player: (usize, usize),
+    pub elapsed: f32,
+    /// the map's messages the player has not stepped on yet.
+    pub messages: Vec<MapMessage>,
+}
+
+impl MazeState {
+    /// read a saved state. A missing or unreadable file gives none.
+    pub fn load(path: &str) -> Option<Self> {
+        std::fs::read_to_string(path).ok().and_then(|contents| serde_json::from_str(&contents).ok())
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)
+    }
+}
+
+// A seed for a maze file, the same every time for the same path (FNV-1a over its bytes).
+fn path_seed(path: &str) -> u64 {
+    path.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+// A heart icon: two round lobes over a triangle pointing down, `size` pixels wide.
+fn draw_heart(d: &mut impl RaylibDraw, center: Vector2, size: f32, color: Color) {
+    let r = size / 4.0;
+    d.draw_circle_v(Vector2::new(center.x - r, center.y - r / 2.0), r, color);
+    d.draw_circle_v(Vector2::new(center.x + r, center.y - r / 2.0), r, color);
+    d.draw_triangle(
+        Vector2::new(center.x - size / 2.0, center.y - r / 4.0),
+        Vector2::new(center.x, center.y + size / 2.0),
+        Vector2::new(center.x + size / 2.0, center.y - r / 4.0),
+        color,
+    );
+}
+
+/// Check a hand-made map with [`validate`], counting its enemy, item and treasure entities too.
+pub fn validate_map(map: &MapData, grid: &[Vec<CellType>]) -> ValidationReport {
+    let mut report = validate(grid);
+    if !map.entities.iter().any(|e| EnemyKind::from_map_kind(&e.kind).is_some()) {
+        report.warnings.push(ValidationWarning::NoEnemies);
+    }
+    if map.entities.iter().any(|e| e.kind == "treasure" || Item::from_map_kind(&e.kind).is_some()) {
+        report.warnings.retain(|w| *w != ValidationWarning::NoCollectibles);
+    }
+    report
+}
+
+// the colour the player is drawn in and their size as a share of a normal player's, moving as `mode`
+fn mode_look(mode: MoveMode) -> (Color, f32) {
+    match mode {
+        MoveMode::Normal => (Color::BLUE, 1.0),
+        MoveMode::Ghost => (Color::SKYBLUE.alpha(0.5), 1.0),
+        MoveMode::Giant => (Color::DARKBLUE, 2.0),
+        MoveMode::Tiny => (Color::VIOLET, 0.5),
+    }
+}
+
+// the colour of a wall `age` frames old: dark grey when new, a little lighter when fully worn
+fn worn_wall_color(base: Color, age: u8) -> Color {
+    let wear = (age as u32 * 40 / 255) as u8;
+    Color::new(base.r.saturating_add(wear), base.g.saturating_add(wear), base.b.saturating_add(wear), 255)
+}
+
+// the gates of each button on a floor
+fn gates(grid: &[Vec<CellType>]) -> HashMap<ButtonId, Vec<(usize, usize)>> {
+    let mut gates: HashMap<ButtonId, Vec<(usize, usize)>> = HashMap::new();
+    for (y, row) in grid.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if let CellType::LinkedWall(id) = cell {
+                gates.entry(*id).or_default().push((x, y));
+            }
+        }
+    }
+    gates
+}
+
+// a flickering torch beside every altar and vendor
+fn torches(grid: &[Vec<CellType>]) -> Vec<LightSource> {
+    torch_spots(grid, |cell| matches!(cell, CellType::Altar | CellType::Vendor))
+        .into_iter()
+        .map(|(x, y)| LightSource {
+            x,
+            y,
+            radius: TORCH_RADIUS,
+            color: Color::ORANGE,
+            flicker_amplitude: TORCH_FLICKER_AMPLITUDE,
+        })
+        .collect()
+}
+
+// position of the first cell in reading order that matches
+fn position_of(grid: &[Vec<CellType>], wanted: impl Fn(&CellType) -> bool) -> Option<(usize, usize)> {
+    grid.iter().enumerate().find_map(|(y, row)| row.iter().position(&wanted).map(|x| (x, y)))
+}
+
+// The size to draw tiles of `tile_size_px` at, shrunk on a window too small to show
+// `MIN_VIEW_CELLS` of them across and down.
+fn fitted_tile_size(tile_size_px: i32, screen_width: i32, screen_height: i32) -> i32 {
+    tile_size_px.min(screen_width / MIN_VIEW_CELLS).min(screen_height / MIN_VIEW_CELLS).max(1)
+}
+
+// A loop for a minotaur to patrol: out from the exit to a random cell some way off and back again,
+// starting at the far end.
+fn minotaur_patrol(grid: &[Vec<CellType>], rng: &mut impl Rng) -> Option<Vec<(usize, usize)>> {
+    let exit = position_of(grid, |c| *c == CellType::Exit)?;
+    // measured over the cells the minotaur can walk, so the far end is never behind a mine
+    let flat = FlatGrid::from_rows(grid);
+    let distances = flat.distances_through(exit, |cell| cell.is_enemy_walkable());
+    let mut candidates = Vec::new();
+    for (y, row) in grid.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if distances[flat.index(x, y)].is_some_and(|d| MINOTAUR_PATROL_REACH.contains(&d)) && *cell == CellType::Path {
+                candidates.push((x, y));
+            }
+        }
+    }
+    let &far_end = candidates.choose(rng)?;
+
+    let mut patrol = path_through(grid, far_end, exit, |cell| cell.is_enemy_walkable())?;
+    let back: Vec<(usize, usize)> = patrol[1..patrol.len() - 1].iter().rev().copied().collect();
+    patrol.extend(back);
+    Some(patrol)
+}
+
+// The walkable layout of a hand-made map: its own cells if it has them. Otherwise empty and wall
+// tiles are walls, the player entity marks the start and the goal entity the exit.
+fn grid_from_map(map: &MapData) -> Vec<Vec<CellType>> {
+    if let Some(cells) = &map.cells {
+        return cells.clone();
+    }
+    let mut grid: Vec<Vec<CellType>> = map
+        .tiles
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&tid| if tid >= 0 && !is_wall_tile(tid) { CellType::Path } else { CellType::Wall })
+                .collect()
+        })
+        .collect();
+
+    for e in &map.entities {
+        let cell = match e.kind.as_str() {
+            "player" => CellType::Start,
+            "goal" => CellType::Exit,
+            "heart" => CellType::Heart,
+            _ => continue,
+        };
+        if let Some(c) = grid.get_mut(e.y).and_then(|row| row.get_mut(e.x)) {
+            *c = cell;
+        }
+    }
+    grid
+}
+
+/// Where the player's moves are coming from. Touch mode puts a D-pad on the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputMode {
+    Keyboard,
+    Touch,
+    Gamepad,
+}
+
+/// A rectangle of cells a map fragment has revealed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RevealedRegion {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A ghost of the player walking the way to the goal, shown for a moment after stepping on an echo.
+/// It is only drawn, and sets nothing off.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GhostPlayer {
+    pub path: Vec<(usize, usize)>,
+    /// the cell along `path` the ghost is walking from.
+    pub step: usize,
+    /// seconds until it reaches the next cell.
+    pub step_timer: f32,
+    /// seconds until the ghost fades away.
+    pub time_left: f32,
+}
+
+/// The rival in a race: it walks the shortest way from the start to the exit, gates and all, one cell
+/// every [`RIVAL_STEP_TIME`] seconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rival {
+    /// the cells of its way, each with whether it is on the lower floor.
+    pub path: Vec<(bool, usize, usize)>,
+    /// the cell along `path` it is walking from.
+    pub step: usize,
+    /// seconds until it reaches the next cell.
+    pub step_timer: f32,
+}
+
+impl Rival {
+    /// cells it still has to walk.
+    pub fn steps_left(&self) -> usize {
+        self.path.len().saturating_sub(self.step + 1)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.steps_left() == 0
+    }
+
+    /// Move on by `dt` seconds, a cell every [`RIVAL_STEP_TIME`].
+    pub fn advance(&mut self, dt: f32) {
+        self.step_timer += dt;
+        while self.step_timer >= RIVAL_STEP_TIME && !self.finished() {
+            self.step_timer -= RIVAL_STEP_TIME;
+            self.step += 1;
+        }
+    }
+}
+
+/// Someone walking the maze. The first player sets off what is in it (pickups, traps, ladders and
+/// the rest) and is the one the camera follows. In co-op a second player walks the same maze on the
+/// arrow keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Player {
+    pub x: usize,
+    pub y: usize,
+    /// what a second player is drawn in. The first is drawn in the colour of how they are moving.
+    pub color: Color,
+    /// seconds until another move is accepted, see [`crate::settings::Settings::move_cooldown`].
+    pub move_cooldown: f32,
+    /// a second player who has made it to the open exit, where they wait for the maze to end.
+    pub reached_exit: bool,
+}
+
+impl Player {
+    fn at(x: usize, y: usize, color: Color) -> Self {
+        Self { x, y, color, move_cooldown: 0.0, reached_exit: false }
+    }
+}
+
+/// A gate sliding open into the wall beside it. It still blocks the way until `progress` reaches 1.0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimatingDoor {
+    pub x: usize,
+    pub y: usize,
+    /// how far open, from 0.0 to 1.0.
+    pub progress: f32,
+    /// which way the gate slides.
+    pub direction: Direction,
+}
+
+/// A torch or other light that lets the player see the cells around it through the fog, wherever
+/// the player is. Its reach flickers by up to `flicker_amplitude` cells either way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSource {
+    pub x: usize,
+    pub y: usize,
+    pub radius: f32,
+    pub color: Color,
+    pub flicker_amplitude: f32,
+}
+
+impl LightSource {
+    /// how far the light reaches at `time` seconds. Each light flickers out of step with the others.
+    pub fn radius_at(&self, time: f32) -> f32 {
+        let phase = (self.x * 7 + self.y * 13) as f32;
+        self.radius + self.flicker_amplitude * (time * LIGHT_FLICKER_SPEED + phase).sin()
+    }
+
+    /// whether the light reaches the cell at (x, y) at `time` seconds.
+    pub fn lights(&self, x: usize, y: usize, time: f32) -> bool {
+        let dx = x as f32 - self.x as f32;
+        let dy = y as f32 - self.y as f32;
+        (dx * dx + dy * dy).sqrt() <= self.radius_at(time)
+    }
+}
+
+/// A short description of a cell, shown in a box next to it for a few seconds.
+pub struct Tooltip {
+    pub message: String,
+    /// world position (in pixels) of the top-left corner of the cell it describes.
+    pub x: i32,
+    pub y: i32,
+    /// seconds left before it disappears.
+    pub remaining: f32,
+}
+
 pub struct MazeScene {
-    pub map_path: String,   
+    pub source: MazeSource,
+    /// whether the maze has been built from its source, which only happens the first time it is
+    /// entered. A [`crate::level_intro::LevelIntroScene`] enters it before handing it on.
+    entered: bool,
 
     map: MapData,
+    /// what each cell of the current floor is, indexed `[y][x]`. Movement and the exit check use this,
+    /// the tiles of `map` are only drawn.
+    grid: Vec<Vec<CellType>>,
+    /// the floor the player is not on, in a dungeon with two floors. Ladders swap it with `grid`.
+    floor: Option<Vec<Vec<CellType>>>,
+    // enemies waiting on the other floor
+    floor_enemies: Vec<Enemy>,
+    on_lower_floor: bool,
+    /// the ladders between the floors of a generated dungeon.
+    connections: Vec<FloorConnection>,
+    /// the inputs so far of a generated dungeon, kept at [`REPLAY_PATH`] when it is left.
+    replay: Option<ReplayData>,
+    /// frames played, which the recorded inputs are timed by.
+    frame: u32,
+    /// enemies that appear during play stay this far from the start, from the difficulty.
+    spawn_safe_radius: usize,
+
+    /// numbered checkpoints on the exit's floor that have to be visited in order before the exit opens.
+    goals: Vec<(usize, usize)>,
+    /// index into `goals` of the checkpoint to visit next. Equal to `goals.len()` once all are done.
+    next_goal: usize,
 
     tileset: Option<Texture2D>, // Use Option since we can't load it in from_map
+    /// the player's walk cycle, if `assets/player.png` is there: square frames in a row, the idle
+    /// frame first. The player is a plain circle without it.
+    player_sheet: Option<Texture2D>,
+    /// which way the player last moved. The sprite faces right, and is flipped when moving west.
+    facing: Direction,
+    /// the walk frame showing, counted from the first frame after the idle one.
+    walk_frame: i32,
+    walk_timer: f32,
     tile_size: i32,
 
-    player_x: usize,
-    player_y: usize,
+    /// everyone in the maze, the first player first. Never empty.
+    players: Vec<Player>,
 
     player_speed: f32,
+
+    projectiles: Vec<Projectile>,
+    shield_active: bool,
+
+    enemies: Vec<Enemy>,
+    // steps from every cell to the way off the player's floor (the exit, or the ladder on the
+    // floor without one), for interceptors
+    exit_distances: Vec<Vec<u32>>,
+    // where every enemy, projectile and collectible is stored, by id
+    registry: EntityRegistry,
+    /// where everything left to chance in play comes from: enemy wandering, events, wormholes,
+    /// altars, shops, side mazes and new seeds from the console. It is seeded from the maze's seed,
+    /// or its file's path, so the same maze and the same moves play out the same.
+    rng: StdRng,
+    /// where particles get their spread from, seeded the same way. They have their own so that
+    /// turning effects off does not change what happens in play.
+    effects_rng: StdRng,
+    // seconds before an enemy can hurt the player by touching them again
+    contact_cooldown: f32,
+
+    events: Vec<RandomEvent>,
+    // seconds since the last roll for a random event
+    event_clock: f32,
+    /// how far the player can see, in cells. Anything further away is covered up.
+    visibility_radius: f32,
+    // time left on the effects of random events
+    lights_out_timer: f32,
+    freeze_timer: f32,
+    speed_boost_timer: f32,
+    // announcement of the last random event and how long it stays on screen
+    banner: Option<(&'static str, f32)>,
+
+    /// how many times the player has entered each cell, indexed `[y][x]`. Exported as a CSV heatmap on
+    /// exit, and whenever the console's `heatmap` asks for one.
+    visit_counts: Vec<Vec<u32>>,
+    /// when (in seconds of `elapsed`) the player last entered each cell on the current floor, indexed `[y][x]`.
+    /// Never-visited cells hold negative infinity.
+    last_visited_time: Vec<Vec<f32>>,
+
+    // direction of a blocked move and how long its nudge animation has left to play
+    bump_dir: Option<Direction>,
+    bump_timer: f32,
+
+    /// seconds until quicksand lets go of the player, while they are stuck in it. Movement is
+    /// ignored until then, but everything else (water, mines, enemies) carries on.
+    quicksand_release_timer: Option<f32>,
+    /// the way a slope is sliding the player, while they are on one. Movement is ignored until
+    /// they are off it.
+    sliding: Option<Direction>,
+    /// seconds until the slide moves the player on to the next cell.
+    slide_timer: f32,
+    /// whether the player has just stepped onto an arrow, which pushes them on in the next update.
+    arrow_push: bool,
+    /// how the player moves, changed by the last transformer they stepped on.
+    move_mode: MoveMode,
+    /// seconds until the player turns back to [`MoveMode::Normal`].
+    mode_timer: f32,
+    /// the last open cell the player stood on, which a transformation that ends in a wall puts
+    /// them back within reach of.
+    last_open_cell: (usize, usize),
+    /// whether four corners mode is on for this maze.
+    four_corners: bool,
+    /// which corner regions of the exit's floor the player has been into, numbered as in
+    /// [`corner_region`]. All true outside four corners mode.
+    corners_visited: [bool; 4],
+
+    tooltip: Option<Tooltip>,
+    // seconds the "Screenshot saved!" notice has left in the HUD
+    screenshot_notice: f32,
+    // seconds the no-backtrack challenge's penalty notice has left
+    revisit_notice: f32,
+    // seconds since the player last moved
+    idle_timer: f32,
+    /// the input the player used last.
+    input_mode: InputMode,
+    /// seconds left of a time stop. Enemies, projectiles and rising water wait until it runs out.
+    time_stop_remaining: f32,
+    /// how much noise the player has made lately. Sound traps within `ceil` of it steps hear them.
+    player_noise_level: f32,
+    /// creeping (C) moves at half speed and without a sound.
+    creeping: bool,
+    /// sound traps that fired, with the seconds left before they can fire again.
+    sound_trap_cooldowns: HashMap<(usize, usize), f32>,
+    /// where the gates of each button on the player's floor are, open or shut.
+    gates: HashMap<ButtonId, Vec<(usize, usize)>>,
+    floor_gates: HashMap<ButtonId, Vec<(usize, usize)>>,
+    /// the buttons whose gates are open. Ids are never shared between floors.
+    open_gates: HashSet<ButtonId>,
+    /// whether this is a side maze behind a warp, which pops back to its maze when it ends.
+    side_maze: bool,
+    /// the player has just stepped onto a warp, so its side maze opens on the next update.
+    entering_warp: bool,
+    /// the warp whose side maze is open above this maze.
+    open_warp: Option<(usize, usize)>,
+    /// how many times an enemy has noticed the player this run, see [`Enemy::alerted`].
+    enemies_alerted: u32,
+    /// no enemy has noticed the player yet.
+    perfect_stealth: bool,
+    // whether the player was thrown into lava, which ends the run whatever their health or shield
+    burned: bool,
+    // the suggested next cell and the seconds it stays highlighted
+    hint: Option<((usize, usize), f32)>,
+    /// hints left to show in this maze, whether asked for with H or shown after idling.
+    hints_left: u32,
+    // seconds the "No hints left" notice has left in the HUD
+    no_hints_notice: f32,
+    /// flavour text about what just happened.
+    narrator: Narrator,
+    /// the map's messages the player has not stepped on yet, by cell.
+    triggered_messages: HashMap<(usize, usize), String>,
+    /// the F4 or backtick console, for trying things out.
+    console: DebugConsole,
+    /// where the wall cues were last played for, so they only play again after a step.
+    cue_cell: Option<(usize, usize, bool)>,
+    /// the ghost from the last echo stepped on, while it lasts.
+    ghost: Option<GhostPlayer>,
+    /// seconds until a wormhole will throw the player again.
+    wormhole_cooldown: f32,
+    /// the rival, when racing it.
+    rival: Option<Rival>,
+    /// crumbling floor the player has stepped on, with the seconds until each gives way.
+    crumbling: HashMap<(usize, usize), f32>,
+    /// seconds left of the player's fall into a pit. They climb out once it is over.
+    fall_timer: Option<f32>,
+    /// the fog has been turned off from the console.
+    fog_hidden: bool,
+    /// damage a mine does to the player and to enemies caught in the blast.
+    pub mine_damage: i32,
+    particles: Vec<Particle>,
+    /// illusions the player has walked through, with the seconds left on their fading outline.
+    /// The cells themselves are already paths.
+    dissolving_walls: HashMap<(usize, usize), f32>,
+    /// gates that are sliding open. The cells turn into paths once they are fully open.
+    animating_doors: Vec<AnimatingDoor>,
+
+    /// whether the exit's floor is flooding. The water rises one row every `WATER_RISE_INTERVAL` seconds.
+    rising_water: bool,
+    /// the next row to flood.
+    water_level_row: usize,
+    // seconds until the water rises again
+    water_rise_timer: f32,
+
+    /// cells the player has stood on, on the current floor. Moving onto one of them again sets `backtracked`.
+    visited_cells: HashSet<(usize, usize)>,
+    // the same for the other floor
+    floor_visited_cells: HashSet<(usize, usize)>,
+    /// the way from where the player started the floor to their cell, with any loops walked on the
+    /// way cut out, so walking it backwards goes straight back.
+    breadcrumbs: Vec<(usize, usize)>,
+    // the same for the other floor
+    floor_breadcrumbs: Vec<(usize, usize)>,
+    /// whether the player is being walked back along `breadcrumbs`. Only Esc, to stop, is listened
+    /// to until they get there.
+    retracing: bool,
+    /// seconds until the walk back takes its next step.
+    retrace_timer: f32,
+    /// the torches on the player's floor.
+    lights: Vec<LightSource>,
+    floor_lights: Vec<LightSource>,
+    /// the regions map fragments have revealed on the player's floor.
+    revealed_regions: Vec<RevealedRegion>,
+    floor_revealed_regions: Vec<RevealedRegion>,
+    /// cells of the player's floor that can be seen through the fog, indexed `[y][x]`.
+    discovered: Vec<Vec<bool>>,
+    floor_discovered: Vec<Vec<bool>>,
+    /// cells of the player's floor they have stood on or next to, for the auto-map, indexed `[y][x]`.
+    explored: Vec<Vec<bool>>,
+    floor_explored: Vec<Vec<bool>>,
+    /// frames each wall of the player's floor has spent in view, up to 255. Older walls look more worn.
+    wall_age: Vec<Vec<u8>>,
+    floor_wall_age: Vec<Vec<u8>>,
+    /// whether the player has retraced a step, which loses the no-backtrack bonus.
+    backtracked: bool,
+    /// how many times the player has stepped back onto a visited cell.
+    revisits: u32,
+
+    // how the run is going, for the difficulty adjustment at the end
+    elapsed: f32,
+    steps: u32,
+    par_steps: u32,
+
+    camera: Camera2D,
+    /// how far the view is turned, in degrees clockwise. Always a whole number of quarter turns, but
+    /// not wrapped to 0..360 so the camera can turn the short way towards it.
+    view_angle: f32,
+    peeking: bool,
+    peek_timer: f32,
 }
 
+// the fewest cells across and down a window shows. The tiles shrink on a window too small for them
+const MIN_VIEW_CELLS: i32 = 5;
+// visibility radius when nothing is limiting the player's view
+const FULL_VISIBILITY: f32 = f32::INFINITY;
+// seconds an enemy waits before it can hurt the player by contact again
+const CONTACT_COOLDOWN: f32 = 1.0;
+// how many tanks an enemy rush wave brings, and how close to the player they may appear
+const RUSH_WAVE_SIZE: usize = 4;
+const RUSH_WAVE_MIN_DISTANCE: usize = 5;
+// points for picking up a treasure dropped by an enemy
+const TREASURE_POINTS: u32 = 25;
+const SPEED_BOOST_DURATION: f32 = 10.0;
+const BANNER_DURATION: f32 = 3.0;
+const TOOLTIP_DURATION: f32 = 3.0;
+const WATER_RISE_INTERVAL: f32 = 6.0;
+const DROWNING_DAMAGE: i32 = 15;
+// altars, vendors and map fragments put on each floor of a generated dungeon
+const ALTARS_PER_FLOOR: usize = 1;
+const VENDORS_PER_FLOOR: usize = 1;
+const MAP_FRAGMENTS_PER_FLOOR: usize = 2;
+const ECHOES_PER_FLOOR: usize = 1;
+// the auto-map panel's width and height, and the size of one cell on it, in pixels
+const AUTO_MAP_SIZE: i32 = 200;
+const AUTO_MAP_CELL: i32 = 4;
+const WORMHOLES_PER_FLOOR: usize = 1;
+const TRANSFORMERS_PER_FLOOR: usize = 1;
+/// how many floors a generated dungeon has unless asked for fewer, see [`MazeScene::with_floors`].
+pub const DUNGEON_FLOORS: usize = 2;
+/// the folder visit heatmaps are written to, made when the first one is.
+pub const HEATMAP_DIR: &str = "heatmaps";
+// added to a maze's seed for the randomness in play and in effects, away from the ones the layout uses
+const PLAY_SEED_OFFSET: u64 = 100;
+const EFFECTS_SEED_OFFSET: u64 = 101;
+// seconds a transformer changes the player for
+const TRANSFORM_TIME: f32 = 10.0;
+const CRUMBLE_PER_FLOOR: usize = 6;
+// seconds crumbling floor holds after it is stepped on
+const CRUMBLE_TIME: f32 = 0.5;
+// seconds of falling into a pit, and the damage the fall does
+const PIT_FALL_TIME: f32 = 0.4;
+const PIT_FALL_DAMAGE: i32 = 20;
+/// seconds the rival takes to walk one cell.
+pub const RIVAL_STEP_TIME: f32 = 0.25;
+// seconds after going through a wormhole before one works again
+const WORMHOLE_COOLDOWN: f32 = 2.0;
+// seconds an echo's ghost lasts
+const ECHO_TIME: f32 = 2.0;
+// the ghost walks no faster than this many seconds a cell, even with no move cooldown
+const ECHO_MIN_STEP_TIME: f32 = 0.05;
+const WIND_PER_FLOOR: usize = 4;
+const ARROWS_PER_FLOOR: usize = 3;
+const SLOPES_PER_FLOOR: usize = 2;
+// seconds between the steps of a walk back along the breadcrumbs
+const RETRACE_STEP_TIME: f32 = 0.12;
+// seconds a slope takes to slide the player one cell
+const SLIDE_STEP_TIME: f32 = 0.08;
+const BUTTONS_PER_FLOOR: usize = 1;
+// seconds a gate takes to slide open
+const DOOR_SLIDE_TIME: f32 = 0.3;
+// warps on the top floor of a dungeon that is not a side maze itself
+const WARPS_PER_DUNGEON: usize = 1;
+// the size of the side maze behind a warp, and the points for clearing it
+const SIDE_MAZE_WIDTH: usize = 15;
+const SIDE_MAZE_HEIGHT: usize = 11;
+const SIDE_MAZE_REWARD: u32 = 50;
+const LAVA_PER_FLOOR: usize = 8;
+// hazards and pickups drawn from the difficulty's cell weights on each floor
+const WEIGHTED_CELLS_PER_FLOOR: usize = 12;
+// seconds a time stop pickup freezes everything but the player
+const TIME_STOP_DURATION: f32 = 5.0;
+// noise a mushroom bounce (the game's dash) and a sprinting step make, and how fast it dies down
+const DASH_NOISE: f32 = 3.0;
+const SPRINT_NOISE: f32 = 1.0;
+const NOISE_FADE_PER_SECOND: f32 = 2.0;
+// points the no-backtrack challenge takes for each step back onto a visited cell
+const REVISIT_PENALTY: u32 = 10;
+// seconds before a sound trap that fired can fire again
+const SOUND_TRAP_RESET: f32 = 3.0;
+// the shortest wait between creeping steps, for when the move cooldown setting is off
+const CREEP_MOVE_COOLDOWN: f32 = 0.2;
+// torches by altars and vendors: how far they light, in cells, and how fast and far that flickers
+const TORCH_RADIUS: f32 = 3.5;
+const TORCH_FLICKER_AMPLITUDE: f32 = 0.4;
+const LIGHT_FLICKER_SPEED: f32 = 7.0;
+// seconds between the steps of a wind cell's moving arrows
+const WIND_ANIMATION_STEP: f32 = 0.2;
+// how many cells out from a map fragment, in every direction, it reveals
+const MAP_FRAGMENT_REACH: usize = 6;
+// seconds quicksand holds the player before they can climb out
+const QUICKSAND_HOLD_TIME: f32 = 2.0;
+const DEFAULT_MINE_DAMAGE: i32 = 40;
+// how far (in cells, counting along the grid) a mine's blast reaches
+const MINE_BLAST_RADIUS: usize = 2;
+const MINES_PER_FLOOR: usize = 3;
+// seconds an illusion's outline takes to fade once the player walks through it
+const ILLUSION_DISSOLVE_TIME: f32 = 0.3;
+// how far (in steps) out from the exit a minotaur's patrol reaches
+const MINOTAUR_PATROL_REACH: std::ops::RangeInclusive<usize> = 8..=16;
+// length of the nudge played when the player walks into a wall
+const BUMP_DURATION: f32 = 0.15;
+// how long holding the peek key keeps the camera on the exit before it returns to the player
+const PEEK_DURATION: f32 = 3.0;
+// how quickly the camera catches up with its target, as a fraction of the distance per second
+const CAMERA_PAN_SPEED: f32 = 6.0;
+// how quickly the view turns towards its angle, as a fraction of the remaining turn per second
+const CAMERA_TURN_SPEED: f32 = 10.0;
+// radius of the on-screen D-pad buttons, and how far each one sits from the pad's centre
+const DPAD_BUTTON_RADIUS: f32 = 36.0;
+const DPAD_BUTTON_SPREAD: f32 = 72.0;
+// how many cells from the player walls age, when the player can see that far
+const WALL_AGE_RADIUS: f32 = 6.0;
+// the movement keys and the way each one moves. In co-op the first player has WASD and the second the arrows.
+const LETTER_KEYS: [(Direction, KeyboardKey); 4] = [
+    (Direction::East, KeyboardKey::KEY_D),
+    (Direction::West, KeyboardKey::KEY_A),
+    (Direction::South, KeyboardKey::KEY_S),
+    (Direction::North, KeyboardKey::KEY_W),
+];
+const ARROW_KEYS: [(Direction, KeyboardKey); 4] = [
+    (Direction::East, KeyboardKey::KEY_RIGHT),
+    (Direction::West, KeyboardKey::KEY_LEFT),
+    (Direction::South, KeyboardKey::KEY_DOWN),
+    (Direction::North, KeyboardKey::KEY_UP),
+];
+const MOVEMENT_KEYS: [(Direction, KeyboardKey); 8] = [
+    ARROW_KEYS[0], LETTER_KEYS[0],
+    ARROW_KEYS[1], LETTER_KEYS[1],
+    ARROW_KEYS[2], LETTER_KEYS[2],
+    ARROW_KEYS[3], LETTER_KEYS[3],
+];
+const PLAYER_SHEET: &str = "assets/player.png";
+// seconds each frame of the walk cycle shows for
+const WALK_FRAME_TIME: f32 = 0.1;
+// seconds after the last move that the player still counts as walking
+const WALK_IDLE_TIME: f32 = 0.25;
+// seconds without moving before the next step towards the goal is shown
+const HINT_IDLE_TIME: f32 = 10.0;
+// seconds without moving before the HUD says a hint is on its way
+const HINT_NOTICE_TIME: f32 = 5.0;
+// how long a hint stays highlighted
+const HINT_DURATION: f32 = 3.0;
+/// how many hints the player gets in each maze.
+pub const HINTS_PER_MAZE: u32 = 3;
+
 
 
 
 
 impl MazeScene {
     pub fn from_map(path: String) -> Self {
+        let map = load_map(&path);
+        Self::with_source(MazeSource::File(path), map)
+    }
+
+    /// A maze read from an `.mzb` file when the scene starts.
+    pub fn from_binary_file(path: String) -> Self {
+        Self::with_source(MazeSource::Binary(path), MapData::blank(0, 0, 32))
+    }
+
+    /// A generated dungeon of two floors, `width` x `height` cells each. The same seed always gives the same dungeon.
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self::generated(width, height, MazeAlgorithm::Backtracker, seed)
+    }
+
+    /// Like [`MazeScene::new`], with the floors generated by the given algorithm.
+    pub fn generated(width: usize, height: usize, algorithm: MazeAlgorithm, seed: u64) -> Self {
+        let source = MazeSource::Generated { width, height, algorithm, seed, floors: DUNGEON_FLOORS };
+        Self::with_source(source, MapData::blank(width, height, 32))
+    }
+
+    /// The same generated dungeon with only its first floor, and the exit on it, when `floors` is 1.
+    /// Has no effect on a maze from a file.
+    pub fn with_floors(mut self, floors: usize) -> Self {
+        if let MazeSource::Generated { floors: count, .. } = &mut self.source {
+            *count = floors.clamp(1, DUNGEON_FLOORS);
+        }
+        self
+    }
+
+    /// A fresh scene on the same maze, with the clock and steps back at zero.
+    pub fn restarted(&self) -> Self {
+        match &self.source {
+            MazeSource::File(path) => Self::from_map(path.clone()),
+            MazeSource::Binary(path) => Self::from_binary_file(path.clone()),
+            &MazeSource::Generated { width, height, algorithm, seed, floors } => Self::generated(width, height, algorithm, seed).with_floors(floors),
+        }
+    }
+
+    /// A small side maze behind a warp. Clearing it earns a reward and goes back to the maze the warp
+    /// is in, and so does dying in it, for that maze to handle.
+    pub fn side_maze(seed: u64) -> Self {
+        let (width, height) = maze_size!(SIDE_MAZE_WIDTH, SIDE_MAZE_HEIGHT);
+        let mut scene = Self::generated(width, height, MazeAlgorithm::Backtracker, seed);
+        scene.side_maze = true;
+        scene
+    }
+
+    fn with_source(source: MazeSource, map: MapData) -> Self {
+        let seed = match &source {
+            MazeSource::Generated { seed, .. } => *seed,
+            MazeSource::File(path) | MazeSource::Binary(path) => path_seed(path),
+        };
+        let rng = StdRng::seed_from_u64(seed.wrapping_add(PLAY_SEED_OFFSET));
+        let effects_rng = StdRng::seed_from_u64(seed.wrapping_add(EFFECTS_SEED_OFFSET));
         Self {
-            map_path: path.clone(),
-            map: load_map(&path),
+            source,
+            entered: false,
+            map,
+            grid: Vec::new(),
+            floor: None,
+            floor_enemies: Vec::new(),
+            on_lower_floor: false,
+            connections: Vec::new(),
+            replay: None,
+            frame: 0,
+            spawn_safe_radius: SPAWN_SAFE_RADIUS,
+            goals: Vec::new(),
+            next_goal: 0,
             tileset: None, 
+            player_sheet: None,
+            facing: Direction::East,
+            walk_frame: 0,
+            walk_timer: 0.0,
             tile_size: 32,
-            player_x: 0,
-            player_y: 0,
+            players: vec![Player::at(0, 0, Color::WHITE)],
             player_speed: 0.0,
+            projectiles: Vec::new(),
+            shield_active: false,
+            enemies: Vec::new(),
+            registry: EntityRegistry::new(),
+            rng,
+            effects_rng,
+            exit_distances: Vec::new(),
+            contact_cooldown: 0.0,
+            events: RandomEvent::defaults(),
+            event_clock: 0.0,
+            visibility_radius: FULL_VISIBILITY,
+            lights_out_timer: 0.0,
+            freeze_timer: 0.0,
+            speed_boost_timer: 0.0,
+            banner: None,
+            visit_counts: Vec::new(),
+            last_visited_time: Vec::new(),
+            bump_dir: None,
+            bump_timer: 0.0,
+            quicksand_release_timer: None,
+            sliding: None,
+            arrow_push: false,
+            move_mode: MoveMode::Normal,
+            mode_timer: 0.0,
+            last_open_cell: (0, 0),
+            slide_timer: 0.0,
+            four_corners: false,
+            corners_visited: [true; 4],
+            tooltip: None,
+            screenshot_notice: 0.0,
+            revisit_notice: 0.0,
+            idle_timer: 0.0,
+            burned: false,
+            input_mode: InputMode::Keyboard,
+            time_stop_remaining: 0.0,
+            player_noise_level: 0.0,
+            creeping: false,
+            sound_trap_cooldowns: HashMap::new(),
+            gates: HashMap::new(),
+            floor_gates: HashMap::new(),
+            open_gates: HashSet::new(),
+            side_maze: false,
+            entering_warp: false,
+            open_warp: None,
+            enemies_alerted: 0,
+            perfect_stealth: true,
+            hint: None,
+            hints_left: HINTS_PER_MAZE,
+            no_hints_notice: 0.0,
+            narrator: Narrator::default(),
+            triggered_messages: HashMap::new(),
+            console: DebugConsole::default(),
+            cue_cell: None,
+            ghost: None,
+            wormhole_cooldown: 0.0,
+            rival: None,
+            crumbling: HashMap::new(),
+            fall_timer: None,
+            fog_hidden: false,
+            mine_damage: DEFAULT_MINE_DAMAGE,
+            particles: Vec::new(),
+            dissolving_walls: HashMap::new(),
+            animating_doors: Vec::new(),
+            rising_water: false,
+            water_level_row: 0,
+            water_rise_timer: 0.0,
+            visited_cells: HashSet::new(),
+            floor_visited_cells: HashSet::new(),
+            breadcrumbs: Vec::new(),
+            floor_breadcrumbs: Vec::new(),
+            retracing: false,
+            retrace_timer: 0.0,
+            revealed_regions: Vec::new(),
+            floor_revealed_regions: Vec::new(),
+            lights: Vec::new(),
+            floor_lights: Vec::new(),
+            discovered: Vec::new(),
+            floor_discovered: Vec::new(),
+            explored: Vec::new(),
+            floor_explored: Vec::new(),
+            wall_age: Vec::new(),
+            floor_wall_age: Vec::new(),
+            backtracked: false,
+            revisits: 0,
+            elapsed: 0.0,
+            steps: 0,
+            par_steps: 0,
+            camera: Camera2D {
+                offset: Vector2::zero(),
+                target: Vector2::zero(),
+                rotation: 0.0,
+                zoom: 1.0,
+            },
+            view_angle: 0.0,
+            peeking: false,
+            peek_timer: 0.0,
         }
     }
 
+    /// add a projectile to the maze. It is moved and checked for hits in [`Scene::update`].
+    pub fn spawn_projectile(&mut self, mut projectile: Projectile) -> EntityId {
+        projectile.id = self.registry.create(EntityKind::Projectile(self.projectiles.len()));
+        let id = projectile.id;
+        self.projectiles.push(projectile);
+        id
+    }
 
-    
-    
-    
-    // Check if a move to the given position is valid
-    fn is_valid_move(&self, x: usize, y: usize) -> bool {
-        if x >= self.map.grid_w || y >= self.map.grid_h {
-            return false;
-        }
-        let tid = self.map.tiles[y][x];
-        tid >= 0 && !is_wall_tile(tid)
+    /// add an enemy to the player's floor.
+    pub fn spawn_enemy(&mut self, mut enemy: Enemy) -> EntityId {
+        enemy.id = self.registry.create(EntityKind::Enemy(self.enemies.len()));
+        let id = enemy.id;
+        self.enemies.push(enemy);
+        id
     }
-    fn draw_tile(&self, d: &mut RaylibDrawHandle, tile_id: i32, x: usize, y: usize) {
-        let tileset = match &self.tileset {
-            Some(t) => t,
-            None => return, 
-        };
-        let cols = tileset.width() / self.tile_size;
-        let src = Rectangle {
-            x: ((tile_id % cols) * self.tile_size) as f32,
-            y: ((tile_id / cols) * self.tile_size) as f32,
-            width: self.tile_size as f32,
-            height: self.tile_size as f32,
-        };
 
-        let dst = Rectangle {
-            x: (x as i32 * self.tile_size) as f32,
-            y: (y as i32 * self.tile_size) as f32,
-            width: self.tile_size as f32,
-            height: self.tile_size as f32,
-        };
+    // leave a treasure or item lying on the map
+    fn spawn_collectible(&mut self, kind: String, x: usize, y: usize) -> EntityId {
+        let id = self.registry.create(EntityKind::Collectible(self.map.entities.len()));
+        self.map.entities.push(MapEntity { kind, x, y, id });
+        id
+    }
 
-        d.draw_texture_pro(tileset, 
-            src, 
-            dst, Vector2::zero(), 0.0, Color::WHITE);
+    // bring the registry's indices up to date after entities were removed, reordered or moved
+    // between floors
+    fn reindex_entities(&mut self) {
+        self.registry.reindex(self.enemies.iter().map(|e| e.id), EntityKind::Enemy);
+        self.registry.reindex(self.floor_enemies.iter().map(|e| e.id), EntityKind::FloorEnemy);
+        self.registry.reindex(self.projectiles.iter().map(|p| p.id), EntityKind::Projectile);
+        self.registry.reindex(self.map.entities.iter().map(|e| e.id), EntityKind::Collectible);
     }
 
-    fn tile_src_rect(tile_id: i32, tile_size: i32, tileset_width: i32) -> Rectangle {
-            let cols = tileset_width / tile_size;
-            let x = (tile_id % cols) * tile_size;
-            let y = (tile_id / cols) * tile_size;
+    // centre of the player's cell in pixels
+    fn player_center(&self) -> Vector2 {
+        self.cell_center(self.players[0].x, self.players[0].y)
+    }
 
-            Rectangle {
-                x: x as f32,
-                y: y as f32,
-                width: tile_size as f32,
-                height: tile_size as f32,
-            }
+    // centre of the exit cell in pixels, if the current floor has one
+    fn goal_center(&self) -> Option<Vector2> {
+        self.find_cell(CellType::Exit).map(|(x, y)| self.cell_center(x, y))
     }
-}
 
-impl Scene for MazeScene {
-    fn on_enter(&mut self, rl: &mut RaylibHandle, data: &mut GameData) {
-        self.map = load_map(&self.map_path);
-        self.tile_size = self.map.tile_size_px;
+    // position of the first cell of the given type on the current floor
+    fn find_cell(&self, cell: CellType) -> Option<(usize, usize)> {
+        position_of(&self.grid, |c| *c == cell)
+    }
 
-        // Load texture using the thread from GameData
-        if let Some(ref thread) = data.thread {
-            self.tileset = Some(
-                rl.load_texture(thread, "assets/tileset0.png")
-                    .expect("Failed to load tileset")
-            );
-        }
+    // the exit and the checkpoints are on the lower floor of a two-floor dungeon
+    fn on_exit_floor(&self) -> bool {
+        self.floor.is_none() || self.on_lower_floor
+    }
 
-        for e in &self.map.entities {
-            if e.kind == "player" {
-                self.player_x = e.x;
-                self.player_y = e.y;
-            }
-        }
+    /// where the first player is: whether on the lower floor, and the cell.
+    pub fn player_cell(&self) -> (bool, usize, usize) {
+        (self.on_lower_floor, self.players[0].x, self.players[0].y)
     }
 
+    /// The cell to step onto next on the shortest way to the current goal: the next checkpoint,
+    /// then the exit, by way of the ladder when the exit is on the other floor.
+    pub fn hint_step(&self) -> Option<(usize, usize)> {
+        self.route_to_goal()?.get(1).copied()
+    }
 
+    // the shortest way from the player to the ladder, the next checkpoint or the exit, whichever is next
+    fn route_to_goal(&self) -> Option<Vec<(usize, usize)>> {
+        let target = if !self.on_exit_floor() {
+            position_of(&self.grid, |c| matches!(c, CellType::Ladder(_)))?
+        } else if let Some(&goal) = self.goals.get(self.next_goal) {
+            goal
+        } else {
+            self.find_cell(CellType::Exit)?
+        };
+        path_through(&self.grid, (self.players[0].x, self.players[0].y), target, |c| c.is_walkable())
+    }
 
-    fn handle_input(&mut self, rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
-        // Handle player movement with arrow keys or WASD
-        let mut new_x = self.player_x;
-        let mut new_y = self.player_y;
-        
-        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) || rl.is_key_pressed(KeyboardKey::KEY_D) {
-            new_x += 1;
+    /// Highlight the next step towards the goal for a few seconds, using up one of the maze's hints,
+    /// and start waiting for the next one. Returns false, changing nothing, once the hints have run
+    /// out or when there is no step to show.
+    pub fn show_hint(&mut self) -> bool {
+        self.idle_timer = 0.0;
+        if self.hints_left == 0 {
+            return false;
         }
-        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) || rl.is_key_pressed(KeyboardKey::KEY_A) {
-            if new_x > 0 {
-                new_x -= 1;
-            }
+        let Some(cell) = self.hint_step() else { return false };
+        self.hint = Some((cell, HINT_DURATION));
+        self.hints_left -= 1;
+        true
+    }
+
+    /// Tick off the current checkpoint if the player is standing on it. Returns true when it was reached.
+    pub fn advance_goal(&mut self) -> bool {
+        if !self.on_exit_floor() {
+            return false;
         }
-        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) || rl.is_key_pressed(KeyboardKey::KEY_S) {
-            new_y += 1;
+        if self.goals.get(self.next_goal) == Some(&(self.players[0].x, self.players[0].y)) {
+            self.next_goal += 1;
+            return true;
         }
-        if rl.is_key_pressed(KeyboardKey::KEY_UP) || rl.is_key_pressed(KeyboardKey::KEY_W) {
-            if new_y > 0 {
-                new_y -= 1;
+        false
+    }
+
+    // Length of the shortest route from the start, through the checkpoints, to the exit. A two-floor
+    // dungeon is walked to the ladder on the upper floor and from the ladder on the lower one.
+    fn compute_par_steps(&self) -> u32 {
+        let start = (self.players[0].x, self.players[0].y);
+        let length = match &self.floor {
+            Some(lower) => {
+                let ladder = position_of(&self.grid, |c| matches!(c, CellType::Ladder(_)));
+                let exit = position_of(lower, |c| *c == CellType::Exit);
+                match (ladder, exit) {
+                    (Some(ladder), Some(exit)) => {
+                        let mut stops = vec![ladder];
+                        stops.extend(&self.goals);
+                        stops.push(exit);
+                        route_length(&self.grid, &[start, ladder]).zip(route_length(lower, &stops)).map(|(a, b)| a + b)
+                    }
+                    _ => None,
+                }
             }
+            None => position_of(&self.grid, |c| *c == CellType::Exit).and_then(|exit| {
+                let mut stops = vec![start];
+                stops.extend(&self.goals);
+                stops.push(exit);
+                route_length(&self.grid, &stops)
+            }),
+        };
+        length.unwrap_or(0) as u32
+    }
+
+    /// how the run has gone so far.
+    pub fn run_stats(&self, died: bool) -> RunStats {
+        RunStats {
+            time: self.elapsed,
+            steps: self.steps,
+            par_steps: self.par_steps,
+            died,
         }
-        
-        // Check if the move is valid and update position
-        if self.is_valid_move(new_x, new_y) {
-            self.player_x = new_x;
-            self.player_y = new_y;
+    }
+
+    /// The points for reaching the exit after this run, see [`GameData::score`].
+    pub fn compute_level_score(&self, data: &GameData) -> u32 {
+        data.score(self.run_stats(false), self.objectives().all_coins)
+    }
+
+    /// Which star objectives the run has met, for a player standing on the open exit.
+    pub fn objectives(&self) -> StarObjectives {
+        StarObjectives {
+            completed: true,
+            within_par: self.steps as f32 <= self.par_steps as f32 * PAR_STAR_ALLOWANCE,
+            all_coins: !self.map.entities.iter().any(|e| e.kind == "treasure"),
         }
-        
-        SceneSwitch::None
     }
 
-    fn update(&mut self, _dt: f32, data: &mut GameData) -> SceneSwitch {
-        // Check if player has reached the goal
-        for e in &self.map.entities {
-            if e.kind == "goal" && e.x == self.player_x && e.y == self.player_y {
-                // Add points for completing the maze
-                data.score();
-                return SceneSwitch::Push(Box::new(WinScene));
+    /// whether every checkpoint has been visited.
+    pub fn goals_complete(&self) -> bool {
+        self.next_goal >= self.goals.len()
+    }
+
+    /// whether every checkpoint and (in four corners mode) every corner has been visited, so reaching
+    /// the exit completes the maze.
+    pub fn exit_open(&self) -> bool {
+        self.goals_complete() && self.corners_visited.iter().all(|v| *v)
+    }
+
+    // Tick off the corner region the player is standing in, if it is on the exit's floor.
+    fn visit_corner(&mut self) {
+        if !self.on_exit_floor() {
+            return;
+        }
+        if let Some(corner) = corner_region(self.players[0].x, self.players[0].y, self.map.grid_w, self.map.grid_h) {
+            self.corners_visited[corner] = true;
+        }
+    }
+
+    // Swap to the other floor when the player steps onto a ladder. Both floors have their ladder
+    // at the same position, so the player arrives standing on the ladder leading back.
+    fn climb_ladder(&mut self) {
+        if !matches!(self.grid[self.players[0].y][self.players[0].x], CellType::Ladder(_)) {
+            return;
+        }
+        // gates still sliding open on the floor being left finish opening
+        if self.floor.is_some() {
+            self.finish_doors();
+            self.finish_crumbling();
+        }
+        if let Some(other) = self.floor.as_mut() {
+            let floor = self.on_lower_floor as usize;
+            if let Some((_, cell)) = self.connections.iter().find_map(|c| c.other_end(floor, (self.players[0].x, self.players[0].y))) {
+                (self.players[0].x, self.players[0].y) = cell;
+            }
+            // the ghost only knew the way on the floor being left
+            self.ghost = None;
+            // the other players come along, unless they are already waiting at the exit
+            let (x, y) = (self.players[0].x, self.players[0].y);
+            for player in self.players[1..].iter_mut().filter(|p| !p.reached_exit) {
+                (player.x, player.y) = (x, y);
             }
+            std::mem::swap(&mut self.grid, other);
+            std::mem::swap(&mut self.enemies, &mut self.floor_enemies);
+            for projectile in self.projectiles.drain(..) {
+                self.registry.destroy(projectile.id);
+            }
+            self.reindex_entities();
+            self.dissolving_walls.clear();
+            self.sound_trap_cooldowns.clear();
+            self.on_lower_floor = !self.on_lower_floor;
+            self.narrator.say(if self.on_lower_floor { "The light fades..." } else { "Fresher air drifts down from above." });
+            self.clear_trail();
+            // arriving by the ladder counts as a step onto the new floor, so going back down it is a backtrack
+            std::mem::swap(&mut self.visited_cells, &mut self.floor_visited_cells);
+            std::mem::swap(&mut self.breadcrumbs, &mut self.floor_breadcrumbs);
+            std::mem::swap(&mut self.explored, &mut self.floor_explored);
+            self.mark_visited();
+            std::mem::swap(&mut self.revealed_regions, &mut self.floor_revealed_regions);
+            std::mem::swap(&mut self.lights, &mut self.floor_lights);
+            std::mem::swap(&mut self.gates, &mut self.floor_gates);
+            std::mem::swap(&mut self.discovered, &mut self.floor_discovered);
+            std::mem::swap(&mut self.wall_age, &mut self.floor_wall_age);
+            self.refresh_exit_distances();
         }
-        
-        SceneSwitch::None
     }
 
-    
+    // work out the interceptors' distance map for the player's floor
+    fn refresh_exit_distances(&mut self) {
+        self.exit_distances = if self.on_exit_floor() {
+            dijkstra_from_exit(&self.grid)
+        } else {
+            let ladders = position_of(&self.grid, |c| matches!(c, CellType::Ladder(_)));
+            dijkstra_from(&self.grid, ladders, |cell| cell.is_walkable())
+        };
+    }
 
-    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
-        d.clear_background(Color::WHITE);
-        // Background 
-        for y in 0..self.map.grid_h {
-            for x in 0..self.map.grid_w {
-                let tid = self.map.tiles[y][x];
-                if tid >= 0 && is_floor_tile(tid) {
-                    self.draw_tile(d, tid, x, y);
-                }
+    /// Reveal the cells in `region` (clipped to the map) for the rest of the maze.
+    pub fn reveal(&mut self, region: RevealedRegion) {
+        for row in self.discovered.iter_mut().skip(region.y).take(region.height) {
+            for cell in row.iter_mut().skip(region.x).take(region.width) {
+                *cell = true;
             }
         }
+        self.revealed_regions.push(region);
+    }
 
-        // Structures
-        for y in 0..self.map.grid_h {
-            for x in 0..self.map.grid_w {
-                let tid = self.map.tiles[y][x];
-                if tid >= 0 && is_wall_tile(tid) {
-                    self.draw_tile(d, tid, x, y);
-                }
-            }
+    /// whether the cell can be seen through the fog thanks to a map fragment.
+    pub fn is_discovered(&self, x: usize, y: usize) -> bool {
+        self.discovered.get(y).and_then(|row| row.get(x)).copied().unwrap_or(false)
+    }
+
+    // Keep the camera inside the map so we never scroll past its edges.
+    // A map smaller than the screen is simply centred.
+    fn clamp_camera_target(&self, focus: Vector2, data: &GameData) -> Vector2 {
+        let map_w = (self.map.grid_w as i32 * self.tile_size) as f32;
+        let map_h = (self.map.grid_h as i32 * self.tile_size) as f32;
+        let mut half_w = data.screen_width as f32 / 2.0;
+        let mut half_h = data.screen_height as f32 / 2.0;
+        // turned a quarter, the map's width runs up the screen
+        if self.view_turns() % 2 == 1 {
+            std::mem::swap(&mut half_w, &mut half_h);
         }
 
-        // Entities
-        for e in &self.map.entities {
-            let screen_x = (e.x as i32) * self.tile_size;
-            let screen_y = (e.y as i32) * self.tile_size;
+        let x = if map_w <= half_w * 2.0 { map_w / 2.0 } else { focus.x.clamp(half_w, map_w - half_w) };
+        let y = if map_h <= half_h * 2.0 { map_h / 2.0 } else { focus.y.clamp(half_h, map_h - half_h) };
+        Vector2::new(x, y)
+    }
 
-            match e.kind.as_str() {
-                "player" => {
-                    d.draw_circle(
-                        screen_x + self.tile_size / 2,
-                        screen_y + self.tile_size / 2,
-                        self.tile_size as f32 * 0.4,
-                        Color::BLUE,
-                    );
-                }
-                "goal" => {
-                    d.draw_rectangle(
-                        screen_x,
-                        screen_y,
-                        self.tile_size,
-                        self.tile_size,
-                        Color::GOLD,
-                    );
-                }
-                _ => {}
-            }
+    /// Look over the maze without its fog, zoomed out to `zoom`, for
+    /// [`crate::level_intro::LevelIntroScene`]. The camera aims nearer the middle of the maze the
+    /// further out it is. At 1.0 the fog and the camera are back as play has them.
+    pub fn set_overview(&mut self, zoom: f32, data: &GameData) {
+        let middle = Vector2::new(
+            (self.map.grid_w as i32 * self.tile_size) as f32 / 2.0,
+            (self.map.grid_h as i32 * self.tile_size) as f32 / 2.0,
+        );
+        let play_target = self.clamp_camera_target(self.player_center(), data);
+        self.fog_hidden = zoom < 1.0;
+        self.camera.zoom = zoom.min(1.0);
+        self.camera.target = middle.lerp(play_target, zoom.min(1.0));
+    }
+
+    // Smoothly move the camera towards the player, or towards the exit while peeking.
+    fn update_camera(&mut self, dt: f32, data: &GameData) {
+        if self.peeking {
+            self.peek_timer += dt;
+        } else {
+            self.peek_timer = 0.0;
         }
 
+        let player = self.player_center();
+        let focus = if self.peeking && self.peek_timer < PEEK_DURATION {
+            self.goal_center().unwrap_or(player)
+        } else {
+            player
+        };
 
-       
+        let target = self.clamp_camera_target(focus, data);
+        self.camera.offset = Vector2::new(data.screen_width as f32 / 2.0, data.screen_height as f32 / 2.0);
+        self.camera.target = self.camera.target.lerp(target, (CAMERA_PAN_SPEED * dt).min(1.0));
+
+        if data.settings.reduce_motion {
+            self.camera.rotation = self.view_angle;
+        } else {
+            self.camera.rotation += (self.view_angle - self.camera.rotation) * (CAMERA_TURN_SPEED * dt).min(1.0);
+        }
+    }
+
+    /// how many quarter turns clockwise the view is turned, from 0 to 3.
+    pub fn view_turns(&self) -> u8 {
+        ((self.view_angle / 90.0).round() as i32).rem_euclid(4) as u8
+    }
+
+    // the direction of one of `keys` pressed this frame, if any
+    fn pressed_direction(rl: &RaylibHandle, keys: &[(Direction, KeyboardKey)]) -> Option<Direction> {
+        Self::direction_keys(keys, |key| rl.is_key_pressed(key))
+    }
+
+    // the direction of one of `keys` being held down, if any
+    fn held_direction(rl: &RaylibHandle, keys: &[(Direction, KeyboardKey)]) -> Option<Direction> {
+        Self::direction_keys(keys, |key| rl.is_key_down(key))
+    }
+
+    // the first player's movement keys: the arrows and WASD, or only WASD in co-op
+    fn movement_keys(&self) -> &'static [(Direction, KeyboardKey)] {
+        if self.players.len() > 1 { &LETTER_KEYS } else { &MOVEMENT_KEYS }
+    }
+
+    // the direction of a swipe on a touch screen, if one just ended
+    fn swipe_direction(rl: &RaylibHandle) -> Option<Direction> {
+        match rl.get_gesture_detected() {
+            Gesture::GESTURE_SWIPE_UP => Some(Direction::North),
+            Gesture::GESTURE_SWIPE_DOWN => Some(Direction::South),
+            Gesture::GESTURE_SWIPE_LEFT => Some(Direction::West),
+            Gesture::GESTURE_SWIPE_RIGHT => Some(Direction::East),
+            _ => None,
+        }
+    }
+
+    // the direction of a D-pad button pressed on the first gamepad, if any
+    fn gamepad_direction(rl: &RaylibHandle) -> Option<Direction> {
+        const BUTTONS: [(Direction, GamepadButton); 4] = [
+            (Direction::East, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+            (Direction::West, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+            (Direction::South, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+            (Direction::North, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP),
+        ];
+        if !rl.is_gamepad_available(0) {
+            return None;
+        }
+        BUTTONS.iter()
+            .find(|(_, button)| rl.is_gamepad_button_pressed(0, *button))
+            .map(|(direction, _)| *direction)
+    }
+
+    // the on-screen D-pad's buttons: which way each one moves and where its centre is
+    fn dpad_buttons(data: &GameData) -> [(Direction, Vector2); 4] {
+        let center = Vector2::new(data.screen_width as f32 - 140.0, data.screen_height as f32 - 150.0);
+        Direction::ALL.map(|direction| (direction, center + direction.to_vector() * DPAD_BUTTON_SPREAD))
+    }
+
+    // the direction of the on-screen D-pad button just tapped, if any
+    fn dpad_direction(rl: &RaylibHandle, data: &GameData) -> Option<Direction> {
+        if !rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            return None;
+        }
+        let tap = rl.get_mouse_position();
+        Self::dpad_buttons(data)
+            .iter()
+            .find(|(_, center)| center.distance_to(tap) <= DPAD_BUTTON_RADIUS)
+            .map(|(direction, _)| *direction)
+    }
+
+    // Switch to whichever kind of input the player just used.
+    fn update_input_mode(&mut self, rl: &RaylibHandle) {
+        if rl.get_touch_point_count() > 0 || Self::swipe_direction(rl).is_some() {
+            self.input_mode = InputMode::Touch;
+        } else if Self::gamepad_direction(rl).is_some() {
+            self.input_mode = InputMode::Gamepad;
+        } else if Self::pressed_direction(rl, &MOVEMENT_KEYS).is_some() {
+            self.input_mode = InputMode::Keyboard;
+        }
+    }
+
+    // the direction the player asked to move in this frame, from whichever input they use
+    fn requested_direction(&self, rl: &RaylibHandle, data: &GameData) -> Option<Direction> {
+        let touch = match self.input_mode {
+            InputMode::Touch => Self::dpad_direction(rl, data).or_else(|| Self::swipe_direction(rl)),
+            _ => None,
+        };
+        Self::pressed_direction(rl, self.movement_keys()).or(touch).or_else(|| Self::gamepad_direction(rl))
+    }
+
+    // the direction of the first of `keys` that passes `check`
+    fn direction_keys(keys: &[(Direction, KeyboardKey)], check: impl Fn(KeyboardKey) -> bool) -> Option<Direction> {
+        keys.iter().find(|(_, key)| check(*key)).map(|(direction, _)| *direction)
+    }
+
+    // A move player `index` asked for, towards `direction` on the grid. It is dropped during the
+    // cooldown after their last move, and one that goes through starts the next cooldown. The first
+    // player's is longer when creeping and shorter when `sprinting`. Whether the player moved.
+    pub(crate) fn request_move(&mut self, index: usize, direction: Direction, sprinting: bool, data: &mut GameData) -> bool {
+        if self.players[index].move_cooldown > 0.0 {
+            return false;
+        }
+        if index > 0 {
+            return self.move_follower(index, direction, data);
+        }
+        let revisits = self.revisits;
+        if !self.try_move(direction) {
+            // a blocked move nudges the player towards the wall and back, unless reduce motion is on
+            if !data.settings.reduce_motion {
+                self.bump_dir = Some(direction);
+                self.bump_timer = BUMP_DURATION;
+            }
+            return false;
+        }
+
+        // the no-backtrack challenge charges for every visited cell the move went back over
+        let new_revisits = self.revisits - revisits;
+        if data.settings.no_backtrack_challenge && new_revisits > 0 {
+            data.points = data.points.saturating_sub(REVISIT_PENALTY * new_revisits);
+            self.revisit_notice = NOTICE_DURATION;
+        }
+        self.players[0].move_cooldown = if self.creeping {
+            (data.settings.move_cooldown * 2.0).max(CREEP_MOVE_COOLDOWN)
+        } else if sprinting {
+            self.player_noise_level += SPRINT_NOISE;
+            data.settings.move_cooldown / 2.0
+        } else {
+            data.settings.move_cooldown
+        };
+        self.idle_timer = 0.0;
+        self.hint = None;
+        self.visit_corner();
+        true
+    }
+
+    /// creep at half speed but silently, or stop creeping.
+    pub(crate) fn toggle_creeping(&mut self) {
+        self.creeping = !self.creeping;
+        if self.creeping {
+            self.player_noise_level = 0.0;
+        }
+    }
+
+    // note down an input of the first player for the replay, on the frame it happened on
+    fn record(&mut self, input: InputEvent) {
+        if let Some(replay) = self.replay.as_mut() {
+            replay.inputs.push((self.frame, input));
+        }
+    }
+
+    // Move a player after the first one cell over open ground. They set nothing off on the way and
+    // stay put once they reach the open exit.
+    fn move_follower(&mut self, index: usize, direction: Direction, data: &GameData) -> bool {
+        let Player { x, y, reached_exit, .. } = self.players[index];
+        if reached_exit {
+            return false;
+        }
+        let Some((x, y)) = self.neighbour(x, y, direction).filter(|&(x, y)| self.is_open(x, y)) else { return false };
+        let at_exit = self.grid[y][x] == CellType::Exit && self.exit_open();
+        let player = &mut self.players[index];
+        (player.x, player.y) = (x, y);
+        player.move_cooldown = data.settings.move_cooldown;
+        player.reached_exit = at_exit;
+        true
+    }
+
+    // Once the player is on a new cell, play a tone for each side of it on screen that has a wall.
+    fn play_wall_cues(&mut self, data: &GameData) {
+        let cell = (self.players[0].x, self.players[0].y, self.on_lower_floor);
+        if !data.settings.wall_audio_cues || self.cue_cell == Some(cell) {
+            return;
+        }
+        self.cue_cell = Some(cell);
+        let Some(cues) = &data.wall_cues else { return };
+        let turns = self.view_turns();
+        let walls: Vec<Direction> = Direction::ALL
+            .into_iter()
+            .filter(|direction| {
+                self.neighbour(self.players[0].x, self.players[0].y, *direction)
+                    .is_none_or(|(x, y)| !self.is_valid_move(x, y))
+            })
+            .map(|direction| direction.rotated_clockwise(turns))
+            .collect();
+        cues.play(&walls);
+    }
+
+    // Whether the maze is done: the first player is on the open exit, or in co-op another player
+    // got there first in a race, or everyone is there when playing together.
+    fn level_complete(&self, mode: CoOpMode) -> bool {
+        let player_out = self.grid[self.players[0].y][self.players[0].x] == CellType::Exit && self.exit_open();
+        let others = &self.players[1..];
+        match mode {
+            _ if others.is_empty() => player_out,
+            CoOpMode::Race => player_out || others.iter().any(|p| p.reached_exit),
+            CoOpMode::Together => player_out && others.iter().all(|p| p.reached_exit),
+            CoOpMode::Off => player_out,
+        }
+    }
+
+    // the cell one step from (x, y) in the given direction, if it is still on the map
+    fn neighbour(&self, x: usize, y: usize, direction: Direction) -> Option<(usize, usize)> {
+        let (dx, dy) = direction.delta();
+        let nx = x as i32 + dx;
+        let ny = y as i32 + dy;
+        if nx < 0 || ny < 0 || nx as usize >= self.map.grid_w || ny as usize >= self.map.grid_h {
+            return None;
+        }
+        Some((nx as usize, ny as usize))
+    }
+
+    /// Try to move the player one cell. Returns false (and leaves the player where they are)
+    /// when the move is blocked. Every move is a whole cell along one axis, so there are no wall
+    /// corners to clip and nothing to slide along.
+    pub fn try_move(&mut self, direction: Direction) -> bool {
+        // leaving a wind cell, the player goes where the wind blows if that way is open
+        let direction = match self.grid[self.players[0].y][self.players[0].x] {
+            CellType::Wind(wind)
+                if self
+                    .neighbour(self.players[0].x, self.players[0].y, wind)
+                    .is_some_and(|(x, y)| self.is_valid_move(x, y)) =>
+            {
+                wind
+            }
+            _ => direction,
+        };
+        match self.neighbour(self.players[0].x, self.players[0].y, direction) {
+            Some((x, y)) if self.is_valid_move(x, y) && self.shove_enemies(x, y, direction) => {
+                self.players[0].x = x;
+                self.players[0].y = y;
+                self.facing = direction;
+                self.steps += 1;
+                self.arrive(direction);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Everything stepping onto the player's cell sets off, coming in `direction`: the visit is
+    // counted, and buttons, transformers, wormholes, mushrooms, slopes, arrows and ladders act.
+    fn arrive(&mut self, direction: Direction) {
+        let (x, y) = (self.players[0].x, self.players[0].y);
+        self.record_visit();
+        self.mark_visited();
+        self.dissolve_illusion();
+        self.press_button();
+        if self.grid[y][x] == CellType::Crumble {
+            self.crumbling.entry((x, y)).or_insert(CRUMBLE_TIME);
+        }
+        if let CellType::Transformer(mode) = self.grid[y][x] {
+            self.grid[y][x] = CellType::Path;
+            self.transform(mode);
+        }
+        self.enter_wormhole();
+        self.entering_warp = self.grid[y][x] == CellType::Warp;
+        // a new echo replaces the ghost of the last one
+        if self.grid[y][x] == CellType::Echo {
+            self.ghost = self.route_to_goal().map(|path| GhostPlayer {
+                path,
+                step: 0,
+                step_timer: 0.0,
+                time_left: ECHO_TIME,
+            });
+        }
+        if let CellType::Mushroom(distance) = self.grid[y][x] {
+            self.bounce(direction, distance);
+        }
+        if self.grid[self.players[0].y][self.players[0].x] == CellType::Quicksand {
+            self.narrator.say("The sand drags at your boots.");
+            self.quicksand_release_timer = Some(QUICKSAND_HOLD_TIME);
+        }
+        // stepping onto a slope from any side starts a slide down it
+        self.sliding = match self.grid[self.players[0].y][self.players[0].x] {
+            CellType::SlipperySlope(slope) => Some(slope),
+            _ => None,
+        };
+        self.slide_timer = SLIDE_STEP_TIME;
+        self.arrow_push = matches!(self.grid[self.players[0].y][self.players[0].x], CellType::ArrowTile(_));
+        self.climb_ladder();
+    }
+
+    // A wormhole the player steps on throws them to a random path cell on their floor at least a third
+    // of the way across the maze (counting width and height together), that they could have walked to.
+    fn enter_wormhole(&mut self) {
+        if self.grid[self.players[0].y][self.players[0].x] != CellType::Wormhole || self.wormhole_cooldown > 0.0 {
+            return;
+        }
+        let min_distance = (self.map.grid_w + self.map.grid_h) / 3;
+        let reachable = distances_from(&self.grid, (self.players[0].x, self.players[0].y));
+        let destinations: Vec<(usize, usize)> = reachable
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().filter(|(_, d)| d.is_some()).map(move |(x, _)| (x, y)))
+            .filter(|&(x, y)| {
+                self.grid[y][x] == CellType::Path && x.abs_diff(self.players[0].x) + y.abs_diff(self.players[0].y) >= min_distance
+            })
+            .collect();
+        let Some(&(x, y)) = destinations.choose(&mut self.rng) else { return };
+        self.particles.extend(burst(self.player_center(), 20, 120.0, Color::MAGENTA, &mut self.effects_rng));
+        (self.players[0].x, self.players[0].y) = (x, y);
+        self.record_visit();
+        self.mark_visited();
+        self.wormhole_cooldown = WORMHOLE_COOLDOWN;
+        self.narrator.say("The world folds, and you are somewhere else.");
+    }
+
+    // A button the player steps on starts its gates sliding open if they are shut, and shuts them
+    // straight away if they are open (or still opening).
+    fn press_button(&mut self) {
+        let CellType::Button(id) = self.grid[self.players[0].y][self.players[0].x] else { return };
+        let open = self.open_gates.insert(id);
+        if !open {
+            self.open_gates.remove(&id);
+        }
+        let cells = self.gates.get(&id).cloned().unwrap_or_default();
+        if open {
+            for &(x, y) in &cells {
+                let direction = self.slide_direction(x, y);
+                self.animating_doors.push(AnimatingDoor { x, y, progress: 0.0, direction });
+            }
+        } else {
+            self.animating_doors.retain(|door| !cells.contains(&(door.x, door.y)));
+            for &(x, y) in &cells {
+                self.grid[y][x] = CellType::LinkedWall(id);
+            }
+            self.refresh_exit_distances();
+        }
+    }
+
+    // a gate slides into the first wall beside it, or up if there is none
+    fn slide_direction(&self, x: usize, y: usize) -> Direction {
+        Direction::ALL
+            .into_iter()
+            .find(|&direction| self.neighbour(x, y, direction).is_some_and(|(nx, ny)| self.grid[ny][nx] == CellType::Wall))
+            .unwrap_or(Direction::North)
+    }
+
+    // Move the sliding gates along, opening the ones that are all the way open.
+    fn update_doors(&mut self, dt: f32) {
+        for door in &mut self.animating_doors {
+            door.progress = (door.progress + dt / DOOR_SLIDE_TIME).min(1.0);
+        }
+        if self.animating_doors.iter().any(|door| door.progress >= 1.0) {
+            for door in self.animating_doors.iter().filter(|door| door.progress >= 1.0) {
+                self.grid[door.y][door.x] = CellType::Path;
+            }
+            self.animating_doors.retain(|door| door.progress < 1.0);
+            self.refresh_exit_distances();
+        }
+    }
+
+    // open every sliding gate at once, for when the player leaves the floor
+    fn finish_doors(&mut self) {
+        for door in self.animating_doors.drain(..) {
+            self.grid[door.y][door.x] = CellType::Path;
+        }
+    }
+
+    // What is left of a sliding gate: the closed gate, shrunk towards where it slides.
+    fn draw_animating_door(&self, d: &mut impl RaylibDraw, door: &AnimatingDoor) {
+        let size = self.tile_size as f32;
+        let left = door.x as f32 * size;
+        let top = door.y as f32 * size;
+        let shift = door.progress * size;
+        let rect = match door.direction {
+            Direction::North => Rectangle::new(left, top, size, size - shift),
+            Direction::South => Rectangle::new(left, top + shift, size, size - shift),
+            Direction::West => Rectangle::new(left, top, size - shift, size),
+            Direction::East => Rectangle::new(left + shift, top, size - shift, size),
+        };
+        d.draw_rectangle_rec(rect, Color::DARKBROWN);
+        d.draw_rectangle_lines_ex(rect, 1.0, Color::BROWN);
+    }
+
+    fn draw_gate(&self, d: &mut impl RaylibDraw, x: usize, y: usize, open: bool) {
+        let left = x as i32 * self.tile_size;
+        let top = y as i32 * self.tile_size;
+        if open {
+            // the two halves swung back against the walls
+            d.draw_rectangle(left + 2, top, 3, self.tile_size, Color::BROWN);
+            d.draw_rectangle(left + self.tile_size - 5, top, 3, self.tile_size, Color::BROWN);
+        } else {
+            d.draw_rectangle(left, top, self.tile_size, self.tile_size, Color::DARKBROWN);
+            let mut bar = left + self.tile_size / 6;
+            while bar < left + self.tile_size {
+                d.draw_line(bar, top, bar, top + self.tile_size, Color::BROWN);
+                bar += self.tile_size / 3;
+            }
+        }
+    }
+
+    // A warp: violet rings shrinking towards the middle, over and over.
+    fn draw_warp(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let max_radius = self.tile_size as f32 * 0.45;
+        for ring in 0..3 {
+            let phase = (self.elapsed * 0.8 + ring as f32 / 3.0).fract();
+            d.draw_circle_lines(center.x as i32, center.y as i32, max_radius * (1.0 - phase), Color::VIOLET);
+        }
+        d.draw_circle_v(center, self.tile_size as f32 * 0.1, Color::DARKPURPLE);
+    }
+
+    // An echo: a faint blue footprint inside a ring.
+    fn draw_echo(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        d.draw_circle_lines(center.x as i32, center.y as i32, size * 0.4, Color::SKYBLUE);
+        d.draw_ellipse(center.x as i32, (center.y + size * 0.05) as i32, size * 0.1, size * 0.18, Color::SKYBLUE);
+        d.draw_circle_v(Vector2::new(center.x, center.y - size * 0.2), size * 0.06, Color::SKYBLUE);
+    }
+
+    // A wormhole: rings of different colours, each turning at its own speed.
+    fn draw_wormhole(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        let rings = [(0.42, Color::MAGENTA, 1.0), (0.3, Color::SKYBLUE, -1.6), (0.18, Color::GOLD, 2.3)];
+        for (radius, color, speed) in rings {
+            let start = (self.elapsed * speed * 180.0).rem_euclid(360.0);
+            d.draw_ring(center, size * (radius - 0.06), size * radius, start, start + 270.0, 12, color);
+        }
+        d.draw_circle_v(center, size * 0.06, Color::BLACK);
+    }
+
+    fn draw_button(&self, d: &mut impl RaylibDraw, x: usize, y: usize, id: ButtonId) {
+        let inset = self.tile_size / 5;
+        let left = x as i32 * self.tile_size + inset;
+        let top = y as i32 * self.tile_size + inset;
+        let size = self.tile_size - inset * 2;
+        // a raised button looks pushed in while its gates are open
+        let raised = if self.open_gates.contains(&id) { 0 } else { 3 };
+        d.draw_rectangle(left, top + 3, size, size - 3, Color::DARKGRAY);
+        d.draw_rectangle(left, top + 3 - raised, size, size - 3, Color::GRAY);
+    }
+
+    // An illusion the player steps into turns into a path, leaving a ghost of the wall to fade out.
+    fn dissolve_illusion(&mut self) {
+        let (x, y) = (self.players[0].x, self.players[0].y);
+        if self.grid[y][x] == CellType::Illusion {
+            self.grid[y][x] = CellType::Path;
+            self.dissolving_walls.insert((x, y), ILLUSION_DISSOLVE_TIME);
+        }
+    }
+
+    // The rival's way from the start to the exit with A*, by way of the ladder on a two-floor dungeon.
+    // Gates do not stop it.
+    fn rival_route(&self) -> Option<Vec<(bool, usize, usize)>> {
+        let opened = |grid: &[Vec<CellType>]| -> Vec<Vec<CellType>> {
+            grid.iter()
+                .map(|row| row.iter().map(|c| if matches!(c, CellType::LinkedWall(_)) { CellType::Path } else { *c }).collect())
+                .collect()
+        };
+        let start = (self.players[0].x, self.players[0].y);
+        let on_floor = |lower: bool| move |(x, y): (usize, usize)| (lower, x, y);
+        match &self.floor {
+            Some(lower) => {
+                let ladder = position_of(&self.grid, |c| matches!(c, CellType::Ladder(_)))?;
+                let exit = position_of(lower, |c| *c == CellType::Exit)?;
+                let upper_leg = solve_astar(&opened(&self.grid), start, ladder)?;
+                let lower_leg = solve_astar(&opened(lower), ladder, exit)?;
+                // the ladder is one cell, at the top and the bottom
+                let path = upper_leg.into_iter().map(on_floor(false)).chain(lower_leg.into_iter().skip(1).map(on_floor(true)));
+                Some(path.collect())
+            }
+            None => {
+                let exit = self.find_cell(CellType::Exit)?;
+                Some(solve_astar(&opened(&self.grid), start, exit)?.into_iter().map(on_floor(false)).collect())
+            }
+        }
+    }
+
+    // The rival: a purple player part of the way from its cell to the next, when it is on the
+    // player's floor.
+    fn draw_rival(&self, d: &mut impl RaylibDraw, rival: &Rival) {
+        let Some(&(lower, x, y)) = rival.path.get(rival.step) else { return };
+        if lower != self.on_lower_floor {
+            return;
+        }
+        let from = self.cell_center(x, y);
+        let position = match rival.path.get(rival.step + 1) {
+            Some(&(next_lower, next_x, next_y)) if next_lower == lower => {
+                from.lerp(self.cell_center(next_x, next_y), (rival.step_timer / RIVAL_STEP_TIME).min(1.0))
+            }
+            _ => from,
+        };
+        d.draw_circle_v(position, self.tile_size as f32 * 0.35, Color::PURPLE);
+    }
+
+    // Walk the echo's ghost along its path a cell every `step_time` seconds (the player's own pace),
+    // until it fades away.
+    fn update_ghost(&mut self, dt: f32, step_time: f32) {
+        let Some(ghost) = self.ghost.as_mut() else { return };
+        ghost.time_left -= dt;
+        if ghost.time_left <= 0.0 {
+            self.ghost = None;
+            return;
+        }
+        ghost.step_timer += dt;
+        let step_time = step_time.max(ECHO_MIN_STEP_TIME);
+        while ghost.step_timer >= step_time && ghost.step + 1 < ghost.path.len() {
+            ghost.step_timer -= step_time;
+            ghost.step += 1;
+        }
+    }
+
+    // The ghost: a see-through blue player, part of the way from its cell to the next one.
+    fn draw_ghost(&self, d: &mut impl RaylibDraw, ghost: &GhostPlayer, step_time: f32) {
+        let Some(&(x, y)) = ghost.path.get(ghost.step) else { return };
+        let from = self.cell_center(x, y);
+        let position = match ghost.path.get(ghost.step + 1) {
+            Some(&(next_x, next_y)) => {
+                let t = (ghost.step_timer / step_time.max(ECHO_MIN_STEP_TIME)).min(1.0);
+                from.lerp(self.cell_center(next_x, next_y), t)
+            }
+            None => from,
+        };
+        // fade out over the last half second
+        let alpha = 0.5 * (ghost.time_left / 0.5).min(1.0);
+        d.draw_circle_v(position, self.tile_size as f32 * 0.35, Color::BLUE.alpha(alpha));
+    }
+
+    // Count down the crumbling floor, turning each into a pit once its time is up. A player still
+    // standing on it falls in, and climbs out again once the fall is over.
+    fn update_crumbling(&mut self, dt: f32, data: &mut GameData) {
+        let mut given_way = Vec::new();
+        self.crumbling.retain(|&cell, time_left| {
+            *time_left -= dt;
+            if *time_left <= 0.0 {
+                given_way.push(cell);
+            }
+            *time_left > 0.0
+        });
+        for (x, y) in given_way {
+            self.grid[y][x] = CellType::Pit;
+            if (x, y) == (self.players[0].x, self.players[0].y) {
+                self.fall_timer = Some(PIT_FALL_TIME);
+                self.sliding = None;
+                self.narrator.say("The floor gives way beneath you!");
+                self.damage_player(PIT_FALL_DAMAGE, data);
+            }
+        }
+
+        let Some(time_left) = self.fall_timer.as_mut() else { return };
+        *time_left -= dt;
+        if *time_left > 0.0 {
+            return;
+        }
+        self.fall_timer = None;
+        // out onto the first floor beside the pit
+        let out = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| self.neighbour(self.players[0].x, self.players[0].y, direction))
+            .find(|&(x, y)| self.is_valid_move(x, y));
+        if let Some((x, y)) = out {
+            (self.players[0].x, self.players[0].y) = (x, y);
+            self.record_visit();
+            self.mark_visited();
+        }
+    }
+
+    // all crumbling floor gives way at once, for when the player leaves the floor
+    fn finish_crumbling(&mut self) {
+        for ((x, y), _) in self.crumbling.drain() {
+            self.grid[y][x] = CellType::Pit;
+        }
+    }
+
+    // Crumbling floor: a sandy cell with a crack across it. Once stepped on, a dark outline closes in
+    // as the time before it gives way runs out.
+    fn draw_crumble(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let screen_x = x as i32 * self.tile_size;
+        let screen_y = y as i32 * self.tile_size;
+        let size = self.tile_size as f32;
+        d.draw_rectangle(screen_x, screen_y, self.tile_size, self.tile_size, Color::new(194, 170, 130, 255));
+        let corner = Vector2::new(screen_x as f32, screen_y as f32);
+        d.draw_line_v(corner + Vector2::new(size * 0.2, size * 0.3), corner + Vector2::new(size * 0.5, size * 0.5), Color::BROWN);
+        d.draw_line_v(corner + Vector2::new(size * 0.5, size * 0.5), corner + Vector2::new(size * 0.8, size * 0.4), Color::BROWN);
+        if let Some(time_left) = self.crumbling.get(&(x, y)) {
+            let progress = 1.0 - time_left / CRUMBLE_TIME;
+            let inset = (progress * size * 0.5) as i32;
+            let inner = self.tile_size - inset * 2;
+            d.draw_rectangle_lines(screen_x + inset, screen_y + inset, inner, inner, Color::DARKBROWN);
+        }
+    }
+
+    // A pit: black, with a dark rim.
+    fn draw_pit(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let screen_x = x as i32 * self.tile_size;
+        let screen_y = y as i32 * self.tile_size;
+        d.draw_rectangle(screen_x, screen_y, self.tile_size, self.tile_size, Color::DARKBROWN);
+        let rim = self.tile_size / 8;
+        d.draw_rectangle(screen_x + rim, screen_y + rim, self.tile_size - rim * 2, self.tile_size - rim * 2, Color::BLACK);
+    }
+
+    // Slide the player on down the slope they are on, one cell every `SLIDE_STEP_TIME` seconds. The
+    // slide stops at a wall, or once a step takes them off the slope.
+    fn update_slide(&mut self, dt: f32) {
+        let Some(direction) = self.sliding else { return };
+        self.slide_timer -= dt;
+        if self.slide_timer > 0.0 {
+            return;
+        }
+        if !self.try_move(direction) {
+            self.sliding = None;
+        }
+    }
+
+    // Start walking the player back along their breadcrumbs, if they have left any. Whether they are
+    // on their way.
+    fn start_retrace(&mut self) -> bool {
+        if self.breadcrumbs.len() > 1 {
+            self.retracing = true;
+            self.retrace_timer = 0.0;
+        }
+        self.retracing
+    }
+
+    // Walk the player back one breadcrumb every `RETRACE_STEP_TIME` seconds. The first breadcrumb of
+    // a lower floor is the ladder, which takes them up onto the breadcrumbs of the floor above, so
+    // the walk goes on to the start. It stops early if the way back is blocked or is not a step,
+    // e.g. where a wormhole took them, or if a slope takes over.
+    fn update_retrace(&mut self, dt: f32) {
+        if !self.retracing {
+            return;
+        }
+        self.retrace_timer -= dt;
+        if self.retrace_timer > 0.0 {
+            return;
+        }
+        self.retrace_timer += RETRACE_STEP_TIME;
+        let Some(&target) = self.breadcrumbs.iter().rev().nth(1) else {
+            self.retracing = false;
+            return;
+        };
+        let step = Direction::ALL
+            .into_iter()
+            .find(|&direction| self.neighbour(self.players[0].x, self.players[0].y, direction) == Some(target));
+        if !step.is_some_and(|direction| self.try_move(direction))
+            || (self.players[0].x, self.players[0].y) != target
+            || self.sliding.is_some()
+        {
+            self.retracing = false;
+        }
+    }
+
+    // a generator of its own, drawn from the scene's, for when the scene's is borrowed already
+    fn fork_rng(&mut self) -> StdRng {
+        StdRng::seed_from_u64(self.rng.random())
+    }
+
+    // Change how the player moves for `TRANSFORM_TIME` seconds.
+    fn transform(&mut self, mode: MoveMode) {
+        self.move_mode = mode;
+        self.mode_timer = TRANSFORM_TIME;
+        self.narrator.say(match mode {
+            MoveMode::Normal => "You feel like yourself again.",
+            MoveMode::Ghost => "Your hands pass through the stone.",
+            MoveMode::Giant => "Your head scrapes the ceiling.",
+            MoveMode::Tiny => "The corridor towers over you.",
+        });
+    }
+
+    // Count down the player's transformation. Turned back, a player left in a wall or under a gate
+    // is put out on the nearest open cell they could have walked to from the last one they stood on.
+    fn update_move_mode(&mut self, dt: f32) {
+        if self.is_open(self.players[0].x, self.players[0].y) {
+            self.last_open_cell = (self.players[0].x, self.players[0].y);
+        }
+        if self.move_mode == MoveMode::Normal {
+            return;
+        }
+        self.mode_timer -= dt;
+        if self.mode_timer > 0.0 {
+            return;
+        }
+        self.move_mode = MoveMode::Normal;
+        if self.is_open(self.players[0].x, self.players[0].y) {
+            return;
+        }
+        // the last open cell can be on the other floor after a ghost climbs a ladder, and then any
+        // open cell will do
+        let reachable = distances_from(&self.grid, self.last_open_cell);
+        let open: Vec<(usize, usize)> = (0..self.map.grid_h)
+            .flat_map(|y| (0..self.map.grid_w).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_open(x, y))
+            .collect();
+        let distance = |&(x, y): &(usize, usize)| x.abs_diff(self.players[0].x) + y.abs_diff(self.players[0].y);
+        let nearest = open
+            .iter()
+            .filter(|&&(x, y)| reachable[y][x].is_some())
+            .min_by_key(|cell| distance(cell))
+            .or_else(|| open.iter().min_by_key(|cell| distance(cell)))
+            .copied();
+        if let Some((x, y)) = nearest {
+            (self.players[0].x, self.players[0].y) = (x, y);
+            self.record_visit();
+            self.mark_visited();
+        }
+    }
+
+    // Shove the enemies in the way of a giant stepping `direction` to (x, y) along until they are
+    // clear of it. Returns false, shoving nobody, if any of them has nowhere to go.
+    fn shove_enemies(&mut self, x: usize, y: usize, direction: Direction) -> bool {
+        if self.move_mode != MoveMode::Giant {
+            return true;
+        }
+        let under_giant = |ex: usize, ey: usize| (x..x + 2).contains(&ex) && (y..y + 2).contains(&ey);
+        let mut shoves = Vec::new();
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            let mut to = Some((enemy.x, enemy.y));
+            while let Some((tx, ty)) = to
+                && under_giant(tx, ty)
+            {
+                to = self.neighbour(tx, ty, direction).filter(|&(nx, ny)| self.grid[ny][nx].is_enemy_walkable());
+            }
+            match to {
+                Some(to) if to != (enemy.x, enemy.y) => shoves.push((i, to)),
+                Some(_) => {}
+                None => return false,
+            }
+        }
+        for (i, (to_x, to_y)) in shoves {
+            self.enemies[i].x = to_x;
+            self.enemies[i].y = to_y;
+        }
+        true
+    }
+
+    // Launch the player up to `distance` more cells along `direction`, stopping early at a wall.
+    // The cell they land on is not bounced from again, even if it is another mushroom.
+    fn bounce(&mut self, direction: Direction, distance: u8) {
+        self.particles.extend(burst(self.player_center(), 20, 120.0, Color::GREEN, &mut self.effects_rng));
+        if !self.creeping {
+            self.player_noise_level += DASH_NOISE;
+        }
+        for _ in 0..distance {
+            match self.neighbour(self.players[0].x, self.players[0].y, direction) {
+                // nothing stops a bounce into lava
+                Some((x, y)) if self.grid[y][x] == CellType::Lava => {
+                    self.players[0].x = x;
+                    self.players[0].y = y;
+                    self.burned = true;
+                    break;
+                }
+                Some((x, y)) if self.is_valid_move(x, y) => {
+                    self.players[0].x = x;
+                    self.players[0].y = y;
+                    self.record_visit();
+                    self.mark_visited();
+                    self.dissolve_illusion();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Carry out a line typed into the debug console, printing what came of it there. Starting over
+    /// on another maze is left to the scene switch returned.
+    pub fn exec_command(&mut self, line: &str, data: &mut GameData) -> SceneSwitch {
+        let seed = match ConsoleCommand::parse(line) {
+            Ok(ConsoleCommand::Seed(seed)) => seed,
+            Ok(ConsoleCommand::Regen) => self.rng.random(),
+            Ok(command) => {
+                let reply = self.run_command(command, data);
+                self.console.print(reply);
+                return SceneSwitch::None;
+            }
+            Err(error) => {
+                self.console.print(error);
+                return SceneSwitch::None;
+            }
+        };
+        let (width, height, algorithm, floors) = match self.source {
+            MazeSource::Generated { width, height, algorithm, floors, .. } => (width, height, algorithm, floors),
+            _ => (data.difficulty.maze_width, data.difficulty.maze_height, data.settings.maze_algorithm, data.settings.dungeon_floors),
+        };
+        SceneSwitch::Replace(Box::new(MazeScene::generated(width, height, algorithm, seed).with_floors(floors)))
+    }
+
+    // Carry out a debug console command, returning what to print in the console.
+    fn run_command(&mut self, command: ConsoleCommand, data: &mut GameData) -> String {
+        match command {
+            ConsoleCommand::Warp(x, y) if self.is_valid_move(x, y) => {
+                self.players[0].x = x;
+                self.players[0].y = y;
+                self.arrive(self.facing);
+                format!("warped to {} {}", x, y)
+            }
+            ConsoleCommand::Warp(x, y) => format!("cannot stand at {} {}", x, y),
+            ConsoleCommand::SetHealth(health) => {
+                data.health = health.min(data.max_health);
+                format!("health is {}", data.health)
+            }
+            ConsoleCommand::Give(item) => {
+                data.inventory.push(item);
+                format!("gave a {}", item.map_kind())
+            }
+            ConsoleCommand::SetLevel(level) => {
+                data.level = level.max(1);
+                format!("level is {}", data.level)
+            }
+            ConsoleCommand::ToggleFog => {
+                self.fog_hidden = !self.fog_hidden;
+                format!("fog {}", if self.fog_hidden { "off" } else { "on" })
+            }
+            ConsoleCommand::KillEnemies => {
+                let count = self.enemies.len();
+                for enemy in self.enemies.drain(..) {
+                    self.registry.destroy(enemy.id);
+                }
+                format!("removed {} enemies", count)
+            }
+            ConsoleCommand::Reveal => {
+                self.reveal(RevealedRegion { x: 0, y: 0, width: self.map.grid_w, height: self.map.grid_h });
+                "floor revealed".to_string()
+            }
+            ConsoleCommand::GiveCoins(count) => {
+                data.coins += count;
+                format!("coins are {}", data.coins)
+            }
+            ConsoleCommand::Heatmap => match self.export_visit_heatmap() {
+                Ok(path) => format!("wrote {}", path),
+                Err(e) => format!("could not write the heatmap: {}", e),
+            },
+            ConsoleCommand::Seed(_) | ConsoleCommand::Regen => unreachable!("exec_command starts over itself"),
+        }
+    }
+
+    /// In debug builds, panic if the player has ended up off the map or inside a wall, to catch
+    /// movement bugs where they happen. Lava only ever holds a player a bounce threw in, who is
+    /// about to die, and a pit one falling into it.
+    pub fn debug_assert_player_valid(&self) {
+        let (x, y) = (self.players[0].x, self.players[0].y);
+        let cell = self.grid.get(y).and_then(|row| row.get(x));
+        debug_assert!(cell.is_some(), "player is off the map at ({}, {})", x, y);
+        debug_assert!(
+            cell.is_none_or(|c| {
+                c.is_walkable()
+                    || (*c == CellType::Lava && self.burned)
+                    || (*c == CellType::Pit && self.fall_timer.is_some())
+                    || self.move_mode != MoveMode::Normal
+            }),
+            "player is standing on {:?} at ({}, {})",
+            cell,
+            x,
+            y
+        );
+    }
+
+    // Set the maze up from its source: its floors and everything placed on them, the player at the
+    // start and the camera on them. All of entering it but loading the textures.
+    pub(crate) fn build(&mut self, data: &mut GameData) {
+        self.spawn_safe_radius = data.difficulty.spawn_safe_radius;
+        self.replay = match self.source {
+            MazeSource::Generated { seed, algorithm, floors, .. } if !self.side_maze => {
+                Some(ReplayData { seed, difficulty: data.difficulty, algorithm, floors, inputs: Vec::new() })
+            }
+            _ => None,
+        };
+        self.frame = 0;
+        if data.settings.wall_audio_cues && data.wall_cues.is_none() {
+            data.wall_cues = WallCues::load();
+        }
+        match &self.source {
+            MazeSource::File(map_path) => {
+                self.map = load_map(map_path);
+                self.grid = grid_from_map(&self.map);
+                self.triggered_messages = self.map.messages.iter().map(|m| ((m.x, m.y), m.text.clone())).collect();
+                let report = validate_map(&self.map, &self.grid);
+                for error in &report.errors {
+                    debug_log!("{}: {}", map_path, error);
+                }
+                for warning in &report.warnings {
+                    debug_log!("{}: warning: {}", map_path, warning);
+                }
+            }
+            MazeSource::Binary(path) => {
+                let maze = std::fs::read(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| FlatGrid::from_binary(&bytes).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| panic!("Failed to load {}: {}", path, e));
+                self.map = MapData::blank(maze.width, maze.height, self.map.tile_size_px);
+                self.grid = maze.to_rows();
+            }
+            &MazeSource::Generated { width, height, algorithm, seed, floors } => {
+                let maze = MultiFloorMaze::generate_with(algorithm, floors, width, height, seed);
+                self.connections = maze.connections;
+                let mut floors = maze.floors;
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(3));
+                let weights = data.difficulty.cell_weights();
+                for floor in floors.iter_mut() {
+                    scatter_weighted(floor, &weights, WEIGHTED_CELLS_PER_FLOOR, &mut rng);
+                }
+                for floor in floors.iter_mut() {
+                    scatter_cells(floor, CellType::Altar, ALTARS_PER_FLOOR, &mut rng);
+                }
+                for (i, floor) in floors.iter_mut().enumerate() {
+                    scatter_cells(floor, CellType::Vendor, VENDORS_PER_FLOOR, &mut rng);
+                    if i == 0 && !self.side_maze {
+                        scatter_cells(floor, CellType::Warp, WARPS_PER_DUNGEON, &mut rng);
+                    }
+                }
+                for (cell, count) in [(CellType::MapFragment, MAP_FRAGMENTS_PER_FLOOR), (CellType::Echo, ECHOES_PER_FLOOR), (CellType::Wormhole, WORMHOLES_PER_FLOOR)] {
+                    for floor in floors.iter_mut() {
+                        scatter_cells(floor, cell, count, &mut rng);
+                    }
+                }
+                // a giant cannot fit down one-cell corridors, so only room mazes turn the player into one
+                let modes: &[MoveMode] = match algorithm {
+                    MazeAlgorithm::DungeonRooms => &[MoveMode::Ghost, MoveMode::Giant, MoveMode::Tiny],
+                    _ => &[MoveMode::Ghost, MoveMode::Tiny],
+                };
+                for floor in floors.iter_mut() {
+                    if let Some(&mode) = modes.choose(&mut rng) {
+                        scatter_cells(floor, CellType::Transformer(mode), TRANSFORMERS_PER_FLOOR, &mut rng);
+                    }
+                }
+                for floor in floors.iter_mut() {
+                    place_lava(floor, LAVA_PER_FLOOR, &mut rng);
+                }
+                let mut floors = floors.into_iter();
+                let upper = floors.next().unwrap_or_default();
+                // the generator rounds sizes up to odd numbers
+                self.map = MapData::blank(upper[0].len(), upper.len(), self.map.tile_size_px);
+                self.grid = upper;
+                self.floor = floors.next();
+                self.on_lower_floor = false;
+            }
+        }
+        self.tile_size = fitted_tile_size(self.map.tile_size_px, data.screen_width, data.screen_height);
+        self.lights = torches(&self.grid);
+        self.floor_lights = self.floor.as_deref().map(torches).unwrap_or_default();
+
+        let (x, y) = self.find_cell(CellType::Start).unwrap_or((self.players[0].x, self.players[0].y));
+        self.players = vec![Player::at(x, y, Color::WHITE)];
+        if data.settings.co_op != CoOpMode::Off {
+            self.players.push(Player::at(x, y, Color::ORANGE));
+        }
+
+        // Rising water floods the exit's floor from the bottom, so its exit goes to the top
+        self.rising_water = data.settings.rising_water;
+        self.water_level_row = self.map.grid_h.saturating_sub(2);
+        self.water_rise_timer = WATER_RISE_INTERVAL;
+        if self.rising_water {
+            let start = (self.players[0].x, self.players[0].y);
+            match self.floor.as_mut() {
+                Some(lower) => {
+                    let ladder = position_of(lower, |c| matches!(c, CellType::Ladder(_))).unwrap_or(start);
+                    move_exit_to_top(lower, ladder);
+                }
+                None => move_exit_to_top(&mut self.grid, start),
+            }
+        }
+
+        // Mines go on generated floors once the exit has settled, keeping clear of the way across each
+        // floor: from the start to the ladder and from the ladder down to the exit, or from the start to
+        // the exit of a single floor
+        if let &MazeSource::Generated { seed, .. } = &self.source {
+            let start = (self.players[0].x, self.players[0].y);
+            let way_out = position_of(&self.grid, |c| matches!(c, CellType::Ladder(_) | CellType::Exit)).unwrap_or(start);
+            let mut legs = vec![(&mut self.grid, start, way_out)];
+            if let Some(lower) = self.floor.as_mut() {
+                let exit = position_of(lower, |c| *c == CellType::Exit).unwrap_or(way_out);
+                legs.push((lower, way_out, exit));
+            }
+
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(4));
+            for (grid, from, to) in legs.iter_mut() {
+                place_mines(grid, MINES_PER_FLOOR, *from, *to, &mut rng);
+            }
+
+            // and some of the walls closing off dead ends are only illusions
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(6));
+            for (grid, from, to) in legs.iter_mut() {
+                place_illusions(grid, ILLUSION_FRACTION, *from, *to, &mut rng);
+            }
+
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(7));
+            for (grid, from, _) in legs.iter_mut() {
+                place_wind(grid, WIND_PER_FLOOR, *from, &mut rng);
+            }
+            for (grid, from, _) in legs.iter_mut() {
+                place_arrows(grid, ARROWS_PER_FLOOR, *from, &mut rng);
+            }
+            for (grid, from, _) in legs.iter_mut() {
+                place_slopes(grid, SLOPES_PER_FLOOR, *from, &mut rng);
+            }
+            for (grid, from, _) in legs.iter_mut() {
+                place_crumble(grid, CRUMBLE_PER_FLOOR, *from, &mut rng);
+            }
+
+            // a gate across the way on each floor, with its button back towards where the floor starts
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(8));
+            let mut next_id = 0;
+            for (grid, from, to) in legs.iter_mut() {
+                next_id = place_buttons(grid, BUTTONS_PER_FLOOR, next_id, *from, *to, &mut rng);
+            }
+        }
+        self.gates = gates(&self.grid);
+        self.floor_gates = self.floor.as_deref().map(gates).unwrap_or_default();
+        self.open_gates.clear();
+
+        self.rival = if data.settings.race_rival && !self.side_maze {
+            self.rival_route().map(|path| Rival { path, step: 0, step_timer: 0.0 })
+        } else {
+            None
+        };
+
+        // Four corners mode: generated floors get a way into every corner of the exit's floor, and a
+        // corner that still cannot be reached (on a hand-made map) counts as visited from the start
+        self.four_corners = data.settings.four_corners;
+        self.corners_visited = [true; 4];
+        if self.four_corners {
+            let start = (self.players[0].x, self.players[0].y);
+            let reachable = match (&self.source, self.floor.as_mut()) {
+                (MazeSource::Generated { .. }, Some(lower)) => {
+                    let ladder = position_of(lower, |c| matches!(c, CellType::Ladder(_))).unwrap_or(start);
+                    open_corners(lower, ladder);
+                    reachable_corners(lower, ladder)
+                }
+                (MazeSource::Generated { .. }, None) => {
+                    open_corners(&mut self.grid, start);
+                    reachable_corners(&self.grid, start)
+                }
+                (_, Some(lower)) => reachable_corners(lower, start),
+                (_, None) => reachable_corners(&self.grid, start),
+            };
+            self.corners_visited = reachable.map(|r| !r);
+        }
+
+        // Checkpoints go on the exit's floor, reachable from where the player arrives on it: the start
+        // of a single floor, or the bottom of the ladder. Generated dungeons place them from the seed.
+        self.next_goal = 0;
+        self.goals = match (&self.source, &self.floor) {
+            (&MazeSource::Generated { seed, .. }, Some(lower)) => {
+                let ladder = position_of(lower, |c| matches!(c, CellType::Ladder(_)));
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(2));
+                place_checkpoints(lower, ladder.unwrap_or((1, 1)), data.settings.checkpoints, &mut rng)
+            }
+            (&MazeSource::Generated { seed, .. }, None) => {
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(2));
+                place_checkpoints(&self.grid, (self.players[0].x, self.players[0].y), data.settings.checkpoints, &mut rng)
+            }
+            _ => place_checkpoints(&self.grid, (self.players[0].x, self.players[0].y), data.settings.checkpoints, &mut self.rng),
+        };
+
+        self.elapsed = 0.0;
+        self.steps = 0;
+        self.par_steps = self.compute_par_steps();
+
+        self.registry.clear();
+        let map_enemies: Vec<_> = self
+            .map
+            .entities
+            .iter()
+            .filter_map(|e| EnemyKind::from_map_kind(&e.kind).map(|kind| Enemy::new(kind, e.x, e.y)))
+            .collect();
+        self.enemies.clear();
+        self.floor_enemies.clear();
+        for enemy in map_enemies {
+            self.spawn_enemy(enemy);
+        }
+        for (index, entity) in self.map.entities.iter_mut().enumerate() {
+            if entity.kind == "treasure" || Item::from_map_kind(&entity.kind).is_some() {
+                entity.id = self.registry.create(EntityKind::Collectible(index));
+            }
+        }
+
+        // Harder generated dungeons have a minotaur guarding the exit, on the lower floor if there is one
+        if let &MazeSource::Generated { seed, .. } = &self.source
+            && data.difficulty.has_minotaur()
+        {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(5));
+            match &self.floor {
+                Some(lower) => {
+                    if let Some(patrol) = minotaur_patrol(lower, &mut rng) {
+                        let mut minotaur = Enemy::minotaur(patrol);
+                        minotaur.id = self.registry.create(EntityKind::FloorEnemy(self.floor_enemies.len()));
+                        self.floor_enemies.push(minotaur);
+                    }
+                }
+                None => {
+                    if let Some(patrol) = minotaur_patrol(&self.grid, &mut rng) {
+                        self.spawn_enemy(Enemy::minotaur(patrol));
+                    }
+                }
+            }
+        }
+
+        self.refresh_exit_distances();
+
+        self.visit_counts = vec![vec![0; self.map.grid_w]; self.map.grid_h];
+        self.clear_trail();
+        self.record_visit();
+        self.visited_cells.clear();
+        self.floor_visited_cells.clear();
+        self.breadcrumbs.clear();
+        self.floor_breadcrumbs.clear();
+        self.retracing = false;
+        self.revealed_regions.clear();
+        self.floor_revealed_regions.clear();
+        self.discovered = vec![vec![false; self.map.grid_w]; self.map.grid_h];
+        self.floor_discovered = self.discovered.clone();
+        self.explored = vec![vec![false; self.map.grid_w]; self.map.grid_h];
+        self.floor_explored = self.explored.clone();
+        self.explore_around();
+        self.wall_age = vec![vec![0; self.map.grid_w]; self.map.grid_h];
+        self.floor_wall_age = self.wall_age.clone();
+        self.backtracked = false;
+        self.revisits = 0;
+        self.mark_visited();
+
+        // every maze starts the right way up
+        self.view_angle = 0.0;
+        self.camera.rotation = 0.0;
+
+        // start with the camera already on the player instead of panning in from the corner
+        self.camera.offset = Vector2::new(data.screen_width as f32 / 2.0, data.screen_height as f32 / 2.0);
+        self.camera.target = self.clamp_camera_target(self.player_center(), data);
+    }
+
+    // one frame of play. `update` checks the player is still on a sensible cell after it
+    fn advance(&mut self, dt: f32, data: &mut GameData) -> SceneSwitch {
+        self.elapsed += dt;
+        // a time trial's clock keeps running from one maze to the next
+        if let Some(time_left) = data.time_trial.as_mut() {
+            *time_left -= dt;
+            if *time_left <= 0.0 {
+                return SceneSwitch::Replace(Box::new(TimeTrialSummaryScene));
+            }
+        }
+        self.collect_items(data);
+        self.update_events(dt);
+
+        // a speed boost event makes every enemy and projectile live through time faster, a time
+        // stop holds them (and the water) still
+        if self.time_stop_remaining > 0.0 {
+            self.time_stop_remaining = (self.time_stop_remaining - dt).max(0.0);
+        } else {
+            let world_dt = dt * self.speed_factor();
+            self.update_enemies(world_dt, data);
+            self.update_projectiles(world_dt, data);
+            self.update_water(dt, data);
+        }
+        self.update_camera(dt, data);
+        self.update_wall_age();
+        self.update_sound_traps(dt);
+
+        if self.grid[self.players[0].y][self.players[0].x] == CellType::Mine {
+            self.detonate(self.players[0].x, self.players[0].y, data);
+        }
+
+        for particle in self.particles.iter_mut() {
+            particle.update(dt);
+        }
+        self.particles.retain(|p| p.is_alive());
+
+        if let Some(tooltip) = &mut self.tooltip {
+            tooltip.remaining -= dt;
+            if tooltip.remaining <= 0.0 {
+                self.tooltip = None;
+            }
+        }
+
+        self.screenshot_notice = (self.screenshot_notice - dt).max(0.0);
+        self.revisit_notice = (self.revisit_notice - dt).max(0.0);
+        self.no_hints_notice = (self.no_hints_notice - dt).max(0.0);
+        self.narrator.update(dt);
+
+        self.idle_timer += dt;
+        if self.idle_timer < WALK_IDLE_TIME {
+            self.walk_timer += dt;
+            while self.walk_timer >= WALK_FRAME_TIME {
+                self.walk_timer -= WALK_FRAME_TIME;
+                self.walk_frame += 1;
+            }
+        } else {
+            self.walk_frame = 0;
+            self.walk_timer = 0.0;
+        }
+        if self.idle_timer >= HINT_IDLE_TIME && self.hints_left > 0 {
+            self.show_hint();
+        }
+        if let Some((_, time_left)) = &mut self.hint {
+            *time_left -= dt;
+            if *time_left <= 0.0 {
+                self.hint = None;
+            }
+        }
+        for player in &mut self.players {
+            player.move_cooldown = (player.move_cooldown - dt).max(0.0);
+        }
+        if let Some(time_left) = self.quicksand_release_timer.as_mut() {
+            *time_left -= dt;
+        }
+        self.update_slide(dt);
+        // An arrow pushes the player on one cell its way. Arrows pushing them onto more arrows all
+        // fire this frame, but no more than `max_chain` of them, in case they point round in a loop.
+        let max_chain: u8 = 10;
+        let mut chain = 0;
+        while self.arrow_push && chain < max_chain {
+            self.arrow_push = false;
+            if let CellType::ArrowTile(direction) = self.grid[self.players[0].y][self.players[0].x] {
+                self.try_move(direction);
+            }
+            chain += 1;
+        }
+        self.arrow_push = false;
+        self.update_retrace(dt);
+        self.wormhole_cooldown = (self.wormhole_cooldown - dt).max(0.0);
+        self.update_move_mode(dt);
+        self.update_ghost(dt, data.settings.move_cooldown);
+        self.update_crumbling(dt, data);
+        // the rival stops with everything else while time does
+        if let Some(rival) = self.rival.as_mut()
+            && self.time_stop_remaining <= 0.0
+        {
+            rival.advance(dt);
+        }
+        self.dissolving_walls.retain(|_, time_left| {
+            *time_left -= dt;
+            *time_left > 0.0
+        });
+        self.update_doors(dt);
+
+        if self.bump_timer > 0.0 {
+            self.bump_timer = (self.bump_timer - dt).max(0.0);
+            if self.bump_timer == 0.0 {
+                self.bump_dir = None;
+            }
+        }
+
+        if self.burned {
+            data.health = 0;
+        }
+        // losing the race ends the run just like dying
+        let beaten = self.rival.as_ref().is_some_and(Rival::finished) && !self.level_complete(data.settings.co_op);
+        if beaten {
+            self.narrator.say("Your rival reached the exit first.");
+        }
+        if data.health <= 0 || beaten {
+            if self.side_maze {
+                return SceneSwitch::Pop;
+            }
+            data.difficulty.adjust_difficulty(self.run_stats(true));
+            if data.time_trial.is_some() {
+                return SceneSwitch::Replace(Box::new(TimeTrialSummaryScene));
+            }
+            // practice mode goes straight back to the start of the same maze
+            if data.settings.instant_restart {
+                data.reset_run();
+                return SceneSwitch::Replace(Box::new(self.restarted()));
+            }
+            return SceneSwitch::FadeReplace(Box::new(GameOverScene));
+        }
+
+        // an altar makes its offer once, whether or not the player takes it
+        if self.grid[self.players[0].y][self.players[0].x] == CellType::Altar {
+            self.grid[self.players[0].y][self.players[0].x] = CellType::Path;
+            return SceneSwitch::Push(Box::new(AltarScene::new(Trade::random_offers(&mut self.rng))));
+        }
+
+        // a cleared side maze leaves its warp a plain path, one that was left unfinished can be tried again
+        if data.side_maze_cleared {
+            data.side_maze_cleared = false;
+            if let Some((x, y)) = self.open_warp.take() {
+                self.grid[y][x] = CellType::Path;
+            }
+        }
+        if self.entering_warp {
+            self.entering_warp = false;
+            let (x, y) = (self.players[0].x, self.players[0].y);
+            self.open_warp = Some((x, y));
+            let seed = match self.source {
+                MazeSource::Generated { seed, .. } => seed.wrapping_add((y * self.map.grid_w + x) as u64),
+                _ => self.rng.random(),
+            };
+            return SceneSwitch::FadePush(Box::new(MazeScene::side_maze(seed)));
+        }
+
+        // a vendor packs up after one visit, whether or not the player buys anything
+        if self.grid[self.players[0].y][self.players[0].x] == CellType::Vendor {
+            self.grid[self.players[0].y][self.players[0].x] = CellType::Path;
+            let stock = ShopItem::random_stock(data.level, &mut self.rng);
+            return SceneSwitch::Push(Box::new(ShopModal::new(stock)));
+        }
+
+        self.advance_goal();
+
+        // Check if player has reached the goal
+        if self.level_complete(data.settings.co_op) {
+            if self.side_maze {
+                data.points += SIDE_MAZE_REWARD;
+                data.side_maze_cleared = true;
+                return SceneSwitch::Pop;
+            }
+            // Add points for completing the maze
+            data.add_points(self.compute_level_score(data));
+            data.difficulty.adjust_difficulty(self.run_stats(false));
+            // in a time trial the next maze starts straight away
+            if data.time_trial.is_some() {
+                data.mazes_cleared += 1;
+                data.advance_level();
+                return SceneSwitch::Replace(Box::new(generated_level(data)));
+            }
+            let win = WinScene::new(!self.backtracked)
+                .with_objectives(self.level_key(), self.objectives())
+                .with_stealth(self.enemies_alerted);
+            return SceneSwitch::FadeReplace(Box::new(win));
+        }
+        
+        SceneSwitch::None
+    }
+
+    // how far the player is drawn from the centre of its cell. Only the wall bump moves it off centre.
+    fn player_render_offset(&self) -> Vector2 {
+        // sinking into quicksand, straight down the screen whichever way the view is turned
+        if let Some(time_left) = self.quicksand_release_timer {
+            let progress = (1.0 - time_left / QUICKSAND_HOLD_TIME).clamp(0.0, 1.0);
+            let down = Direction::South.rotated_clockwise(4 - self.view_turns());
+            return down.to_vector() * progress * self.tile_size as f32 * 0.3;
+        }
+        match self.bump_dir {
+            Some(dir) if self.bump_timer > 0.0 => {
+                let progress = 1.0 - self.bump_timer / BUMP_DURATION;
+                let distance = (progress * std::f32::consts::PI).sin() * self.tile_size as f32 * 0.2;
+                dir.to_vector() * distance
+            }
+            _ => Vector2::zero(),
+        }
+    }
+
+    /// Show the tooltip of a special cell next to the player, if there is one. The player has to be
+    /// beside the cell, not standing on it. Replaces any tooltip already showing.
+    pub fn show_tooltip(&mut self) {
+        let (px, py) = (self.players[0].x, self.players[0].y);
+        let found = Direction::ALL.iter().find_map(|dir| {
+            let (x, y) = self.neighbour(px, py, *dir)?;
+            self.grid[y][x].description().map(|text| (x, y, text))
+        });
+
+        if let Some((x, y, (name, description))) = found {
+            self.tooltip = Some(Tooltip {
+                message: format!("{}: {}", name, description),
+                x: x as i32 * self.tile_size,
+                y: y as i32 * self.tile_size,
+                remaining: TOOLTIP_DURATION,
+            });
+        }
+    }
+
+    // Turn a mine or sound trap next to the player into plain path for one of their coins. Without a
+    // coin nothing changes. Either way the tooltip over the trap says how it went.
+    fn disarm_trap(&mut self, data: &mut GameData) {
+        let (px, py) = (self.players[0].x, self.players[0].y);
+        let found = Direction::ALL.iter().find_map(|dir| {
+            let (x, y) = self.neighbour(px, py, *dir)?;
+            matches!(self.grid[y][x], CellType::Mine | CellType::SoundTrap).then_some((x, y))
+        });
+        let Some((x, y)) = found else { return };
+
+        let name = self.grid[y][x].description().map_or("Trap", |(name, _)| name);
+        let message = if data.coins == 0 {
+            format!("{}: need a coin to disarm it.", name)
+        } else {
+            data.coins -= 1;
+            self.grid[y][x] = CellType::Path;
+            format!("{} disarmed for a coin.", name)
+        };
+        self.tooltip = Some(Tooltip {
+            message,
+            x: x as i32 * self.tile_size,
+            y: y as i32 * self.tile_size,
+            remaining: TOOLTIP_DURATION,
+        });
+    }
+
+    // One HUD heart per `HEALTH_PER_HEART` of max health: full ones red, a partly used one pink
+    // and lost ones grey.
+    fn draw_hearts(&self, d: &mut impl RaylibDraw, data: &GameData) {
+        let hearts = (data.max_health + HEALTH_PER_HEART - 1) / HEALTH_PER_HEART;
+        for i in 0..hearts {
+            let heart_health = data.health - i * HEALTH_PER_HEART;
+            let color = if heart_health >= HEALTH_PER_HEART {
+                Color::RED
+            } else if heart_health > 0 {
+                Color::PINK
+            } else {
+                Color::GRAY
+            };
+            let center = Vector2::new(172.0 + i as f32 * 26.0, (data.screen_height - 15) as f32);
+            draw_heart(d, center, 20.0, color);
+        }
+    }
+
+    // Draw the tooltip in screen space above its cell, or below it if there is no room above,
+    // kept inside the screen.
+    fn draw_tooltip(&self, d: &mut RaylibDrawHandle, data: &GameData) {
+        let tooltip = match &self.tooltip {
+            Some(t) => t,
+            None => return,
+        };
+        let font_size = 20;
+        let width = d.measure_text(&tooltip.message, font_size) + 20;
+        let height = font_size + 12;
+
+        // the cell's centre on screen, through the (possibly turned) camera
+        let half_cell = self.tile_size as f32 / 2.0;
+        let center = d.get_world_to_screen2D(
+            Vector2::new(tooltip.x as f32 + half_cell, tooltip.y as f32 + half_cell),
+            self.camera,
+        );
+        let reach = (half_cell * self.camera.zoom) as i32 + 6;
+
+        let mut y = center.y as i32 - reach - height;
+        if y < 0 {
+            y = center.y as i32 + reach;
+        }
+        let y = y.clamp(0, (data.screen_height - height).max(0));
+        let x = (center.x as i32 - width / 2).clamp(0, (data.screen_width - width).max(0));
+
+        d.draw_rectangle(x, y, width, height, Color::BLACK.alpha(0.75));
+        d.draw_text(&tooltip.message, x + 10, y + 6, font_size, Color::WHITE);
+    }
+
+    // Blow up the mine at (x, y): hurt the player and any enemy within the blast, open up the walls
+    // around it and set off every other mine caught in the blast. The outer wall of the maze survives.
+    fn detonate(&mut self, x: usize, y: usize, data: &mut GameData) {
+        let mut pending = vec![(x, y)];
+        let mut player_hit = false;
+
+        while let Some((mx, my)) = pending.pop() {
+            if self.grid[my][mx] != CellType::Mine {
+                continue;
+            }
+            self.grid[my][mx] = CellType::Path;
+            let center = self.cell_center(mx, my);
+            self.particles.extend(burst(center, 40, 220.0, Color::RED, &mut self.effects_rng));
+            self.particles.extend(burst(center, 40, 160.0, Color::ORANGE, &mut self.effects_rng));
+
+            let in_blast = |cx: usize, cy: usize| cx.abs_diff(mx) + cy.abs_diff(my) <= MINE_BLAST_RADIUS;
+            let ys = my.saturating_sub(MINE_BLAST_RADIUS)..=(my + MINE_BLAST_RADIUS).min(self.map.grid_h - 1);
+            for cy in ys {
+                let xs = mx.saturating_sub(MINE_BLAST_RADIUS)..=(mx + MINE_BLAST_RADIUS).min(self.map.grid_w - 1);
+                for cx in xs {
+                    if !in_blast(cx, cy) {
+                        continue;
+                    }
+                    let border = cx == 0 || cy == 0 || cx == self.map.grid_w - 1 || cy == self.map.grid_h - 1;
+                    match self.grid[cy][cx] {
+                        CellType::Wall | CellType::Illusion if !border => self.grid[cy][cx] = CellType::Path,
+                        CellType::Mine => pending.push((cx, cy)),
+                        _ => {}
+                    }
+                }
+            }
+
+            player_hit |= in_blast(self.players[0].x, self.players[0].y);
+            for enemy in self.enemies.iter_mut().filter(|e| in_blast(e.x, e.y)) {
+                enemy.health -= self.mine_damage;
+            }
+        }
+
+        // however many mines go off, the player is only hurt once
+        if player_hit {
+            self.narrator.say("The ground erupts beneath you!");
+            self.damage_player(self.mine_damage, data);
+        }
+        let registry = &mut self.registry;
+        self.enemies.retain(|e| {
+            if e.health <= 0 {
+                registry.destroy(e.id);
+            }
+            e.health > 0
+        });
+        self.reindex_entities();
+    }
+
+    // Quicksand: a tan cell speckled with darker grains.
+    fn draw_quicksand(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let screen_x = x as i32 * self.tile_size;
+        let screen_y = y as i32 * self.tile_size;
+        d.draw_rectangle(screen_x, screen_y, self.tile_size, self.tile_size, Color::BEIGE);
+        let step = self.tile_size / 4;
+        for row in 0..3 {
+            for col in 0..3 {
+                // every other row is shifted so the grains do not line up
+                let dot_x = screen_x + step * (col + 1) - (row % 2) * step / 2;
+                let dot_y = screen_y + step * (row + 1);
+                d.draw_circle(dot_x, dot_y, 1.5, Color::BROWN);
+            }
+        }
+    }
+
+    // A slope: pale grey with dark stripes slanting across it, and a band along its downhill edge.
+    fn draw_slope(&self, d: &mut impl RaylibDraw, x: usize, y: usize, direction: Direction) {
+        let screen_x = x as f32 * self.tile_size as f32;
+        let screen_y = y as f32 * self.tile_size as f32;
+        let size = self.tile_size as f32;
+        d.draw_rectangle_v(Vector2::new(screen_x, screen_y), Vector2::new(size, size), Color::LIGHTGRAY);
+        for i in 1..4 {
+            let offset = size * i as f32 / 4.0;
+            d.draw_line_v(
+                Vector2::new(screen_x + offset, screen_y),
+                Vector2::new(screen_x, screen_y + offset),
+                Color::GRAY,
+            );
+            d.draw_line_v(
+                Vector2::new(screen_x + size, screen_y + offset),
+                Vector2::new(screen_x + offset, screen_y + size),
+                Color::GRAY,
+            );
+        }
+        let band = size / 6.0;
+        let (position, band_size) = match direction {
+            Direction::North => (Vector2::new(screen_x, screen_y), Vector2::new(size, band)),
+            Direction::South => (Vector2::new(screen_x, screen_y + size - band), Vector2::new(size, band)),
+            Direction::East => (Vector2::new(screen_x + size - band, screen_y), Vector2::new(band, size)),
+            Direction::West => (Vector2::new(screen_x, screen_y), Vector2::new(band, size)),
+        };
+        d.draw_rectangle_v(position, band_size, Color::DARKGRAY);
+    }
+
+    // An altar: a stone block with a violet flame flickering on top.
+    fn draw_altar(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        d.draw_rectangle_v(
+            Vector2::new(center.x - size * 0.3, center.y),
+            Vector2::new(size * 0.6, size * 0.3),
+            Color::GRAY,
+        );
+        let flicker = (self.elapsed * 8.0 + x as f32).sin() * size * 0.04;
+        d.draw_circle_v(Vector2::new(center.x, center.y - size * 0.12 + flicker), size * 0.13, Color::VIOLET);
+    }
+
+    // A vendor: a wooden counter with a gold coin on it.
+    fn draw_vendor(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        d.draw_rectangle_v(
+            Vector2::new(center.x - size * 0.35, center.y + size * 0.05),
+            Vector2::new(size * 0.7, size * 0.25),
+            Color::BROWN,
+        );
+        d.draw_circle_v(Vector2::new(center.x, center.y - size * 0.1), size * 0.14, Color::GOLD);
+        d.draw_circle_lines(center.x as i32, (center.y - size * 0.1) as i32, size * 0.14, Color::ORANGE);
+    }
+
+    // A map fragment: a torn scrap of parchment with a route scribbled across it.
+    fn draw_map_fragment(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        d.draw_rectangle_v(
+            Vector2::new(center.x - size * 0.3, center.y - size * 0.25),
+            Vector2::new(size * 0.6, size * 0.5),
+            Color::BEIGE,
+        );
+        d.draw_line_v(
+            Vector2::new(center.x - size * 0.2, center.y + size * 0.1),
+            Vector2::new(center.x, center.y - size * 0.1),
+            Color::BROWN,
+        );
+        d.draw_line_v(
+            Vector2::new(center.x, center.y - size * 0.1),
+            Vector2::new(center.x + size * 0.2, center.y + size * 0.05),
+            Color::BROWN,
+        );
+    }
+
+    // A wind cell: pale blue with three chevrons pointing downwind, one brighter than the others.
+    // The bright one moves on a chevron every `WIND_ANIMATION_STEP` seconds.
+    fn draw_wind(&self, d: &mut impl RaylibDraw, x: usize, y: usize, direction: Direction) {
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        d.draw_rectangle(x as i32 * self.tile_size, y as i32 * self.tile_size, self.tile_size, self.tile_size, Color::SKYBLUE.alpha(0.3));
+
+        let forward = direction.to_vector();
+        let side = Vector2::new(-forward.y, forward.x);
+        let lit = (self.elapsed / WIND_ANIMATION_STEP) as i32 % 3;
+        for i in 0..3 {
+            let tip = center + forward * (size * (i as f32 - 1.0) * 0.25 + size * 0.1);
+            let back = tip - forward * (size * 0.15);
+            let color = if i == lit { Color::WHITE } else { Color::SKYBLUE };
+            d.draw_line_v(back + side * (size * 0.15), tip, color);
+            d.draw_line_v(back - side * (size * 0.15), tip, color);
+        }
+    }
+
+    // A transformer: a ring around the player as it would turn them, in small.
+    fn draw_transformer(&self, d: &mut impl RaylibDraw, x: usize, y: usize, mode: MoveMode) {
+        let center = self.cell_center(x, y);
+        let (color, scale) = mode_look(mode);
+        let size = self.tile_size as f32;
+        d.draw_circle_lines(center.x as i32, center.y as i32, size * 0.4, Color::GOLD);
+        d.draw_circle_v(center, size * 0.15 * scale, color);
+    }
+
+    // An arrow tile: a white triangle pointing its way, on dark grey.
+    fn draw_arrow_tile(&self, d: &mut impl RaylibDraw, x: usize, y: usize, direction: Direction) {
+        d.draw_rectangle(x as i32 * self.tile_size, y as i32 * self.tile_size, self.tile_size, self.tile_size, Color::DARKGRAY);
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        let forward = direction.to_vector();
+        let side = Vector2::new(-forward.y, forward.x);
+        let tip = center + forward * (size * 0.3);
+        let back = center - forward * (size * 0.25);
+        // raylib only fills triangles whose corners go anticlockwise on screen
+        d.draw_triangle(tip, back - side * (size * 0.25), back + side * (size * 0.25), Color::RAYWHITE);
+    }
+
+    // Lava: orange that flickers between shades, each cell a little out of step with its neighbours.
+    fn draw_lava(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let flicker = ((self.elapsed * 5.0 + (x * 3 + y * 7) as f32).sin() + 1.0) / 2.0;
+        let color = Color::new(230, 80 + (flicker * 80.0) as u8, 20, 255);
+        d.draw_rectangle(x as i32 * self.tile_size, y as i32 * self.tile_size, self.tile_size, self.tile_size, color);
+        let center = self.cell_center(x, y);
+        d.draw_circle_v(center, self.tile_size as f32 * (0.1 + flicker * 0.08), Color::YELLOW);
+    }
+
+    // A time stop: a clock face with its hands at ten to two.
+    fn draw_time_stop(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let r = self.tile_size as f32 * 0.3;
+        d.draw_circle_v(center, r, Color::RAYWHITE);
+        d.draw_circle_lines(center.x as i32, center.y as i32, r, Color::DARKBLUE);
+        d.draw_line_v(center, center + Vector2::new(-0.5, -0.6) * r, Color::DARKBLUE);
+        d.draw_line_v(center, center + Vector2::new(0.6, -0.4) * r, Color::DARKBLUE);
+    }
+
+    // A sound trap: rings spreading out from a dot, dimmed while it resets.
+    fn draw_sound_trap(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let color = if self.sound_trap_cooldowns.contains_key(&(x, y)) { Color::GRAY } else { Color::MAROON };
+        d.draw_circle_v(center, self.tile_size as f32 * 0.08, color);
+        for ring in 1..=3 {
+            d.draw_circle_lines(center.x as i32, center.y as i32, self.tile_size as f32 * 0.13 * ring as f32, color);
+        }
+    }
+
+    // count an enemy noticing the player, for the stealth rating
+    fn alert(&mut self) {
+        self.enemies_alerted += 1;
+        self.perfect_stealth = false;
+    }
+
+    // Let the player's noise die down, and fire every ready sound trap close enough to hear it: one
+    // projectile each way along the grid.
+    fn update_sound_traps(&mut self, dt: f32) {
+        self.sound_trap_cooldowns.retain(|_, time_left| {
+            *time_left -= dt;
+            *time_left > 0.0
+        });
+        let noise = self.player_noise_level;
+        self.player_noise_level = (noise - NOISE_FADE_PER_SECOND * dt).max(0.0);
+        if noise <= 0.0 || self.time_stop_remaining > 0.0 {
+            return;
+        }
+
+        let reach = noise.ceil() as usize;
+        let (px, py) = (self.players[0].x, self.players[0].y);
+        let mut firing = Vec::new();
+        for y in py.saturating_sub(reach)..=(py + reach).min(self.grid.len().saturating_sub(1)) {
+            for x in px.saturating_sub(reach)..=(px + reach).min(self.grid[y].len().saturating_sub(1)) {
+                let heard = x.abs_diff(px) + y.abs_diff(py) <= reach;
+                if heard && self.grid[y][x] == CellType::SoundTrap && !self.sound_trap_cooldowns.contains_key(&(x, y)) {
+                    firing.push((x, y));
+                }
+            }
+        }
+        for (x, y) in firing {
+            self.sound_trap_cooldowns.insert((x, y), SOUND_TRAP_RESET);
+            let center = self.cell_center(x, y);
+            for direction in Direction::ALL {
+                self.spawn_projectile(Projectile::new(center.x, center.y, direction, 200.0, 10));
+            }
+        }
+    }
+
+    // A mine icon: a dark ball with a short fuse sticking out of the top and a spark on its end.
+    fn draw_mine(&self, d: &mut impl RaylibDraw, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let r = self.tile_size as f32 * 0.25;
+        d.draw_circle_v(center, r, Color::DARKGRAY);
+        let fuse_end = Vector2::new(center.x + r * 0.9, center.y - r * 1.5);
+        d.draw_line(center.x as i32, (center.y - r) as i32, fuse_end.x as i32, fuse_end.y as i32, Color::BROWN);
+        d.draw_circle_v(fuse_end, 2.0, Color::ORANGE);
+    }
+
+    // Age every wall the player can see and has not walked through (illusions count as walls).
+    fn update_wall_age(&mut self) {
+        let radius = self.visibility_radius.min(WALL_AGE_RADIUS);
+        let reach = radius as usize;
+        let ys = self.players[0].y.saturating_sub(reach)..=(self.players[0].y + reach).min(self.map.grid_h.saturating_sub(1));
+        for y in ys {
+            let xs = self.players[0].x.saturating_sub(reach)..=(self.players[0].x + reach).min(self.map.grid_w.saturating_sub(1));
+            for x in xs {
+                let dx = x as f32 - self.players[0].x as f32;
+                let dy = y as f32 - self.players[0].y as f32;
+                let is_wall = matches!(self.grid[y][x], CellType::Wall | CellType::Illusion);
+                if is_wall && (dx * dx + dy * dy).sqrt() <= radius && !self.visited_cells.contains(&(x, y)) {
+                    self.wall_age[y][x] = self.wall_age[y][x].saturating_add(1);
+                }
+            }
+        }
+    }
+
+    // Flood the next row up every few seconds. Anyone standing in water when it rises takes
+    // drowning damage. Only the exit's floor floods.
+    fn update_water(&mut self, dt: f32, data: &mut GameData) {
+        if !self.rising_water || !self.on_exit_floor() {
+            return;
+        }
+        self.water_rise_timer -= dt;
+        if self.water_rise_timer > 0.0 {
+            return;
+        }
+        self.water_rise_timer = WATER_RISE_INTERVAL;
+
+        // the top row is wall, so the water stops just below it
+        if self.water_level_row > 0 {
+            for cell in self.grid[self.water_level_row].iter_mut() {
+                if *cell == CellType::Path {
+                    *cell = CellType::Water;
+                }
+            }
+            self.water_level_row -= 1;
+        }
+        if self.grid[self.players[0].y][self.players[0].x] == CellType::Water {
+            self.damage_player(DROWNING_DAMAGE, data);
+        }
+    }
+
+    // remember the player's cell, noting a backtrack if they have been here before
+    fn mark_visited(&mut self) {
+        if !self.visited_cells.insert((self.players[0].x, self.players[0].y)) {
+            self.backtracked = true;
+            self.revisits += 1;
+        }
+        let cell = (self.players[0].x, self.players[0].y);
+        match self.breadcrumbs.iter().position(|&crumb| crumb == cell) {
+            Some(i) => self.breadcrumbs.truncate(i + 1),
+            None => self.breadcrumbs.push(cell),
+        }
+        if let Some(message) = self.triggered_messages.remove(&cell) {
+            self.narrator.say(&message);
+        }
+        self.explore_around();
+    }
+
+    // put the player's cell and the eight around it on the auto-map
+    fn explore_around(&mut self) {
+        for y in self.players[0].y.saturating_sub(1)..=self.players[0].y + 1 {
+            for x in self.players[0].x.saturating_sub(1)..=self.players[0].x + 1 {
+                if let Some(cell) = self.explored.get_mut(y).and_then(|row| row.get_mut(x)) {
+                    *cell = true;
+                }
+            }
+        }
+    }
+
+    // The auto-map: the explored cells of the player's floor, small, in a panel in the top right
+    // corner. A floor too big for the panel scrolls to keep the player in view.
+    fn draw_auto_map(&self, d: &mut impl RaylibDraw, data: &GameData) {
+        let left = data.screen_width - AUTO_MAP_SIZE - 10;
+        let top = 60;
+        d.draw_rectangle(left, top, AUTO_MAP_SIZE, AUTO_MAP_SIZE, Color::BLACK.alpha(0.6));
+        // the first cell shown on each axis, so the player stays as near the middle as the edges allow
+        let shown = (AUTO_MAP_SIZE / AUTO_MAP_CELL) as usize;
+        let first = |player: usize, cells: usize| player.saturating_sub(shown / 2).min(cells.saturating_sub(shown));
+        let first_x = first(self.players[0].x, self.map.grid_w);
+        let first_y = first(self.players[0].y, self.map.grid_h);
+        for (y, row) in self.explored.iter().enumerate().skip(first_y).take(shown) {
+            for (x, explored) in row.iter().enumerate().skip(first_x).take(shown) {
+                if !explored {
+                    continue;
+                }
+                let color = match self.grid[y][x] {
+                    _ if (x, y) == (self.players[0].x, self.players[0].y) => Color::RED,
+                    CellType::Exit => Color::GREEN,
+                    CellType::Ladder(_) => Color::GOLD,
+                    cell if cell.is_walkable() => Color::LIGHTGRAY,
+                    _ => Color::DARKGRAY,
+                };
+                let screen_x = left + (x - first_x) as i32 * AUTO_MAP_CELL;
+                let screen_y = top + (y - first_y) as i32 * AUTO_MAP_CELL;
+                d.draw_rectangle(screen_x, screen_y, AUTO_MAP_CELL, AUTO_MAP_CELL, color);
+            }
+        }
+        d.draw_rectangle_lines(left, top, AUTO_MAP_SIZE, AUTO_MAP_SIZE, Color::GRAY);
+    }
+
+    // count a visit of the player's current cell
+    fn record_visit(&mut self) {
+        if let Some(count) = self.visit_counts.get_mut(self.players[0].y).and_then(|row| row.get_mut(self.players[0].x)) {
+            *count += 1;
+        }
+        if let Some(time) = self.last_visited_time.get_mut(self.players[0].y).and_then(|row| row.get_mut(self.players[0].x)) {
+            *time = self.elapsed;
+        }
+    }
+
+    // forget the trail, e.g. after changing floor where it would point at the wrong cells
+    fn clear_trail(&mut self) {
+        self.last_visited_time = vec![vec![f32::NEG_INFINITY; self.map.grid_w]; self.map.grid_h];
+    }
+
+    // Tint the cells the player walked through recently. The tint fades out over `fade` seconds,
+    // so cells that were never visited (or not for a while) are left alone.
+    fn draw_trail(&self, d: &mut impl RaylibDraw, fade: f32) {
+        if fade <= 0.0 {
+            return;
+        }
+        for (y, row) in self.last_visited_time.iter().enumerate() {
+            for (x, visited) in row.iter().enumerate() {
+                let age = self.elapsed - visited;
+                if age >= fade {
+                    continue;
+                }
+                let alpha = 0.5 * (1.0 - age / fade);
+                d.draw_rectangle(
+                    x as i32 * self.tile_size,
+                    y as i32 * self.tile_size,
+                    self.tile_size,
+                    self.tile_size,
+                    Color::SKYBLUE.alpha(alpha),
+                );
+            }
+        }
+    }
+
+    /// Where the run has got to, for [`MazeScene::restore`].
+    pub fn state(&self) -> MazeState {
+        let mut messages: Vec<MapMessage> = self.triggered_messages.iter().map(|(&(x, y), text)| MapMessage { x, y, text: text.clone() }).collect();
+        messages.sort_by_key(|m| (m.y, m.x));
+        MazeState {
+            level: self.level_key(),
+            on_lower_floor: self.on_lower_floor,
+            player: (self.players[0].x, self.players[0].y),
+            elapsed: self.elapsed,
+            messages,
+        }
+    }
+
+    /// Pick a saved run up again: the player back on their cell, the clock where it was, and only
+    /// the messages not yet shown still to come. A state from another level or floor, or with the
+    /// player where they cannot stand, is left alone. Whether it was restored.
+    pub fn restore(&mut self, state: MazeState) -> bool {
+        let (x, y) = state.player;
+        if state.level != self.level_key() || state.on_lower_floor != self.on_lower_floor || !self.is_valid_move(x, y) {
+            return false;
+        }
+        self.triggered_messages = state.messages.into_iter().map(|m| ((m.x, m.y), m.text)).collect();
+        self.elapsed = state.elapsed;
+        self.players[0].x = x;
+        self.players[0].y = y;
+        self.arrive(self.facing);
+        true
+    }
+
+    /// What names this level in files and the save slot: the map's file name, e.g. `mapTest`, or
+    /// the seed of a generated maze, e.g. `seed42`.
+    pub fn level_key(&self) -> String {
+        match &self.source {
+            MazeSource::File(map_path) | MazeSource::Binary(map_path) => Path::new(map_path)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("maze")
+                .to_string(),
+            MazeSource::Generated { seed, .. } => format!("seed{}", seed),
+        }
+    }
+
+    /// Write the visit counts as a CSV file in [`HEATMAP_DIR`], one line per maze row, and return
+    /// where it went. The file is named after the level, e.g. `heatmaps/heatmap_mapTest.csv`.
+    pub fn export_visit_heatmap(&self) -> std::io::Result<String> {
+        std::fs::create_dir_all(HEATMAP_DIR)?;
+        let path = format!("{}/heatmap_{}.csv", HEATMAP_DIR, self.level_key());
+
+        let mut file = File::create(&path)?;
+        for row in &self.visit_counts {
+            let line: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+            writeln!(file, "{}", line.join(","))?;
+        }
+        Ok(path)
+    }
+
+    /// Hurt the player. Every source of damage should go through here so an active shield
+    /// can absorb the hit instead.
+    pub fn damage_player(&mut self, amount: i32, data: &mut GameData) {
+        if self.shield_active {
+            self.shield_active = false;
+            return;
+        }
+        data.take_damage(amount);
+    }
+
+    // pick up any item, heart or treasure lying on the player's cell, with a burst of sparks for
+    // each unless reduce motion is on
+    fn collect_items(&mut self, data: &mut GameData) {
+        // a ghost's hands go straight through everything
+        if self.move_mode == MoveMode::Ghost {
+            return;
+        }
+        let (px, py) = (self.players[0].x, self.players[0].y);
+        let mut picked = Vec::new();
+        if self.grid[py][px] == CellType::Heart {
+            data.heal(HEALTH_PER_HEART);
+            self.grid[py][px] = CellType::Path;
+            picked.push(Color::RED);
+        }
+        // another time stop stays where it is until the current one runs out
+        if self.grid[py][px] == CellType::TimeStop && self.time_stop_remaining <= 0.0 {
+            self.grid[py][px] = CellType::Path;
+            self.time_stop_remaining = TIME_STOP_DURATION;
+            picked.push(Color::LIGHTGRAY);
+        }
+        if self.grid[py][px] == CellType::MapFragment {
+            self.narrator.say("A scrap of map, still warm to the touch.");
+            self.grid[py][px] = CellType::Path;
+            self.reveal(RevealedRegion {
+                x: px.saturating_sub(MAP_FRAGMENT_REACH),
+                y: py.saturating_sub(MAP_FRAGMENT_REACH),
+                width: MAP_FRAGMENT_REACH * 2 + 1,
+                height: MAP_FRAGMENT_REACH * 2 + 1,
+            });
+            picked.push(Color::BEIGE);
+        }
+
+        let registry = &mut self.registry;
+        self.map.entities.retain(|e| {
+            if e.x != px || e.y != py {
+                return true;
+            }
+            if e.kind == "treasure" {
+                data.add_points(TREASURE_POINTS);
+                data.coins += 1;
+                picked.push(Color::GOLD);
+                registry.destroy(e.id);
+                return false;
+            }
+            match Item::from_map_kind(&e.kind) {
+                Some(item) => {
+                    data.inventory.push(item);
+                    picked.push(Color::SKYBLUE);
+                    registry.destroy(e.id);
+                    false
+                }
+                None => true,
+            }
+        });
+        if !picked.is_empty() {
+            self.reindex_entities();
+        }
+
+        if !data.settings.reduce_motion {
+            let center = self.cell_center(px, py);
+            for color in picked {
+                self.particles.extend(burst(center, 16, 90.0, color, &mut self.effects_rng));
+            }
+        }
+    }
+
+    // Move every projectile, removing the ones that hit a wall, leave the map or run out of time.
+    // A projectile only hurts the player when it actually reaches the player circle, so standing
+    // at the edge of a cell it passes next to is enough to dodge it.
+    fn update_projectiles(&mut self, dt: f32, data: &mut GameData) {
+        let player = self.player_center();
+        let hit_distance = self.tile_size as f32 * 0.4 + Projectile::RADIUS;
+
+        let mut i = 0;
+        while i < self.projectiles.len() {
+            self.projectiles[i].advance(dt);
+            let projectile = &self.projectiles[i];
+
+            let (cx, cy) = projectile.cell(self.tile_size);
+            let blocked = cx < 0 || cy < 0 || !self.is_open(cx as usize, cy as usize);
+
+            if projectile.position().distance_to(player) <= hit_distance {
+                let damage = projectile.damage;
+                let removed = self.projectiles.swap_remove(i);
+                self.registry.destroy(removed.id);
+                self.narrator.say("You stumble and curse.");
+                self.damage_player(damage, data);
+            } else if blocked || projectile.lifetime <= 0.0 {
+                let removed = self.projectiles.swap_remove(i);
+                self.registry.destroy(removed.id);
+            } else {
+                i += 1;
+            }
+        }
+        self.registry.reindex(self.projectiles.iter().map(|p| p.id), EntityKind::Projectile);
+    }
+
+
+    
+    
+    
+    // how much faster than normal enemies and projectiles are moving
+    fn speed_factor(&self) -> f32 {
+        if self.speed_boost_timer > 0.0 { 2.0 } else { 1.0 }
+    }
+
+    // Step every enemy around the maze, let shooters fire along their row or column and
+    // hurt the player when an enemy shares their cell. Frozen enemies do nothing at all.
+    fn update_enemies(&mut self, dt: f32, data: &mut GameData) {
+        self.contact_cooldown = (self.contact_cooldown - dt).max(0.0);
+        if self.freeze_timer > 0.0 {
+            return;
+        }
+
+        let mut rng = self.fork_rng();
+        for i in 0..self.enemies.len() {
+            let kind = self.enemies[i].kind;
+            let (x, y) = (self.enemies[i].x, self.enemies[i].y);
+
+            // a minotaur heads wherever its state says, taking the shortest way there. It notices the
+            // player by seeing them or by hearing them as the sound traps do.
+            if let Some(mut minotaur) = self.enemies[i].minotaur.take() {
+                let player = (self.players[0].x, self.players[0].y);
+                let heard = self.player_noise_level > 0.0
+                    && x.abs_diff(player.0) + y.abs_diff(player.1) <= self.player_noise_level.ceil() as usize;
+                let noticed = heard || line_of_sight(&self.grid, (x, y), player);
+                minotaur.observe((x, y), noticed.then_some(player), dt);
+                let chasing = minotaur.state == MinotaurState::Chase;
+                if chasing && !self.enemies[i].alerted {
+                    self.alert();
+                }
+                self.enemies[i].alerted = chasing;
+                self.enemies[i].move_timer += dt;
+                if self.enemies[i].move_timer >= minotaur.state.move_interval() {
+                    self.enemies[i].move_timer = 0.0;
+                    let next = minotaur
+                        .target()
+                        .and_then(|target| path_through(&self.grid, (x, y), target, |cell| cell.is_enemy_walkable()))
+                        .and_then(|path| path.get(1).copied());
+                    if let Some((nx, ny)) = next {
+                        self.enemies[i].x = nx;
+                        self.enemies[i].y = ny;
+                    }
+                }
+                self.enemies[i].minotaur = Some(minotaur);
+                continue;
+            }
+
+            self.enemies[i].move_timer += dt;
+            if self.enemies[i].move_timer >= kind.move_interval() {
+                self.enemies[i].move_timer = 0.0;
+                let options: Vec<(usize, usize)> = Direction::ALL
+                    .iter()
+                    .filter_map(|dir| self.neighbour(x, y, *dir))
+                    .filter(|(nx, ny)| self.grid[*ny][*nx].is_enemy_walkable())
+                    .collect();
+                // interceptors wander too when no step gets them any closer
+                let intercept = match kind {
+                    EnemyKind::Interceptor => intercept_step(&self.grid, &self.exit_distances, (x, y), (self.players[0].x, self.players[0].y)),
+                    _ => None,
+                };
+                if let Some((nx, ny)) = intercept.or_else(|| options.choose(&mut rng).copied()) {
+                    self.enemies[i].x = nx;
+                    self.enemies[i].y = ny;
+                }
+            }
+
+            if kind == EnemyKind::Shooter {
+                self.enemies[i].fire_timer += dt;
+                if self.enemies[i].fire_timer >= Enemy::FIRE_INTERVAL {
+                    let (ex, ey) = (self.enemies[i].x, self.enemies[i].y);
+                    let aim = if ex == self.players[0].x && ey != self.players[0].y {
+                        Some(if self.players[0].y < ey { Direction::North } else { Direction::South })
+                    } else if ey == self.players[0].y && ex != self.players[0].x {
+                        Some(if self.players[0].x < ex { Direction::West } else { Direction::East })
+                    } else {
+                        None
+                    };
+                    // only reset the timer on a shot, so a shooter fires as soon as the player lines up
+                    if let Some(direction) = aim {
+                        if !self.enemies[i].alerted {
+                            self.enemies[i].alerted = true;
+                            self.alert();
+                        }
+                        self.enemies[i].fire_timer = 0.0;
+                        let center = self.cell_center(ex, ey);
+                        let owner = Some(self.enemies[i].id);
+                        self.spawn_projectile(Projectile { owner, ..Projectile::new(center.x, center.y, direction, 200.0, 10) });
+                    }
+                }
+            }
+        }
+
+        if self.contact_cooldown <= 0.0 {
+            let touching = self.enemies.iter().find(|e| e.x == self.players[0].x && e.y == self.players[0].y);
+            if let Some(enemy) = touching {
+                let damage = enemy.kind.contact_damage();
+                self.contact_cooldown = CONTACT_COOLDOWN;
+                self.damage_player(damage, data);
+            }
+        }
+    }
+
+    // Roll for a random event once a minute and count down the effects of the ones already running.
+    fn update_events(&mut self, dt: f32) {
+        self.event_clock += dt;
+        if self.event_clock >= EVENT_ROLL_INTERVAL {
+            self.event_clock -= EVENT_ROLL_INTERVAL;
+            let roll: f32 = self.rng.random();
+            if let Some(event) = pick_event(&self.events, roll) {
+                self.trigger_event(event);
+            }
+        }
+
+        if self.lights_out_timer > 0.0 {
+            self.lights_out_timer -= dt;
+            if self.lights_out_timer <= 0.0 {
+                self.visibility_radius = FULL_VISIBILITY;
+            }
+        }
+        self.freeze_timer = (self.freeze_timer - dt).max(0.0);
+        self.speed_boost_timer = (self.speed_boost_timer - dt).max(0.0);
+
+        if let Some((_, time_left)) = &mut self.banner {
+            *time_left -= dt;
+            if *time_left <= 0.0 {
+                self.banner = None;
+            }
+        }
+    }
+
+    /// Apply a random event to the whole maze straight away and announce it.
+    pub fn trigger_event(&mut self, event: EventType) {
+        match event {
+            EventType::EnemyRushWave => {
+                let mut rng = self.fork_rng();
+                for _ in 0..RUSH_WAVE_SIZE {
+                    if let Some((x, y)) = self.random_spawn_cell(&mut rng) {
+                        self.spawn_enemy(Enemy::new(EnemyKind::Tank, x, y));
+                    }
+                }
+            }
+            EventType::TreasureDropAll => {
+                let positions: Vec<_> = self.enemies.iter().map(|e| (e.x, e.y)).collect();
+                for (x, y) in positions {
+                    self.spawn_collectible("treasure".to_string(), x, y);
+                }
+            }
+            EventType::LightsOut(duration) => {
+                self.visibility_radius = 0.0;
+                self.lights_out_timer = duration.as_secs_f32();
+            }
+            EventType::SpeedBoostAll => self.speed_boost_timer = SPEED_BOOST_DURATION,
+            EventType::FreezeEnemies(duration) => self.freeze_timer = duration.as_secs_f32(),
+        }
+        self.banner = Some((event.announcement(), BANNER_DURATION));
+    }
+
+    // A random open cell for an enemy to appear in: far enough from the player, outside the safe
+    // radius around the start, and with a way through to the player.
+    fn random_spawn_cell(&self, rng: &mut impl Rng) -> Option<(usize, usize)> {
+        let start = self.find_cell(CellType::Start);
+        let routes = dijkstra_from(&self.grid, [(self.players[0].x, self.players[0].y)], |cell| cell.is_enemy_walkable());
+        let mut cells = Vec::new();
+        for (y, row) in routes.iter().enumerate() {
+            for (x, route) in row.iter().enumerate() {
+                let distance = x.abs_diff(self.players[0].x) + y.abs_diff(self.players[0].y);
+                let near_start = start.is_some_and(|(sx, sy)| x.abs_diff(sx) + y.abs_diff(sy) <= self.spawn_safe_radius);
+                if distance >= RUSH_WAVE_MIN_DISTANCE && !near_start && *route != UNREACHABLE {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells.choose(rng).copied()
+    }
+
+    // centre of a cell in pixels
+    fn cell_center(&self, x: usize, y: usize) -> Vector2 {
+        Vector2::new(
+            (x as i32 * self.tile_size + self.tile_size / 2) as f32,
+            (y as i32 * self.tile_size + self.tile_size / 2) as f32,
+        )
+    }
+
+    // whether the player can move onto (x, y) as they are: a ghost goes through walls and gates, a
+    // tiny player under gates, and a giant needs all four of its cells open
+    fn is_valid_move(&self, x: usize, y: usize) -> bool {
+        let cell = self.grid.get(y).and_then(|row| row.get(x));
+        match self.move_mode {
+            MoveMode::Normal => self.is_open(x, y),
+            MoveMode::Ghost => cell.is_some_and(|c| !matches!(c, CellType::Lava | CellType::Pit)),
+            MoveMode::Tiny => self.is_open(x, y) || matches!(cell, Some(CellType::LinkedWall(_))),
+            MoveMode::Giant => (0..2).all(|dy| (0..2).all(|dx| self.is_open(x + dx, y + dy))),
+        }
+    }
+
+    // whether (x, y) is on the map and open to walk on
+    fn is_open(&self, x: usize, y: usize) -> bool {
+        if x >= self.map.grid_w || y >= self.map.grid_h {
+            return false;
+        }
+        self.grid[y][x].is_walkable()
+    }
+    fn draw_tile(&self, d: &mut impl RaylibDraw, tile_id: i32, x: usize, y: usize) {
+        let tileset = match &self.tileset {
+            Some(t) => t,
+            None => return, 
+        };
+        let cols = tileset.width() / self.tile_size;
+        let src = Rectangle {
+            x: ((tile_id % cols) * self.tile_size) as f32,
+            y: ((tile_id / cols) * self.tile_size) as f32,
+            width: self.tile_size as f32,
+            height: self.tile_size as f32,
+        };
+
+        let dst = Rectangle {
+            x: (x as i32 * self.tile_size) as f32,
+            y: (y as i32 * self.tile_size) as f32,
+            width: self.tile_size as f32,
+            height: self.tile_size as f32,
+        };
+
+        d.draw_texture_pro(tileset, 
+            src, 
+            dst, Vector2::zero(), 0.0, Color::WHITE);
+    }
+
+    // The current frame of the walk cycle centred on `center`: the idle frame while standing still,
+    // and the frames after it in turn while walking.
+    fn draw_player_sprite(&self, d: &mut impl RaylibDraw, sheet: &Texture2D, center: Vector2, tint: Color, scale: f32) {
+        let frame_size = sheet.height().max(1);
+        let walk_frames = (sheet.width() / frame_size - 1).max(0);
+        let frame = if walk_frames > 0 && self.idle_timer < WALK_IDLE_TIME { 1 + self.walk_frame % walk_frames } else { 0 };
+        // a negative width mirrors the frame
+        let flip = if self.facing == Direction::West { -1.0 } else { 1.0 };
+        let src = Rectangle {
+            x: (frame * frame_size) as f32,
+            y: 0.0,
+            width: frame_size as f32 * flip,
+            height: frame_size as f32,
+        };
+        let size = self.tile_size as f32 * 0.9 * scale;
+        let dst = Rectangle {
+            x: center.x - size / 2.0,
+            y: center.y - size / 2.0,
+            width: size,
+            height: size,
+        };
+        d.draw_texture_pro(sheet, src, dst, Vector2::zero(), 0.0, tint);
+    }
+
+    // A mushroom icon: a red cap with white spots on a pale stem, with its bounce distance above it.
+    fn draw_mushroom(&self, d: &mut impl RaylibDraw, distance: u8, x: usize, y: usize) {
+        let center = self.cell_center(x, y);
+        let size = self.tile_size as f32;
+        let (cx, cy) = (center.x as i32, center.y as i32);
+
+        d.draw_rectangle(cx - (size * 0.1) as i32, cy, (size * 0.2) as i32, (size * 0.3) as i32, Color::BEIGE);
+        d.draw_ellipse(cx, cy, size * 0.35, size * 0.2, Color::RED);
+        d.draw_circle(cx - (size * 0.15) as i32, cy - (size * 0.05) as i32, size * 0.05, Color::WHITE);
+        d.draw_circle(cx + (size * 0.12) as i32, cy - (size * 0.08) as i32, size * 0.04, Color::WHITE);
+        d.draw_text(&distance.to_string(), cx - 3, y as i32 * self.tile_size, 10, Color::BLACK);
+    }
+
+    // A ladder icon: two rails with alternating rungs. Ladders down are brown, ladders up green.
+    fn draw_ladder(&self, d: &mut impl RaylibDraw, direction: LadderDirection, x: usize, y: usize) {
+        let color = match direction {
+            LadderDirection::Down => Color::BROWN,
+            LadderDirection::Up => Color::DARKGREEN,
+        };
+        let left = x as i32 * self.tile_size + self.tile_size / 4;
+        let right = left + self.tile_size / 2;
+        let top = y as i32 * self.tile_size + 2;
+        let bottom = top + self.tile_size - 4;
+
+        d.draw_line(left, top, left, bottom, color);
+        d.draw_line(right, top, right, bottom, color);
+        let mut rung = top + self.tile_size / 6;
+        while rung < bottom {
+            d.draw_line(left, rung, right, rung, color);
+            rung += self.tile_size / 4;
+        }
+    }
+
+    // Draw the maze itself in world (pixel) coordinates. The HUD is drawn separately on top.
+    fn draw_world(&self, d: &mut impl RaylibDraw, data: &GameData) {
+        // Only map files have tiles, other mazes (and map files without their tileset or made of
+        // cells instead of tiles) draw their walls as plain blocks
+        if !matches!(self.source, MazeSource::File(_)) || self.tileset.is_none() || self.map.cells.is_some() {
+            for (y, row) in self.grid.iter().enumerate() {
+                for (x, cell) in row.iter().enumerate() {
+                    // illusions are drawn exactly like the walls they pretend to be
+                    // walls that have been in view a while wear towards a lighter stone
+                    if matches!(cell, CellType::Wall | CellType::Illusion) {
+                        let age = self.wall_age.get(y).and_then(|row| row.get(x)).copied().unwrap_or(0);
+                        d.draw_rectangle(
+                            x as i32 * self.tile_size,
+                            y as i32 * self.tile_size,
+                            self.tile_size,
+                            self.tile_size,
+                            worn_wall_color(self.map.theme.map_or(data.settings.theme.wall, |t| t.wall_color), age),
+                        );
+                    }
+                }
+            }
+        }
+
+        // Background 
+        for y in 0..self.map.grid_h {
+            for x in 0..self.map.grid_w {
+                let tid = self.map.tiles[y][x];
+                if tid >= 0 && is_floor_tile(tid) {
+                    self.draw_tile(d, tid, x, y);
+                }
+            }
+        }
+
+        self.draw_trail(d, data.settings.trail_fade);
+
+        // Structures
+        for y in 0..self.map.grid_h {
+            for x in 0..self.map.grid_w {
+                let tid = self.map.tiles[y][x];
+                if tid >= 0 && is_wall_tile(tid) {
+                    self.draw_tile(d, tid, x, y);
+                }
+            }
+        }
+
+        // Entities
+        for e in &self.map.entities {
+            let screen_x = (e.x as i32) * self.tile_size;
+            let screen_y = (e.y as i32) * self.tile_size;
+
+            match e.kind.as_str() {
+                // the player entity is only the spawn point, the player is drawn at its live position below.
+                // The goal is drawn from the grid with the other special cells.
+                "player" | "goal" => {}
+                "shield" => {
+                    let cx = screen_x + self.tile_size / 2;
+                    let cy = screen_y + self.tile_size / 2;
+                    d.draw_circle(cx, cy, self.tile_size as f32 * 0.25, Color::SKYBLUE);
+                    d.draw_circle_lines(cx, cy, self.tile_size as f32 * 0.3, Color::BLUE);
+                }
+                "treasure" => {
+                    let cx = screen_x + self.tile_size / 2;
+                    let cy = screen_y + self.tile_size / 2;
+                    d.draw_circle(cx, cy, self.tile_size as f32 * 0.2, Color::YELLOW);
+                    d.draw_circle_lines(cx, cy, self.tile_size as f32 * 0.2, Color::ORANGE);
+                }
+                _ => {}
+            }
+        }
+
+        for (id, cells) in &self.gates {
+            if self.open_gates.contains(id) {
+                for &(x, y) in cells {
+                    self.draw_gate(d, x, y, true);
+                }
+            }
+        }
+
+        // Special cells
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                match cell {
+                    // the exit is dimmed until every checkpoint (and corner) has been visited
+                    CellType::Exit => {
+                        let color = self.map.theme.map_or(Color::GOLD, |t| t.exit_color);
+                        d.draw_rectangle(
+                            x as i32 * self.tile_size,
+                            y as i32 * self.tile_size,
+                            self.tile_size,
+                            self.tile_size,
+                            if self.exit_open() { color } else { color.alpha(0.3) },
+                        )
+                    }
+                    // the start is only marked in a map's own colours
+                    CellType::Start => {
+                        if let Some(theme) = &self.map.theme {
+                            d.draw_rectangle(x as i32 * self.tile_size, y as i32 * self.tile_size, self.tile_size, self.tile_size, theme.start_color);
+                        }
+                    }
+                    CellType::Ladder(direction) => self.draw_ladder(d, *direction, x, y),
+                    CellType::Mushroom(distance) => self.draw_mushroom(d, *distance, x, y),
+                    CellType::Mine => self.draw_mine(d, x, y),
+                    CellType::Altar => self.draw_altar(d, x, y),
+                    CellType::Vendor => self.draw_vendor(d, x, y),
+                    CellType::MapFragment => self.draw_map_fragment(d, x, y),
+                    CellType::Wind(direction) => self.draw_wind(d, x, y, *direction),
+                    CellType::Quicksand => self.draw_quicksand(d, x, y),
+                    CellType::SlipperySlope(direction) => self.draw_slope(d, x, y, *direction),
+                    CellType::ArrowTile(direction) => self.draw_arrow_tile(d, x, y, *direction),
+                    CellType::Transformer(mode) => self.draw_transformer(d, x, y, *mode),
+                    CellType::Lava => self.draw_lava(d, x, y),
+                    CellType::TimeStop => self.draw_time_stop(d, x, y),
+                    CellType::SoundTrap => self.draw_sound_trap(d, x, y),
+                    CellType::Button(id) => self.draw_button(d, x, y, *id),
+                    CellType::Warp => self.draw_warp(d, x, y),
+                    CellType::Echo => self.draw_echo(d, x, y),
+                    CellType::Wormhole => self.draw_wormhole(d, x, y),
+                    CellType::Crumble => self.draw_crumble(d, x, y),
+                    CellType::Pit => self.draw_pit(d, x, y),
+                    CellType::LinkedWall(_) if !self.animating_doors.iter().any(|door| (door.x, door.y) == (x, y)) => {
+                        self.draw_gate(d, x, y, false)
+                    }
+                    CellType::Heart => draw_heart(d, self.cell_center(x, y), self.tile_size as f32 * 0.6, Color::RED),
+                    CellType::Water => {
+                        // blue tint with a ripple line that rolls across the flooded area
+                        let screen_x = x as i32 * self.tile_size;
+                        let screen_y = y as i32 * self.tile_size;
+                        let wave = (self.elapsed * 3.0 + x as f32 * 0.8 + y as f32 * 0.5).sin();
+                        d.draw_rectangle(screen_x, screen_y, self.tile_size, self.tile_size, Color::BLUE.alpha(0.45 + 0.1 * wave));
+                        let ripple_y = screen_y + self.tile_size / 2 + (wave * self.tile_size as f32 * 0.2) as i32;
+                        d.draw_line(screen_x + 4, ripple_y, screen_x + self.tile_size - 4, ripple_y, Color::SKYBLUE);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // an outline round every cell, if the player wants grid lines
+        if data.settings.show_grid_lines {
+            for y in 0..self.map.grid_h {
+                for x in 0..self.map.grid_w {
+                    let cell = Rectangle::new(
+                        (x as i32 * self.tile_size) as f32,
+                        (y as i32 * self.tile_size) as f32,
+                        self.tile_size as f32,
+                        self.tile_size as f32,
+                    );
+                    let color = self.map.theme.map_or(data.settings.grid_line_color, |t| t.accent_color);
+                    d.draw_rectangle_lines_ex(cell, data.settings.theme.grid_line_thickness, color);
+                }
+            }
+        }
+
+        // Corner regions still to visit in four corners mode glow violet
+        if self.four_corners && self.on_exit_floor() {
+            for (y, row) in self.grid.iter().enumerate() {
+                for (x, cell) in row.iter().enumerate() {
+                    let Some(corner) = corner_region(x, y, self.map.grid_w, self.map.grid_h) else { continue };
+                    if cell.is_walkable() && !self.corners_visited[corner] {
+                        d.draw_rectangle(
+                            x as i32 * self.tile_size,
+                            y as i32 * self.tile_size,
+                            self.tile_size,
+                            self.tile_size,
+                            Color::VIOLET.alpha(0.25),
+                        );
+                    }
+                }
+            }
+        }
+
+        for door in &self.animating_doors {
+            self.draw_animating_door(d, door);
+        }
+
+        // Illusions the player just walked through: a ghost of the wall's outline fading away
+        for (&(x, y), &time_left) in &self.dissolving_walls {
+            d.draw_rectangle_lines(
+                x as i32 * self.tile_size,
+                y as i32 * self.tile_size,
+                self.tile_size,
+                self.tile_size,
+                Color::BLACK.alpha(time_left / ILLUSION_DISSOLVE_TIME),
+            );
+        }
+
+        // Checkpoints: the next one is highlighted, finished ones are dimmed
+        if self.on_exit_floor() {
+            for (i, &(x, y)) in self.goals.iter().enumerate() {
+                let color = if i < self.next_goal {
+                    Color::LIGHTGRAY
+                } else if i == self.next_goal {
+                    Color::ORANGE
+                } else {
+                    Color::ORANGE.alpha(0.4)
+                };
+                let screen_x = x as i32 * self.tile_size;
+                let screen_y = y as i32 * self.tile_size;
+                d.draw_rectangle(screen_x + 2, screen_y + 2, self.tile_size - 4, self.tile_size - 4, color);
+                d.draw_text(&(i + 1).to_string(), screen_x + self.tile_size / 3, screen_y + self.tile_size / 4, self.tile_size / 2, Color::BLACK);
+            }
+        }
+
+        // the hinted next step pulses yellow
+        if let Some(((x, y), _)) = self.hint {
+            let alpha = 0.45 + 0.35 * (self.elapsed * 6.0).sin();
+            d.draw_rectangle(x as i32 * self.tile_size, y as i32 * self.tile_size, self.tile_size, self.tile_size, Color::YELLOW.alpha(alpha));
+        }
+
+        // Enemies. Frozen ones are tinted blue, and everything stopped in time is grey.
+        let time_stopped = self.time_stop_remaining > 0.0;
+        for enemy in &self.enemies {
+            let center = self.cell_center(enemy.x, enemy.y);
+            let color = if time_stopped {
+                Color::GRAY
+            } else if self.freeze_timer > 0.0 {
+                Color::SKYBLUE
+            } else {
+                Color::MAROON
+            };
+            let size = self.tile_size as f32 * 0.35;
+            match enemy.kind {
+                EnemyKind::Tank => d.draw_poly(center, 4, size, 45.0, color),
+                EnemyKind::Shooter => d.draw_poly(center, 3, size, -90.0, color),
+                EnemyKind::Interceptor => d.draw_poly(center, 5, size, -90.0, color),
+                EnemyKind::Minotaur => {
+                    // a chasing minotaur glows red
+                    let chasing = enemy.minotaur.as_ref().is_some_and(|m| m.state == MinotaurState::Chase);
+                    let color = if chasing && self.freeze_timer <= 0.0 && !time_stopped { Color::RED } else { color };
+                    d.draw_poly(center, 6, size * 1.3, 0.0, color);
+                }
+            }
+        }
+
+        if let Some(ghost) = &self.ghost {
+            self.draw_ghost(d, ghost, data.settings.move_cooldown);
+        }
+        if let Some(rival) = &self.rival {
+            self.draw_rival(d, rival);
+        }
+        for player in &self.players[1..] {
+            d.draw_circle_v(self.cell_center(player.x, player.y), self.tile_size as f32 * 0.35, player.color);
+        }
+
+        // Player, in the colour and size of how they are moving. A giant is drawn over all four of its cells.
+        let (color, scale) = mode_look(self.move_mode);
+        let mut player = self.player_center() + self.player_render_offset();
+        if self.move_mode == MoveMode::Giant {
+            player += Vector2::one() * (self.tile_size as f32 / 2.0);
+        }
+        match (&self.player_sheet, self.fall_timer) {
+            // falling into a pit, shrinking away into it
+            (_, Some(time_left)) => {
+                d.draw_circle_v(player, self.tile_size as f32 * 0.4 * scale * time_left / PIT_FALL_TIME, color)
+            }
+            // the sprite keeps its own colours unless the player is changed
+            (Some(sheet), None) => {
+                let tint = if self.move_mode == MoveMode::Normal { Color::WHITE } else { color };
+                self.draw_player_sprite(d, sheet, player, tint, scale)
+            }
+            (None, None) => d.draw_circle_v(player, self.tile_size as f32 * 0.4 * scale, color),
+        }
+        if self.shield_active {
+            d.draw_circle_lines(player.x as i32, player.y as i32, self.tile_size as f32 * 0.55 * scale, Color::SKYBLUE);
+        }
+
+        // Projectiles, drawn as small diamonds
+        for p in &self.projectiles {
+            d.draw_poly(p.position(), 4, Projectile::RADIUS, 0.0, if time_stopped { Color::GRAY } else { Color::GOLD });
+        }
+
+        if data.settings.particles {
+            for particle in &self.particles {
+                particle.draw(d);
+            }
+        }
+
+        // torches, with a glow over the cells they light
+        for light in &self.lights {
+            let center = self.cell_center(light.x, light.y);
+            let glow = light.radius_at(self.elapsed) * self.tile_size as f32;
+            d.draw_circle_v(center, glow, light.color.alpha(0.12));
+            d.draw_circle_v(center, self.tile_size as f32 * 0.15, light.color);
+        }
+
+        // Fog: cover every cell further from the player than they can currently see, unless a torch
+        // lights it. Cells a map fragment revealed are only shaded. Only torches close enough for
+        // their light to overlap the player's view are looked at. The exit can be set to always show.
+        if self.visibility_radius.is_finite() && !self.fog_hidden {
+            let max_light = self.lights.iter().map(|l| l.radius + l.flicker_amplitude).fold(0.0, f32::max);
+            let reach = self.visibility_radius + max_light;
+            let nearby: Vec<&LightSource> = self
+                .lights
+                .iter()
+                .filter(|l| {
+                    let dx = l.x as f32 - self.players[0].x as f32;
+                    let dy = l.y as f32 - self.players[0].y as f32;
+                    (dx * dx + dy * dy).sqrt() <= reach
+                })
+                .collect();
+            for y in 0..self.map.grid_h {
+                for x in 0..self.map.grid_w {
+                    let dx = x as f32 - self.players[0].x as f32;
+                    let dy = y as f32 - self.players[0].y as f32;
+                    let lit = nearby.iter().any(|light| light.lights(x, y, self.elapsed))
+                        || (data.settings.reveal_exit && self.grid[y][x] == CellType::Exit);
+                    if (dx * dx + dy * dy).sqrt() > self.visibility_radius && !lit {
+                        d.draw_rectangle(
+                            x as i32 * self.tile_size,
+                            y as i32 * self.tile_size,
+                            self.tile_size,
+                            self.tile_size,
+                            if self.is_discovered(x, y) { Color::BLACK.alpha(0.6) } else { Color::BLACK },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn tile_src_rect(tile_id: i32, tile_size: i32, tileset_width: i32) -> Rectangle {
+            let cols = tileset_width / tile_size;
+            let x = (tile_id % cols) * tile_size;
+            let y = (tile_id / cols) * tile_size;
+
+            Rectangle {
+                x: x as f32,
+                y: y as f32,
+                width: tile_size as f32,
+                height: tile_size as f32,
+            }
+    }
+}
+
+impl Scene for MazeScene {
+    fn on_enter(&mut self, rl: &mut RaylibHandle, data: &mut GameData) {
+        if self.entered {
+            return;
+        }
+        self.entered = true;
+        self.build(data);
+
+        // Load the theme's tileset for a hand-made map, and the player's sprite sheet, using the thread
+        // from GameData. Without them the walls are drawn as plain blocks and the player as a circle
+        if let Some(ref thread) = data.thread {
+            if let MazeSource::File(_) = self.source
+                && let Some(path) = data.settings.theme.tileset
+                && Path::new(path).exists()
+            {
+                self.tileset = rl.load_texture(thread, path).ok();
+            }
+            if self.player_sheet.is_none() && Path::new(PLAYER_SHEET).exists() {
+                self.player_sheet = rl.load_texture(thread, PLAYER_SHEET).ok();
+            }
+        }
+    }
+
+
+
+    fn handle_input(&mut self, rl: &mut RaylibHandle, data: &mut GameData) -> SceneSwitch {
+        // F4 or backtick opens the debug console, which takes all of the keyboard while it is open
+        if rl.is_key_pressed(KeyboardKey::KEY_F4) || rl.is_key_pressed(KeyboardKey::KEY_GRAVE) {
+            self.console.open = !self.console.open;
+            return SceneSwitch::None;
+        }
+        if self.console.open {
+            if let Some(line) = self.console.handle_input(rl) {
+                return self.exec_command(&line, data);
+            }
+            return SceneSwitch::None;
+        }
+
+        // Holding Tab pans the camera over to the exit. The player stays put while peeking,
+        // so movement keys are ignored until Tab is released.
+        self.peeking = rl.is_key_down(KeyboardKey::KEY_TAB);
+        if self.peeking {
+            return SceneSwitch::None;
+        }
+
+        // B walks the player back along their breadcrumbs to the start. Esc stops the walk, and
+        // nothing else is listened to until then.
+        if self.retracing {
+            if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+                self.retracing = false;
+            }
+            return SceneSwitch::None;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_B) && self.start_retrace() {
+            return SceneSwitch::None;
+        }
+
+        // Q raises a shield from the inventory. Only one can be up at a time.
+        if rl.is_key_pressed(KeyboardKey::KEY_Q) && !self.shield_active && data.take_item(Item::Shield) {
+            self.shield_active = true;
+        }
+
+        // H shows a hint straight away instead of waiting for one
+        if rl.is_key_pressed(KeyboardKey::KEY_H) && self.hints_left == 0 {
+            self.no_hints_notice = NOTICE_DURATION;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_H) && self.show_hint() {
+            self.record(InputEvent::Hint);
+        }
+
+        // E describes a special cell next to the player
+        if rl.is_key_pressed(KeyboardKey::KEY_E) {
+            self.show_tooltip();
+        }
+
+        // F disarms a trap next to the player, for a coin
+        if rl.is_key_pressed(KeyboardKey::KEY_F) {
+            self.disarm_trap(data);
+        }
+
+        // F5 saves the run and F8 picks it up again
+        if rl.is_key_pressed(KeyboardKey::KEY_F5) {
+            match self.state().save(QUICKSAVE_PATH) {
+                Ok(()) => self.narrator.say("Progress saved."),
+                Err(e) => debug_log!("Failed to save {}: {}", QUICKSAVE_PATH, e),
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_F8) {
+            let restored = MazeState::load(QUICKSAVE_PATH).is_some_and(|state| self.restore(state));
+            self.narrator.say(if restored { "You pick up where you left off." } else { "There is no save for this place." });
+        }
+
+        // F9 prints the player's floor to stdout, for bug reports
+        if rl.is_key_pressed(KeyboardKey::KEY_F9) {
+            print!("{}", FlatGrid::from_rows(&self.grid).to_ascii_with_player((self.players[0].x, self.players[0].y)));
+        }
+
+        // F12 saves the whole maze as a PNG, drawn without the camera or HUD
+        if rl.is_key_pressed(KeyboardKey::KEY_F12) && let Some(thread) = data.thread.as_ref() {
+            let width = (self.grid[0].len() as i32 * self.tile_size) as u32;
+            let height = (self.grid.len() as i32 * self.tile_size) as u32;
+            match ScreenshotMode::capture(rl, thread, width, height, |d| self.draw_world(d, data)) {
+                Ok(path) => {
+                    debug_log!("Saved screenshot to {}", path);
+                    self.screenshot_notice = NOTICE_DURATION;
+                }
+                Err(e) => debug_log!("Failed to save screenshot: {}", e),
+            }
+        }
+
+        // Z and X turn the view a quarter turn anticlockwise or clockwise
+        if data.settings.maze_rotation {
+            if rl.is_key_pressed(KeyboardKey::KEY_Z) {
+                self.view_angle -= 90.0;
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_X) {
+                self.view_angle += 90.0;
+            }
+        }
+
+        // C toggles creeping: half speed, but silent
+        if rl.is_key_pressed(KeyboardKey::KEY_C) {
+            self.toggle_creeping();
+            self.record(InputEvent::Creep);
+        }
+
+        self.update_input_mode(rl);
+
+        // Stuck in quicksand nothing moves the player. Once it lets go, a movement key that is
+        // still held down takes them straight out.
+        let screen_direction = match self.quicksand_release_timer {
+            // nor does anything while a slope has them or they are falling into a pit
+            _ if self.sliding.is_some() || self.fall_timer.is_some() => None,
+            Some(time_left) if time_left > 0.0 => None,
+            Some(_) => {
+                self.quicksand_release_timer = None;
+                Self::held_direction(rl, self.movement_keys())
+            }
+            None => self.requested_direction(rl, data),
+        };
+
+        // Handle player movement with arrow keys or WASD. The keys follow the screen, so in a turned
+        // view they are turned back the other way to get the direction on the grid.
+        if let Some(screen_direction) = screen_direction {
+            let direction = screen_direction.rotated_clockwise(4 - self.view_turns());
+            // holding shift sprints: twice as fast, but the sound traps hear it
+            let sprinting = !self.creeping
+                && (rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT));
+            if self.request_move(0, direction, sprinting, data) {
+                self.record(if sprinting { InputEvent::Sprint(direction) } else { InputEvent::Move(direction) });
+            }
+        }
+        // the other players walk on the arrow keys, which follow a turned view the same way
+        if let Some(screen_direction) = Self::pressed_direction(rl, &ARROW_KEYS) {
+            let direction = screen_direction.rotated_clockwise(4 - self.view_turns());
+            for index in 1..self.players.len() {
+                self.request_move(index, direction, false, data);
+            }
+        }
+        self.play_wall_cues(data);
+        self.debug_assert_player_valid();
+        
+        SceneSwitch::None
+    }
+
+    fn update(&mut self, dt: f32, data: &mut GameData) -> SceneSwitch {
+        self.frame += 1;
+        let switch = self.advance(dt, data);
+        self.debug_assert_player_valid();
+        switch
+    }
+
+    
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.clear_background(self.map.theme.map_or(data.settings.theme.background, |t| t.floor_color));
+
+        // everything in the maze is drawn in world space through the camera
+        {
+            let mut d = d.begin_mode2D(self.camera);
+            self.draw_world(&mut d, data);
+        }
+
+        self.draw_tooltip(d, data);
+
+        // darken the bottom of the screen so the HUD stays readable over any part of the maze
+        let bar = self.map.theme.map_or(Color::BLACK.alpha(0.6), |t| t.hud_color);
+        d.draw_rectangle(0, data.screen_height - 30, data.screen_width, 30, bar);
+
+
+       
         d.draw_text(
             &format!("Score: {}", data.points),
             10,
@@ -241,11 +4074,794 @@ impl Scene for MazeScene {
             20,
             Color::WHITE,
         );
+        self.draw_hearts(d, data);
+        d.draw_text(
+            &format!("Shields: {} (Q)", data.item_count(Item::Shield)),
+            310,
+            data.screen_height - 24,
+            20,
+            if self.shield_active { Color::SKYBLUE } else { Color::WHITE },
+        );
+        d.draw_text(
+            &format!("Streak: {} (x{:.2})", data.win_streak, data.streak_multiplier()),
+            520,
+            data.screen_height - 24,
+            20,
+            Color::WHITE,
+        );
+        d.draw_text(&format!("Coins: {}", data.coins), 740, data.screen_height - 24, 20, Color::GOLD);
+        if !self.goals.is_empty() {
+            d.draw_text(
+                &format!("Checkpoints: {}/{}", self.next_goal, self.goals.len()),
+                860,
+                data.screen_height - 24,
+                20,
+                if self.goals_complete() { Color::GOLD } else { Color::WHITE },
+            );
+        }
+        // one box per corner, laid out like the corners themselves and filled in once visited
+        if self.four_corners {
+            d.draw_text("Corners", 1030, data.screen_height - 24, 20, Color::WHITE);
+            for (corner, visited) in self.corners_visited.iter().enumerate() {
+                let x = 1120 + (corner % 2) as i32 * 12;
+                let y = data.screen_height - 26 + (corner / 2) as i32 * 12;
+                if *visited {
+                    d.draw_rectangle(x, y, 10, 10, Color::GREEN);
+                } else {
+                    d.draw_rectangle_lines(x, y, 10, 10, Color::VIOLET);
+                }
+            }
+        }
+        d.draw_text(
+            &format!("Hints: {}", self.hints_left),
+            1160,
+            data.screen_height - 24,
+            20,
+            if self.hints_left > 0 { Color::WHITE } else { Color::GRAY },
+        );
+        if self.floor.is_some() {
+            d.draw_text(
+                &format!("Floor: {}", if self.on_lower_floor { 2 } else { 1 }),
+                740,
+                data.screen_height - 24,
+                20,
+                Color::WHITE,
+            );
+        }
         
+        if data.settings.instant_restart {
+            let text = format!("Deaths: {}", data.deaths);
+            let width = d.measure_text(&text, 20);
+            d.draw_text(&text, data.screen_width - width - 10, 10, 20, Color::RED);
+        }
+        // shown until the first enemy notices the player
+        if self.perfect_stealth && !(self.enemies.is_empty() && self.floor_enemies.is_empty()) {
+            let width = d.measure_text("Unseen", 20);
+            d.draw_text("Unseen", data.screen_width - width - 10, 35, 20, Color::DARKPURPLE);
+        }
+
+        if let Some((text, _)) = self.banner {
+            let width = d.measure_text(text, 30);
+            let x = (data.screen_width - width) / 2;
+            d.draw_rectangle(x - 20, 10, width + 40, 50, Color::BLACK.alpha(0.7));
+            d.draw_text(text, x, 20, 30, Color::GOLD);
+        }
+
+        if let Some(time_left) = data.time_trial {
+            let seconds = time_left.ceil() as i32;
+            let text = format!("Cleared: {}   {}:{:02}", data.mazes_cleared, seconds / 60, seconds % 60);
+            d.draw_rectangle(0, 0, d.measure_text(&text, 30) + 20, 44, Color::BLACK.alpha(0.6));
+            d.draw_text(&text, 10, 7, 30, if time_left < 30.0 { Color::ORANGE } else { Color::WHITE });
+        }
+
+        // who is ahead in a race, by the steps each has left. The player's count is unknown while a
+        // shut gate is in their way.
+        if let Some(rival) = &self.rival {
+            let player_left = self
+                .exit_distances
+                .get(self.players[0].y)
+                .and_then(|row| row.get(self.players[0].x))
+                .filter(|d| **d != UNREACHABLE)
+                .map(|d| *d as usize + if self.on_exit_floor() { 0 } else { rival.path.iter().filter(|(lower, _, _)| *lower).count() });
+            let (text, color) = match player_left {
+                Some(left) if left < rival.steps_left() => (format!("Race: you lead by {}", rival.steps_left() - left), Color::DARKGREEN),
+                Some(left) if left > rival.steps_left() => (format!("Race: rival leads by {}", left - rival.steps_left()), Color::PURPLE),
+                Some(_) => ("Race: neck and neck".to_string(), Color::DARKGRAY),
+                None => (format!("Race: rival {} steps from the exit", rival.steps_left()), Color::PURPLE),
+            };
+            d.draw_text(&text, 10, 50, 20, color);
+        }
+
+        if self.time_stop_remaining > 0.0 {
+            let text = format!("Time stopped: {:.1}s", self.time_stop_remaining);
+            let width = d.measure_text(&text, 30);
+            d.draw_text(&text, (data.screen_width - width) / 2, 70, 30, Color::DARKBLUE);
+        }
+
+        // the on-screen D-pad, with an arrow in each button
+        if self.input_mode == InputMode::Touch {
+            for (direction, center) in Self::dpad_buttons(data) {
+                d.draw_circle_lines(center.x as i32, center.y as i32, DPAD_BUTTON_RADIUS, Color::WHITE);
+                let rotation = match direction {
+                    Direction::East => 0.0,
+                    Direction::South => 90.0,
+                    Direction::West => 180.0,
+                    Direction::North => 270.0,
+                };
+                d.draw_poly(center, 3, DPAD_BUTTON_RADIUS * 0.4, rotation, Color::WHITE.alpha(0.7));
+            }
+        }
+
+        if self.creeping || self.player_noise_level > 0.0 {
+            let text = format!("Noise: {:.1}{}", self.player_noise_level, if self.creeping { " (creeping, C)" } else { "" });
+            d.draw_text(&text, 10, data.screen_height - 78, 20, if self.creeping { Color::SKYBLUE } else { Color::MAROON });
+        }
+
+        // both notices share a line, and running out of hints is the newer news
+        if self.no_hints_notice > 0.0 {
+            d.draw_text("No hints left in this maze", 10, data.screen_height - 54, 20, Color::ORANGE);
+        } else if self.hint.is_none() && self.hints_left > 0 && self.idle_timer >= HINT_NOTICE_TIME {
+            d.draw_text("Hint available! Tap H to skip to next hint", 10, data.screen_height - 54, 20, Color::YELLOW);
+        }
+
+        self.narrator.draw(d, data.screen_width, data.screen_height - 150);
+
+        if self.revisit_notice > 0.0 {
+            let text = format!("Been here before! -{} points", REVISIT_PENALTY);
+            let width = d.measure_text(&text, 30);
+            d.draw_text(&text, (data.screen_width - width) / 2, data.screen_height - 110, 30, Color::RED);
+        }
+
+        if self.screenshot_notice > 0.0 {
+            let width = d.measure_text("Screenshot saved!", 20);
+            d.draw_text("Screenshot saved!", data.screen_width - width - 10, data.screen_height - 54, 20, Color::DARKGREEN);
+        }
+
+        if data.settings.auto_map {
+            self.draw_auto_map(d, data);
+        }
+
+        self.console.draw(d, data.screen_width);
+
         // // Draw score
         // let message = format!("Score: {}", data.points);
         // d.draw_text(message.as_str(), 10, data.screen_height - 25, 20, Color::BLACK);
     }
 
-    fn on_exit(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
+    fn on_exit(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {
+        match self.export_visit_heatmap() {
+            Ok(path) => debug_log!("Wrote visit heatmap to {}", path),
+            Err(e) => debug_log!("Failed to write visit heatmap: {}", e),
+        }
+        if let Some(replay) = self.replay.as_ref().filter(|replay| !replay.inputs.is_empty()) {
+            match save_replay(REPLAY_PATH, replay) {
+                Ok(()) => debug_log!("Wrote replay to {}", REPLAY_PATH),
+                Err(e) => debug_log!("Failed to write replay {}: {}", REPLAY_PATH, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::{Difficulty, DifficultyParams};
+
+    // A single floor laid out from rows of `#` walls, `.` paths, `S` the start and `E` the exit, set
+    // up as entering it would. It goes through a `.mzb` file named after the test.
+    fn scene(name: &str, rows: &[&str]) -> (MazeScene, GameData) {
+        let grid: Vec<Vec<CellType>> = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| match c {
+                        '#' => CellType::Wall,
+                        'S' => CellType::Start,
+                        'E' => CellType::Exit,
+                        _ => CellType::Path,
+                    })
+                    .collect()
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("dungeon_diver_{}.mzb", name));
+        std::fs::write(&path, FlatGrid::from_rows(&grid).to_binary().unwrap()).unwrap();
+
+        let mut data = GameData::new(800, 600);
+        let mut scene = MazeScene::from_binary_file(path.to_string_lossy().into_owned());
+        scene.build(&mut data);
+        (scene, data)
+    }
+
+    // make each move, with a frame of play after it
+    fn walk(scene: &mut MazeScene, data: &mut GameData, moves: &[Direction]) {
+        for direction in moves {
+            assert!(scene.try_move(*direction), "{:?} from ({}, {})", direction, scene.players[0].x, scene.players[0].y);
+            scene.update(0.0, data);
+        }
+    }
+
+    #[test]
+    fn visit_counts_follow_the_moves() {
+        let (mut scene, _) = scene("visit_counts", &["#####", "#S..#", "###E#", "#####"]);
+        for direction in [Direction::East, Direction::East, Direction::West, Direction::East] {
+            assert!(scene.try_move(direction));
+        }
+        assert_eq!(scene.visit_counts[1], vec![0, 1, 2, 2, 0]);
+        assert_eq!(scene.visit_counts[2][3], 0);
+        assert_eq!(scene.visit_counts.iter().flatten().sum::<u32>(), 5);
+    }
+
+    #[test]
+    fn goals_only_advance_in_order() {
+        let (mut scene, _) = scene("goals_in_order", &["######", "#S...#", "####E#", "######"]);
+        scene.goals = vec![(3, 1), (2, 1)];
+        scene.next_goal = 0;
+
+        // the second checkpoint does not count before the first
+        scene.try_move(Direction::East);
+        assert!(!scene.advance_goal());
+        scene.try_move(Direction::East);
+        assert!(scene.advance_goal());
+        assert!(!scene.goals_complete());
+        assert!(!scene.advance_goal());
+        scene.try_move(Direction::West);
+        assert!(scene.advance_goal());
+        assert!(scene.goals_complete());
+    }
+
+    #[test]
+    fn mines_hurt_and_hearts_heal_within_bounds() {
+        let (mut scene, mut data) = scene("health", &["######", "#S..E#", "######"]);
+        scene.grid[1][2] = CellType::Mine;
+        scene.grid[1][3] = CellType::Heart;
+        data.health = data.max_health;
+
+        scene.try_move(Direction::East);
+        scene.update(0.0, &mut data);
+        assert_eq!(data.health, data.max_health - scene.mine_damage);
+        scene.try_move(Direction::East);
+        scene.update(0.0, &mut data);
+        assert_eq!(data.health, (data.max_health - scene.mine_damage + HEALTH_PER_HEART).min(data.max_health));
+
+        // a heart never fills past the maximum and a mine never takes health below zero
+        scene.grid[1][3] = CellType::Heart;
+        data.health = data.max_health - 1;
+        scene.update(0.0, &mut data);
+        assert_eq!(data.health, data.max_health);
+        scene.grid[1][3] = CellType::Mine;
+        data.health = 1;
+        scene.update(0.0, &mut data);
+        assert_eq!(data.health, 0);
+    }
+
+    #[test]
+    fn moves_inside_the_cooldown_are_dropped() {
+        let (mut scene, mut data) = scene("move_cooldown", &["######", "#S...#", "####E#", "######"]);
+        data.settings.move_cooldown = 0.15;
+
+        assert!(scene.request_move(0, Direction::East, false, &mut data));
+        scene.update(0.1, &mut data);
+        assert!(!scene.request_move(0, Direction::East, false, &mut data));
+        assert_eq!((scene.players[0].x, scene.players[0].y), (2, 1));
+
+        scene.update(0.1, &mut data);
+        assert!(scene.request_move(0, Direction::East, false, &mut data));
+        assert_eq!((scene.players[0].x, scene.players[0].y), (3, 1));
+    }
+
+    #[test]
+    fn each_corner_flips_its_own_flag() {
+        let rows = ["#########", "#.......#", "#.......#", "#.......#", "#...S...#", "#.......#", "#.......#", "#......E#", "#########"];
+        let (mut scene, _) = scene("corner_visits", &rows);
+        scene.corners_visited = [false; 4];
+
+        let mut expected = [false; 4];
+        for ((x, y), corner) in [((4, 4), None), ((2, 2), Some(0)), ((6, 1), Some(1)), ((1, 7), Some(2)), ((4, 7), None), ((7, 6), Some(3))] {
+            (scene.players[0].x, scene.players[0].y) = (x, y);
+            scene.visit_corner();
+            if let Some(corner) = corner {
+                expected[corner] = true;
+            }
+            assert_eq!(scene.corners_visited, expected, "at ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn the_game_speed_scales_the_clock() {
+        let (mut scene, mut data) = scene("game_speed", &["#####", "#S.E#", "#####"]);
+        data.game_speed = 2.0;
+        for _ in 0..10 {
+            scene.update(data.scaled_dt(0.05), &mut data);
+        }
+        assert!((scene.elapsed - 1.0).abs() < 1e-4);
+
+        data.game_speed = 0.5;
+        scene.update(data.scaled_dt(1.0), &mut data);
+        assert!((scene.elapsed - 1.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn a_map_fragment_reveals_the_cells_around_it() {
+        let mut rows = vec!["#".repeat(31)];
+        rows.push(format!("#S{}#", ".".repeat(28)));
+        rows.extend((0..14).map(|_| format!("#{}#", ".".repeat(29))));
+        rows.push(format!("#{}E#", ".".repeat(28)));
+        rows.push("#".repeat(31));
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        let (mut scene, mut data) = scene("map_fragment", &rows);
+        assert!(!scene.discovered.iter().flatten().any(|d| *d));
+
+        scene.grid[8][15] = CellType::MapFragment;
+        (scene.players[0].x, scene.players[0].y) = (15, 8);
+        scene.update(0.0, &mut data);
+        assert_eq!(scene.grid[8][15], CellType::Path);
+        for y in 0..scene.map.grid_h {
+            for x in 0..scene.map.grid_w {
+                let inside = x.abs_diff(15) <= MAP_FRAGMENT_REACH && y.abs_diff(8) <= MAP_FRAGMENT_REACH;
+                assert_eq!(scene.is_discovered(x, y), inside, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn lava_blocks_a_move_like_a_wall() {
+        let (mut scene, mut data) = scene("lava", &["#####", "#S..#", "#.#E#", "#####"]);
+        scene.grid[2][1] = CellType::Lava;
+        assert!(!scene.is_valid_move(1, 2));
+        assert!(!scene.try_move(Direction::South));
+        assert_eq!((scene.players[0].x, scene.players[0].y), (1, 1));
+        assert!(!scene.try_move(Direction::West));
+        assert_eq!((scene.players[0].x, scene.players[0].y), (1, 1));
+        assert!(!scene.burned);
+        assert!(matches!(scene.update(0.0, &mut data), SceneSwitch::None));
+    }
+
+    #[test]
+    fn a_bounce_into_lava_ends_the_run() {
+        let (mut scene, mut data) = scene("lava_bounce", &["######", "#S...#", "####E#", "######"]);
+        scene.grid[1][2] = CellType::Mushroom(2);
+        scene.grid[1][3] = CellType::Lava;
+        scene.try_move(Direction::East);
+        assert!(scene.burned);
+        assert_eq!((scene.players[0].x, scene.players[0].y), (3, 1));
+        assert!(matches!(scene.update(0.0, &mut data), SceneSwitch::FadeReplace(_)));
+        assert_eq!(data.health, 0);
+    }
+
+    #[test]
+    fn a_quick_run_with_every_coin_earns_three_stars_and_a_slow_one_one() {
+        use Direction::*;
+        let rows = ["#############", "#S.........E#", "####.########", "#############"];
+        let (mut quick, mut data) = scene("stars_quick", &rows);
+        quick.spawn_collectible("treasure".to_string(), 4, 2);
+        assert_eq!(quick.par_steps, 10);
+        walk(&mut quick, &mut data, &[East, East, East, South, North, East, East, East, East, East, East]);
+        assert!(quick.try_move(East));
+        assert_eq!(quick.objectives().stars(), 3);
+
+        let (mut slow, mut data) = scene("stars_slow", &rows);
+        slow.spawn_collectible("treasure".to_string(), 4, 2);
+        walk(&mut slow, &mut data, &[East, East, West, West, East, East, East, East, East, East, East, East, East]);
+        assert!(slow.try_move(East));
+        assert_eq!(slow.objectives().stars(), 1);
+    }
+
+    #[test]
+    fn enemies_never_spawn_near_the_start() {
+        for seed in 0..10 {
+            let mut data = GameData::new(800, 600);
+            data.difficulty.spawn_safe_radius = 5;
+            let mut scene = MazeScene::new(41, 29, seed);
+            scene.build(&mut data);
+            let start = scene.find_cell(CellType::Start).unwrap();
+            // with the player far away the start is fair game but for the safe radius
+            (scene.players[0].x, scene.players[0].y) = crate::maze_gen::farthest_cell(&scene.grid, start);
+            for _ in 0..10 {
+                scene.trigger_event(EventType::EnemyRushWave);
+            }
+            assert!(!scene.enemies.is_empty());
+            let routes = dijkstra_from(&scene.grid, [(scene.players[0].x, scene.players[0].y)], |cell| cell.is_enemy_walkable());
+            for enemy in &scene.enemies {
+                assert!(enemy.x.abs_diff(start.0) + enemy.y.abs_diff(start.1) > 5, "seed {}: enemy at ({}, {})", seed, enemy.x, enemy.y);
+                assert_ne!(routes[enemy.y][enemy.x], UNREACHABLE);
+            }
+        }
+    }
+
+    #[test]
+    fn a_random_walk_never_trips_the_player_check() {
+        for seed in 0..5 {
+            let mut data = GameData::new(800, 600);
+            let mut scene = MazeScene::new(31, 21, seed);
+            scene.build(&mut data);
+            let mut rng = StdRng::seed_from_u64(seed);
+            for _ in 0..2000 {
+                scene.try_move(*Direction::ALL.choose(&mut rng).unwrap());
+                // update checks the player after every frame
+                scene.update(1.0 / 60.0, &mut data);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "player is standing on")]
+    fn standing_in_a_wall_trips_the_player_check() {
+        let (mut scene, _) = scene("player_check", &["#####", "#S.E#", "#####"]);
+        scene.players[0].y = 0;
+        scene.debug_assert_player_valid();
+    }
+
+    #[test]
+    fn each_revisit_costs_the_penalty_once() {
+        use Direction::*;
+        let (mut scene, mut data) = scene("revisits", &["######", "#S...#", "####E#", "######"]);
+        data.settings.no_backtrack_challenge = true;
+        data.settings.move_cooldown = 0.0;
+        data.points = 100;
+        for (direction, points) in [(East, 100), (East, 100), (West, 90), (West, 80), (East, 70), (East, 60), (East, 60)] {
+            assert!(scene.request_move(0, direction, false, &mut data));
+            assert_eq!(data.points, points, "after {:?} to ({}, {})", direction, scene.players[0].x, scene.players[0].y);
+        }
+
+        data.settings.no_backtrack_challenge = false;
+        assert!(scene.request_move(0, West, false, &mut data));
+        assert_eq!(data.points, 60);
+    }
+
+    #[test]
+    fn a_button_opens_and_shuts_its_gate_each_time_it_is_pressed() {
+        use Direction::*;
+        let (mut scene, mut data) = scene("button", &["#######", "#S...E#", "#######"]);
+        let gate = CellType::LinkedWall(ButtonId(0));
+        scene.grid[1][2] = CellType::Button(ButtonId(0));
+        scene.grid[1][4] = gate;
+        scene.gates = gates(&scene.grid);
+
+        walk(&mut scene, &mut data, &[East, East]);
+        assert!(!scene.try_move(East));
+        // the gate slides open from the first press
+        scene.update(DOOR_SLIDE_TIME, &mut data);
+        assert_eq!(scene.grid[1][4], CellType::Path);
+
+        // a second press shuts it at once, and a third opens it again
+        walk(&mut scene, &mut data, &[West]);
+        assert_eq!(scene.grid[1][4], gate);
+        walk(&mut scene, &mut data, &[East, West, East]);
+        scene.update(DOOR_SLIDE_TIME, &mut data);
+        assert_eq!(scene.grid[1][4], CellType::Path);
+        assert!(scene.try_move(East));
+    }
+
+    #[test]
+    fn a_warp_pushes_a_side_maze_that_pops_back_with_its_reward() {
+        let (mut hub, mut data) = scene("warp_hub", &["#######", "#S...E#", "#######"]);
+        hub.grid[1][3] = CellType::Warp;
+        walk(&mut hub, &mut data, &[Direction::East]);
+        assert!(hub.try_move(Direction::East));
+        assert!(matches!(hub.update(0.0, &mut data), SceneSwitch::FadePush(_)));
+        assert_eq!(hub.open_warp, Some((3, 1)));
+
+        // a side maze like the one pushed, cleared by the console's shortcut down the ladder and
+        // onto the exit
+        let mut side = MazeScene::side_maze(7);
+        side.build(&mut data);
+        let points = data.points;
+        let ladder = position_of(&side.grid, |c| matches!(c, CellType::Ladder(_))).unwrap();
+        side.exec_command(&format!("tp {} {}", ladder.0, ladder.1), &mut data);
+        assert!(side.on_lower_floor);
+        let exit = side.find_cell(CellType::Exit).unwrap();
+        side.exec_command(&format!("tp {} {}", exit.0, exit.1), &mut data);
+        assert!(matches!(side.update(0.0, &mut data), SceneSwitch::Pop));
+        assert!(data.side_maze_cleared);
+        assert_eq!(data.points, points + SIDE_MAZE_REWARD);
+
+        // back in the hub the cleared warp is gone, so standing on it pushes nothing more
+        assert!(matches!(hub.update(0.0, &mut data), SceneSwitch::None));
+        assert!(!data.side_maze_cleared);
+        assert_eq!(hub.grid[1][3], CellType::Path);
+        assert!(hub.try_move(Direction::West) && hub.try_move(Direction::East));
+        assert!(matches!(hub.update(0.0, &mut data), SceneSwitch::None));
+    }
+
+    #[test]
+    fn an_unfinished_side_maze_leaves_its_warp_to_try_again() {
+        let (mut hub, mut data) = scene("warp_retry", &["#######", "#S...E#", "#######"]);
+        hub.grid[1][3] = CellType::Warp;
+        walk(&mut hub, &mut data, &[Direction::East]);
+        assert!(hub.try_move(Direction::East));
+        assert!(matches!(hub.update(0.0, &mut data), SceneSwitch::FadePush(_)));
+
+        // the side maze popped without being cleared
+        assert!(matches!(hub.update(0.0, &mut data), SceneSwitch::None));
+        assert_eq!(hub.grid[1][3], CellType::Warp);
+        assert!(hub.try_move(Direction::West));
+        hub.update(0.0, &mut data);
+        assert!(hub.try_move(Direction::East));
+        assert!(matches!(hub.update(0.0, &mut data), SceneSwitch::FadePush(_)));
+    }
+
+    #[test]
+    fn each_hint_uses_one_up_until_none_are_left() {
+        let (mut scene, _) = scene("hint_budget", &["#######", "#S...E#", "#######"]);
+        assert_eq!(scene.hints_left, HINTS_PER_MAZE);
+        for left in (0..HINTS_PER_MAZE).rev() {
+            assert!(scene.show_hint());
+            assert_eq!(scene.hints_left, left);
+            assert_eq!(scene.hint.map(|(cell, _)| cell), Some((2, 1)));
+        }
+        scene.hint = None;
+        assert!(!scene.show_hint());
+        assert_eq!(scene.hints_left, 0);
+        assert!(scene.hint.is_none());
+    }
+
+    #[test]
+    fn each_players_input_moves_only_that_player() {
+        let (mut scene, mut data) = scene("co_op", &["#######", "#S...E#", "#######"]);
+        scene.players.push(Player::at(1, 1, Color::ORANGE));
+        assert!(scene.request_move(1, Direction::East, false, &mut data));
+        assert_eq!((scene.players[0].x, scene.players[1].x), (1, 2));
+        assert!(scene.request_move(0, Direction::East, false, &mut data));
+        assert_eq!((scene.players[0].x, scene.players[1].x), (2, 2));
+        assert!(!scene.request_move(1, Direction::North, false, &mut data));
+
+        // the second player reaches the exit alone
+        for _ in 0..3 {
+            scene.update(1.0, &mut data);
+            assert!(scene.request_move(1, Direction::East, false, &mut data));
+        }
+        assert_eq!(scene.players[0].x, 2);
+        assert!(scene.players[1].reached_exit);
+        assert!(scene.level_complete(CoOpMode::Race));
+        assert!(!scene.level_complete(CoOpMode::Together));
+        assert!(!scene.level_complete(CoOpMode::Off));
+
+        scene.update(1.0, &mut data);
+        assert!(!scene.request_move(1, Direction::West, false, &mut data));
+        walk(&mut scene, &mut data, &[Direction::East; 3]);
+        assert!(scene.level_complete(CoOpMode::Together));
+    }
+
+    #[test]
+    fn the_rival_walks_the_shortest_way_through_a_shut_gate() {
+        let (mut scene, _) = scene("rival_route", &["#######", "#S...E#", "#.###.#", "#.....#", "#######"]);
+        scene.grid[1][3] = CellType::LinkedWall(ButtonId(0));
+        let path = scene.rival_route().unwrap();
+        assert_eq!(path.first(), Some(&(false, 1, 1)));
+        assert_eq!(path.last(), Some(&(false, 5, 1)));
+        assert_eq!(path.len(), 5);
+        for pair in path.windows(2) {
+            let ((_, ax, ay), (_, bx, by)) = (pair[0], pair[1]);
+            assert_eq!(ax.abs_diff(bx) + ay.abs_diff(by), 1, "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn tp_moves_the_player_only_onto_a_cell_they_can_stand_on() {
+        let (mut scene, mut data) = scene("console_tp", &["#####", "#S..#", "#.#.#", "#..E#", "#####"]);
+        let _ = scene.exec_command("tp 3 2", &mut data);
+        assert_eq!(scene.player_cell(), (false, 3, 2));
+        for line in ["tp 2 2", "tp 0 0", "tp 9 1", "tp 1 99"] {
+            let _ = scene.exec_command(line, &mut data);
+            assert_eq!(scene.player_cell(), (false, 3, 2), "{}", line);
+        }
+        let _ = scene.exec_command("tp 1 3", &mut data);
+        assert_eq!(scene.player_cell(), (false, 1, 3));
+    }
+
+    #[test]
+    fn retracing_walks_back_to_the_start() {
+        use Direction::*;
+        let (mut scene, mut data) = scene("retrace", &["#######", "#S....#", "#.###.#", "#....E#", "#######"]);
+        walk(&mut scene, &mut data, &[East, East, East, West, East, East, South, South, West, West]);
+        assert_eq!(scene.player_cell(), (false, 3, 3));
+        assert!(scene.start_retrace());
+        for _ in 0..20 {
+            scene.update(RETRACE_STEP_TIME, &mut data);
+        }
+        assert!(!scene.retracing);
+        assert_eq!(scene.player_cell(), (false, 1, 1));
+    }
+
+    #[test]
+    fn a_tiny_window_gets_a_small_maze_on_small_tiles() {
+        let mut data = GameData::new(60, 40);
+        let mut maze = MazeScene::new(1, 1, 3);
+        maze.build(&mut data);
+        assert!(maze.grid.len() >= 5 && maze.grid[0].len() >= 5, "{}x{}", maze.grid[0].len(), maze.grid.len());
+        assert_eq!(maze.tile_size, 8);
+
+        let (big, _) = scene("big_window", &["#####", "#S.E#", "#####"]);
+        assert_eq!(big.tile_size, big.map.tile_size_px);
+        assert_eq!(fitted_tile_size(32, 3, 3), 1);
+    }
+
+    // A walled maze of the given size with one corridor from the start to the exit along the top,
+    // after a run of `steps` steps over `time` seconds on a par of 10 steps
+    fn finished_run(name: &str, width: usize, height: usize, steps: u32, time: f32) -> (MazeScene, GameData) {
+        let mut rows = vec!["#".repeat(width); height];
+        rows[1] = format!("#S{}E#", ".".repeat(width - 4));
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        let (mut maze, data) = scene(name, &rows);
+        (maze.steps, maze.par_steps, maze.elapsed) = (steps, 10, time);
+        (maze, data)
+    }
+
+    #[test]
+    fn the_level_score_adds_up_its_parts() {
+        let start = DifficultyParams::default();
+        // a starting-size maze, twice par in steps, five seconds under par time, no treasure left
+        // and a streak of 4: (100 * (0.5 + 0.5 * 0.5) + 5 * 2 + 50) * 2
+        let (maze, mut data) = finished_run("score_parts", start.maze_width, start.maze_height, 20, 1.0);
+        data.win_streak = 4;
+        assert_eq!(maze.compute_level_score(&data), 270);
+        data.win_streak = 0;
+        assert_eq!(maze.compute_level_score(&data), 135);
+
+        // on par, and slower than par time
+        let (maze, data) = finished_run("score_on_par", start.maze_width, start.maze_height, 10, 60.0);
+        assert_eq!(maze.compute_level_score(&data), 150);
+    }
+
+    #[test]
+    fn the_same_run_scores_more_the_harder_the_game() {
+        let (maze, mut data) = finished_run("score_by_difficulty", 21, 15, 20, 60.0);
+        let scores: Vec<u32> = Difficulty::ALL
+            .into_iter()
+            .map(|difficulty| {
+                data.settings.difficulty = difficulty;
+                maze.compute_level_score(&data)
+            })
+            .collect();
+        // (100 * multiplier * 0.75 + 50), with no time bonus
+        assert_eq!(scores, vec![88, 125, 200]);
+    }
+
+    #[test]
+    fn disarming_a_trap_costs_a_coin_and_needs_one() {
+        let (mut maze, mut data) = scene("disarm", &["######", "#S..E#", "######"]);
+        maze.grid[1][2] = CellType::Mine;
+        data.coins = 0;
+        maze.disarm_trap(&mut data);
+        assert_eq!(maze.grid[1][2], CellType::Mine);
+        assert_eq!(data.coins, 0);
+        assert!(maze.tooltip.as_ref().is_some_and(|t| t.message.contains("need a coin")));
+
+        data.coins = 2;
+        maze.disarm_trap(&mut data);
+        assert_eq!(maze.grid[1][2], CellType::Path);
+        assert_eq!(data.coins, 1);
+
+        // nothing next to the player to disarm
+        maze.disarm_trap(&mut data);
+        assert_eq!(data.coins, 1);
+    }
+
+    #[test]
+    fn messages_not_yet_shown_survive_saving_the_run() {
+        let mut map = MapData::blank(6, 3, 32);
+        let mut cells = vec![vec![CellType::Wall; 6]; 3];
+        cells[1] = vec![CellType::Wall, CellType::Start, CellType::Path, CellType::Path, CellType::Exit, CellType::Wall];
+        map.cells = Some(cells);
+        map.messages.push(MapMessage { x: 2, y: 1, text: "A draught from the east".to_string() });
+        map.messages.push(MapMessage { x: 3, y: 1, text: "Almost there".to_string() });
+        let path = std::env::temp_dir().join("dungeon_diver_maze_state_map.json").to_string_lossy().into_owned();
+        save_map(&path, &map).unwrap();
+
+        let mut data = GameData::new(800, 600);
+        let mut first = MazeScene::from_map(path.clone());
+        first.build(&mut data);
+        walk(&mut first, &mut data, &[Direction::East]);
+        assert_eq!(first.narrator.current_text, "A draught from the east");
+        let state_path = std::env::temp_dir().join("dungeon_diver_maze_state.json");
+        let state_path = state_path.to_str().unwrap();
+        first.state().save(state_path).unwrap();
+
+        let state = MazeState::load(state_path).unwrap();
+        assert_eq!(state, first.state());
+        assert_eq!(state.messages, vec![MapMessage { x: 3, y: 1, text: "Almost there".to_string() }]);
+        let mut second = MazeScene::from_map(path);
+        second.build(&mut data);
+        assert!(second.restore(state.clone()));
+        assert_eq!(second.player_cell(), (false, 2, 1));
+        walk(&mut second, &mut data, &[Direction::West, Direction::East]);
+        assert!(second.narrator.current_text.is_empty());
+        walk(&mut second, &mut data, &[Direction::East]);
+        assert_eq!(second.narrator.current_text, "Almost there");
+
+        // a state only fits the level it was saved in
+        let (mut other, _) = scene("maze_state_other", &["######", "#S..E#", "######"]);
+        assert!(!other.restore(state));
+    }
+
+    #[test]
+    fn a_map_round_trips_through_json_with_its_cells() {
+        let mut map = MapData::blank(3, 2, 32);
+        map.cells = Some(vec![
+            vec![CellType::Start, CellType::Ladder(LadderDirection::Down), CellType::Wind(Direction::West)],
+            vec![CellType::Button(ButtonId(1)), CellType::LinkedWall(ButtonId(1)), CellType::Exit],
+        ]);
+        map.entities.push(MapEntity { kind: "treasure".to_string(), x: 1, y: 0, id: EntityId::default() });
+        map.messages.push(MapMessage { x: 2, y: 1, text: "Out at last".to_string() });
+        map.theme = Some(CustomTheme {
+            wall_color: Color::DARKGRAY,
+            floor_color: Color::WHITE,
+            start_color: Color::GREEN,
+            exit_color: Color::GOLD,
+            accent_color: Color::GRAY,
+            hud_color: Color::BLACK,
+        });
+        let path = std::env::temp_dir().join("dungeon_diver_round_trip.json");
+        let path = path.to_str().unwrap();
+        save_map(path, &map).unwrap();
+        let loaded = load_map(path);
+        assert_eq!(loaded, map);
+        assert_eq!(Some(grid_from_map(&loaded)), map.cells);
+
+        // without cells they are worked out from the tiles, and none are written
+        map.cells = None;
+        assert!(!serde_json::to_string(&map).unwrap().contains("cells"));
+        assert_eq!(grid_from_map(&map), vec![vec![CellType::Wall; 3]; 2]);
+    }
+
+    #[test]
+    fn the_same_seed_sets_the_same_dungeon_up() {
+        let build = || {
+            let mut data = GameData::new(800, 600);
+            let mut maze = MazeScene::new(31, 21, 9);
+            maze.build(&mut data);
+            maze
+        };
+        let (first, second) = (build(), build());
+        assert_eq!((&first.grid, &first.floor, &first.goals), (&second.grid, &second.floor, &second.goals));
+        let cells = |maze: &MazeScene| maze.enemies.iter().map(|e| (e.x, e.y)).collect::<Vec<_>>();
+        assert_eq!(cells(&first), cells(&second));
+    }
+
+    #[test]
+    fn only_generated_dungeons_are_recorded() {
+        let mut data = GameData::new(800, 600);
+        let mut dungeon = MazeScene::generated(21, 15, MazeAlgorithm::Mirror, 5).with_floors(1);
+        dungeon.build(&mut data);
+        let replay = dungeon.replay.as_ref().unwrap();
+        assert_eq!((replay.seed, replay.algorithm, replay.floors), (5, MazeAlgorithm::Mirror, 1));
+        dungeon.record(InputEvent::Hint);
+        assert_eq!(dungeon.replay.unwrap().inputs, vec![(0, InputEvent::Hint)]);
+
+        let mut side = MazeScene::side_maze(5);
+        side.build(&mut data);
+        assert!(side.replay.is_none());
+        let (mut file, _) = scene("not_recorded", &["#####", "#S.E#", "#####"]);
+        file.record(InputEvent::Hint);
+        assert!(file.replay.is_none());
+    }
+
+    #[test]
+    fn a_dungeon_has_one_floor_or_two() {
+        let has = |grid: &[Vec<CellType>], wanted: fn(&CellType) -> bool| grid.iter().flatten().any(wanted);
+        for seed in 0..5 {
+            let mut data = GameData::new(800, 600);
+            data.difficulty.maze_width = 49;
+            let mut one = MazeScene::new(41, 29, seed).with_floors(1);
+            one.build(&mut data);
+            assert!(one.floor.is_none());
+            assert!(has(&one.grid, |c| *c == CellType::Exit));
+            assert!(!has(&one.grid, |c| matches!(c, CellType::Ladder(_))));
+            // the minotaur guards the exit of the only floor
+            assert!(one.enemies.iter().any(|e| e.kind == EnemyKind::Minotaur), "seed {}", seed);
+            let start = one.find_cell(CellType::Start).unwrap();
+            let exit = one.find_cell(CellType::Exit).unwrap();
+            let routes = dijkstra_from(&one.grid, [start], |cell| cell != CellType::Wall);
+            assert_ne!(routes[exit.1][exit.0], UNREACHABLE, "seed {}", seed);
+
+            let mut two = MazeScene::new(41, 29, seed);
+            two.build(&mut data);
+            let lower = two.floor.as_ref().unwrap();
+            assert!(has(&two.grid, |c| matches!(c, CellType::Ladder(_))));
+            assert!(!has(&two.grid, |c| *c == CellType::Exit));
+            assert!(has(lower, |c| *c == CellType::Exit));
+            assert!(has(lower, |c| matches!(c, CellType::Ladder(_))));
+        }
+    }
 }
@@ -3,11 +3,35 @@
 //! 
 use raylib::prelude::*;
 // use rand::{self, Rng};
-
-use crate::game_data::GameData;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::benchmark_scene::PathBenchmarkScene;
+use crate::editor_scene::{MazeEditorScene, EDITOR_HEIGHT, EDITOR_WIDTH};
+use crate::game_data::{GameData, StarObjectives, StealthRank};
+use crate::level_intro::LevelIntroScene;
+use crate::maze_gen::{generate_maze, CellType};
 use crate::maze_scene::MazeScene;
+use crate::pathfinding::solve_astar;
+use crate::replay::{load_replay, ReplayPlayer, REPLAY_PATH};
 use crate::scenes::{Scene,SceneSwitch}; 
+use crate::settings::SettingItem;
+use crate::{debug_log, maze_size};
 use crate::utils::*;
+use std::path::Path;
+
+const MAP_PATH: &str = "assets/maps/mapTest.json";
+
+/// a freshly generated dungeon sized for the player's current difficulty, in the chosen maze style.
+pub fn generated_level(data: &GameData) -> MazeScene {
+    MazeScene::generated(
+        data.difficulty.maze_width,
+        data.difficulty.maze_height,
+        data.settings.maze_algorithm,
+        data.level_seed(),
+    )
+    .with_floors(data.settings.dungeon_floors)
+}
 
 /// A start screen or menu screen scene
 /// A start screen or menu screen scene
@@ -22,7 +46,7 @@ impl Scene for TitleScene {
             // Button rectangle: centered in bottom half (480-960)
             let button_rect = Rectangle::new(490.0, 645.0, 300.0, 150.0);
             if check_collision_point_rect(&click, &button_rect) {
-                return SceneSwitch::Push(Box::new(MenuScene));
+                return SceneSwitch::Push(Box::new(MenuScene::new()));
             }
         }
         
@@ -34,7 +58,7 @@ impl Scene for TitleScene {
     }
 
     fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
-        d.clear_background(Color::WHITE);
+        d.clear_background(data.settings.theme.background);
         
         // Draw title: centered in top half (0-480)
         d.draw_text("Dungeon Diver", 385, 215, 70, Color::BLACK);
@@ -47,51 +71,383 @@ impl Scene for TitleScene {
     fn on_exit(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
 }
 
-pub struct MenuScene;
+/// The entries of the main menu, in the order they are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    Play,
+    TimeTrial,
+    Settings,
+    HighScores,
+    Editor,
+    /// watch the last generated dungeon played, from [`REPLAY_PATH`].
+    Replay,
+    Quit,
+}
 
-impl Scene for MenuScene {
-    fn on_enter(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
+impl MenuItem {
+    pub const ALL: [MenuItem; 7] = [MenuItem::Play, MenuItem::TimeTrial, MenuItem::Settings, MenuItem::HighScores, MenuItem::Editor, MenuItem::Replay, MenuItem::Quit];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MenuItem::Play => "Play",
+            MenuItem::TimeTrial => "Time Trial",
+            MenuItem::Settings => "Settings",
+            MenuItem::HighScores => "High Scores",
+            MenuItem::Editor => "Map Editor",
+            MenuItem::Replay => "Watch Replay",
+            MenuItem::Quit => "Quit",
+        }
+    }
+}
 
-    fn handle_input(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) -> SceneSwitch {
+// size of the maze behind the main menu, in cells, and of its cells in pixels
+const PREVIEW_WIDTH: usize = 31;
+const PREVIEW_HEIGHT: usize = 23;
+const PREVIEW_TILE: i32 = 24;
+// how fast the ghost walks the preview maze, in cells per second, and how long it waits at the exit
+const PREVIEW_SPEED: f32 = 12.0;
+const PREVIEW_PAUSE: f32 = 2.0;
+
+/// A small maze drawn faintly behind the main menu, with a ghost walking the way from its start to
+/// its exit. Once the ghost gets there a new maze is generated.
+struct MenuPreview {
+    /// where each new preview maze comes from.
+    rng: StdRng,
+    grid: Vec<Vec<CellType>>,
+    path: Vec<(usize, usize)>,
+    /// cells along `path` the ghost has walked, counting on past the end while it waits at the exit.
+    progress: f32,
+}
 
-        if _rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
-            let click = _rl.get_mouse_position();
-            let rectangle = Rectangle::new(200.0, 200.0, 150.0, 50.0);
-            if  check_collision_point_rect(&click, &rectangle) {
-                println!("clicked on stage");
-                return SceneSwitch::Push(Box::new(MazeScene::from_map("assets/maps/mapTest.json".to_string())))
+impl MenuPreview {
+    fn new(mut rng: StdRng) -> Self {
+        let (width, height) = maze_size!(PREVIEW_WIDTH, PREVIEW_HEIGHT);
+        let grid = generate_maze(width, height, &mut rng);
+        let find = |wanted: CellType| {
+            grid.iter()
+                .enumerate()
+                .find_map(|(y, row)| row.iter().position(|c| *c == wanted).map(|x| (x, y)))
+        };
+        let path = match (find(CellType::Start), find(CellType::Exit)) {
+            (Some(start), Some(exit)) => solve_astar(&grid, start, exit).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        Self { rng, grid, path, progress: 0.0 }
+    }
 
+    fn update(&mut self, dt: f32) {
+        self.progress += dt * PREVIEW_SPEED;
+        if self.progress >= self.path.len() as f32 + PREVIEW_PAUSE * PREVIEW_SPEED {
+            *self = Self::new(self.rng.clone());
+        }
+    }
 
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &GameData) {
+        let left = (data.screen_width - PREVIEW_WIDTH as i32 * PREVIEW_TILE) / 2;
+        let top = (data.screen_height - PREVIEW_HEIGHT as i32 * PREVIEW_TILE) / 2;
+        let center = |(x, y): (usize, usize)| {
+            Vector2::new(
+                (left + x as i32 * PREVIEW_TILE + PREVIEW_TILE / 2) as f32,
+                (top + y as i32 * PREVIEW_TILE + PREVIEW_TILE / 2) as f32,
+            )
+        };
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if *cell == CellType::Wall {
+                    let (px, py) = (left + x as i32 * PREVIEW_TILE, top + y as i32 * PREVIEW_TILE);
+                    d.draw_rectangle(px, py, PREVIEW_TILE, PREVIEW_TILE, Color::LIGHTGRAY.alpha(0.35));
+                }
             }
         }
-        
+
+        let walked = (self.progress as usize).min(self.path.len().saturating_sub(1));
+        for pair in self.path[..self.path.len().min(walked + 1)].windows(2) {
+            d.draw_line_ex(center(pair[0]), center(pair[1]), 4.0, Color::SKYBLUE.alpha(0.4));
+        }
+        if let Some(&cell) = self.path.get(walked) {
+            d.draw_circle_v(center(cell), PREVIEW_TILE as f32 * 0.35, Color::SKYBLUE.alpha(0.7));
+        }
+    }
+}
+
+/// The main menu. Up/down (or W/S) moves the highlight and Enter activates the selected item.
+pub struct MenuScene {
+    selected: usize,
+    /// the maze behind the menu, while the menu preview setting is on.
+    preview: Option<MenuPreview>,
+}
+
+impl MenuScene {
+    pub fn new() -> Self {
+        Self { selected: 0, preview: None }
+    }
+
+    pub fn selected_item(&self) -> MenuItem {
+        MenuItem::ALL[self.selected]
+    }
+
+    /// move the highlight down one item, wrapping around to the top.
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % MenuItem::ALL.len();
+    }
+
+    /// move the highlight up one item, wrapping around to the bottom.
+    pub fn select_previous(&mut self) {
+        self.selected = (self.selected + MenuItem::ALL.len() - 1) % MenuItem::ALL.len();
+    }
+
+    /// the scene switch for the currently selected item.
+    pub fn activate(&self, data: &mut GameData) -> SceneSwitch {
+        match self.selected_item() {
+            MenuItem::Play => {
+                data.reset();
+                // play the hand-made map when it is installed, otherwise a freshly generated dungeon
+                let maze = if Path::new(MAP_PATH).exists() {
+                    MazeScene::from_map(MAP_PATH.to_string())
+                } else {
+                    generated_level(data)
+                };
+                SceneSwitch::FadePush(Box::new(LevelIntroScene::new(maze)))
+            }
+            MenuItem::TimeTrial => {
+                data.start_time_trial();
+                SceneSwitch::FadePush(Box::new(generated_level(data)))
+            }
+            MenuItem::Settings => SceneSwitch::Push(Box::new(SettingsScene::new())),
+            MenuItem::HighScores => SceneSwitch::Push(Box::new(HighScoresScene)),
+            MenuItem::Editor => SceneSwitch::Push(Box::new(MazeEditorScene::new(EDITOR_WIDTH, EDITOR_HEIGHT))),
+            MenuItem::Replay => match load_replay(REPLAY_PATH) {
+                Ok(replay) => SceneSwitch::Push(Box::new(ReplayPlayer::new(replay))),
+                Err(e) => {
+                    debug_log!("Failed to load {}: {}", REPLAY_PATH, e);
+                    SceneSwitch::None
+                }
+            },
+            MenuItem::Quit => SceneSwitch::Quit,
+        }
+    }
+}
+
+impl Default for MenuScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for MenuScene {
+    fn on_enter(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
+
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_DOWN) || _rl.is_key_pressed(KeyboardKey::KEY_S) {
+            self.select_next();
+        }
+        if _rl.is_key_pressed(KeyboardKey::KEY_UP) || _rl.is_key_pressed(KeyboardKey::KEY_W) {
+            self.select_previous();
+        }
+        if _rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return self.activate(data);
+        }
+        // hidden developer scene
+        let ctrl = _rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || _rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        let shift = _rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || _rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+        if ctrl && shift && _rl.is_key_pressed(KeyboardKey::KEY_B) {
+            return SceneSwitch::Push(Box::new(PathBenchmarkScene::new()));
+        }
+
         SceneSwitch::None
     }
 
     fn update(&mut self, _dt: f32, _data: &mut GameData) -> SceneSwitch {
+        if !_data.settings.menu_preview {
+            self.preview = None;
+        } else if let Some(preview) = self.preview.as_mut() {
+            preview.update(_dt);
+        } else {
+            self.preview = Some(MenuPreview::new(StdRng::seed_from_u64(_data.world_seed)));
+        }
         SceneSwitch::None
 
     }
 
     fn draw(&self, d: &mut RaylibDrawHandle, _data: &mut GameData) {
-        d.clear_background(Color::WHITE);
-        d.draw_text("Dungeon Stages", 450, 95, 50, Color::BLACK);
-        d.draw_rectangle(200, 200, 150, 50, Color::GREEN);
-        d.draw_text("Stage I", 235, 215, 20, Color::WHEAT);
+        d.clear_background(_data.settings.theme.background);
+        if let Some(preview) = &self.preview {
+            preview.draw(d, _data);
+        }
+        d.draw_text("Main Menu", 520, 95, 50, Color::BLACK);
+
+        for (i, item) in MenuItem::ALL.iter().enumerate() {
+            let y = 230 + i as i32 * 70;
+            let color = if i == self.selected { Color::GREEN } else { Color::LIGHTGRAY };
+            d.draw_rectangle(490, y, 300, 60, color);
+            d.draw_text(item.label(), 510, y + 18, 25, Color::BLACK);
+        }
+
+        d.draw_text("Up/Down to choose, Enter to select", 440, 740, 20, Color::GRAY);
     }
 
     fn on_exit(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
 }
 
 
+/// Game settings. Up/down chooses a setting, Enter changes it and Esc or Backspace goes back to the menu.
+pub struct SettingsScene {
+    selected: usize,
+}
+
+impl SettingsScene {
+    pub fn new() -> Self {
+        Self { selected: 0 }
+    }
+}
+
+impl Default for SettingsScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for SettingsScene {
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) || _rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            return SceneSwitch::Pop;
+        }
+
+        let count = SettingItem::ALL.len();
+        if _rl.is_key_pressed(KeyboardKey::KEY_DOWN) || _rl.is_key_pressed(KeyboardKey::KEY_S) {
+            self.selected = (self.selected + 1) % count;
+        }
+        if _rl.is_key_pressed(KeyboardKey::KEY_UP) || _rl.is_key_pressed(KeyboardKey::KEY_W) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if _rl.is_key_pressed(KeyboardKey::KEY_ENTER) || _rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+            SettingItem::ALL[self.selected].toggle(data);
+        }
+
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.clear_background(data.settings.theme.background);
+        d.draw_text("Settings", 540, 95, 50, Color::BLACK);
+
+        for (i, item) in SettingItem::ALL.iter().enumerate() {
+            let y = 160 + i as i32 * 30;
+            let color = if i == self.selected { Color::DARKGREEN } else { Color::BLACK };
+            d.draw_text(item.label(), 340, y, 25, color);
+            d.draw_text(&item.value(data), 840, y, 25, color);
+        }
+
+        d.draw_text("Enter to change, Esc to go back.", 460, 800, 20, Color::GRAY);
+    }
+}
+
+
+/// Shows the best score reached this session. Esc or Backspace goes back to the menu.
+pub struct HighScoresScene;
+
+impl Scene for HighScoresScene {
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) || _rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            return SceneSwitch::Pop;
+        }
+
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.clear_background(data.settings.theme.background);
+        d.draw_text("High Scores", 500, 95, 50, Color::BLACK);
+        let message = format!("Best score: {}", data.high_score);
+        d.draw_text(message.as_str(), 540, 250, 25, Color::BLACK);
+        d.draw_text("Press Esc to go back.", 510, 640, 20, Color::GRAY);
+    }
+}
+
+
 /// A win screen scene
-pub struct WinScene;
+pub struct WinScene {
+    /// whether the level was finished without stepping on any cell twice, which earns a bonus.
+    no_backtrack: bool,
+    bonus: u32,
+    /// the level's save slot key and the star objectives met on it, if it has any.
+    objectives: Option<(String, StarObjectives)>,
+    /// whether the stars beat the best on record for the level.
+    new_best: bool,
+    /// the game is running fast enough to offer a bigger maze next.
+    offer_bigger_maze: bool,
+    /// how many times an enemy noticed the player, if the level keeps count.
+    enemies_alerted: Option<u32>,
+    /// this win earned the Perfect Stealth achievement.
+    perfect_stealth_unlocked: bool,
+}
+
+impl WinScene {
+    pub fn new(no_backtrack: bool) -> Self {
+        Self {
+            no_backtrack,
+            bonus: 0,
+            objectives: None,
+            new_best: false,
+            offer_bigger_maze: false,
+            enemies_alerted: None,
+            perfect_stealth_unlocked: false,
+        }
+    }
+
+    /// show the stars earned on `level` and keep them in the save slot if they are a new best.
+    pub fn with_objectives(mut self, level: String, objectives: StarObjectives) -> Self {
+        self.objectives = Some((level, objectives));
+        self
+    }
+
+    /// show the stealth rating for a level where `enemies_alerted` enemies noticed the player.
+    pub fn with_stealth(mut self, enemies_alerted: u32) -> Self {
+        self.enemies_alerted = Some(enemies_alerted);
+        self
+    }
+}
+
+// A five-pointed star, gold if it was earned and grey if not.
+fn draw_star(d: &mut RaylibDrawHandle, center: Vector2, radius: f32, earned: bool) {
+    let color = if earned { Color::GOLD } else { Color::LIGHTGRAY };
+    let inner = radius * 0.4;
+    let point = |angle: f32, r: f32| center + Vector2::new(angle.to_radians().cos(), angle.to_radians().sin()) * r;
+    for i in 0..5 {
+        let angle = -90.0 + i as f32 * 72.0;
+        d.draw_triangle(point(angle, radius), point(angle - 36.0, inner), point(angle + 36.0, inner), color);
+    }
+    d.draw_poly(center, 5, inner, -54.0, color);
+}
 
 impl Scene for WinScene {
-    fn on_enter(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
+    fn on_enter(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) {
+        // the bonus uses the streak from before this win, like the level bonus does
+        if self.no_backtrack {
+            self.bonus = data.no_backtrack_bonus();
+            data.add_points(self.bonus);
+        }
+        data.win_streak += 1;
+        if let Some((level, objectives)) = &self.objectives {
+            self.new_best = data.record_stars(level, objectives.stars());
+        }
+        if let Some(enemies_alerted) = self.enemies_alerted {
+            self.perfect_stealth_unlocked = data.record_stealth(enemies_alerted);
+        }
+        data.save_progress();
+        self.offer_bigger_maze = data.scale_for_performance();
+    }
 
     fn handle_input(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
-
+        // Enter carries the run on into the next level
+        if _rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            _data.advance_level();
+            return SceneSwitch::FadeReplace(Box::new(LevelIntroScene::new(generated_level(_data))));
+        }
+        if self.offer_bigger_maze && _rl.is_key_pressed(KeyboardKey::KEY_G) {
+            _data.grow_for_performance();
+            self.offer_bigger_maze = false;
+        }
         
         if _rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
             let click = _rl.get_mouse_position();
@@ -113,19 +469,115 @@ impl Scene for WinScene {
     }
 
     fn draw(&self, d: &mut RaylibDrawHandle, _data: &mut GameData) {
-        d.clear_background(Color::WHITE);
+        d.clear_background(_data.settings.theme.background);
         
         d.draw_rectangle(200, 200, 300, 150, Color::GREEN);
         d.draw_text("Win", 210, 205, 20, Color::BLACK);
         let message = format!("Final score: {}", _data.points);
         d.draw_text(message.as_str(), 210, 225, 20, Color::BLACK);
-        d.draw_text("Click to quit.", 210, 250, 20, Color::BEIGE);
+        let streak = format!("Win streak: {} (x{:.2})", _data.win_streak, _data.streak_multiplier());
+        d.draw_text(streak.as_str(), 210, 250, 20, Color::BLACK);
+        d.draw_text("Click to quit.", 210, 275, 20, Color::BEIGE);
+        d.draw_text("Enter: next level", 210, 300, 20, Color::DARKGREEN);
+        d.draw_text(&format!("World seed: {}", _data.world_seed), 210, 325, 20, Color::DARKGRAY);
+
+        if self.bonus > 0 {
+            let message = format!("No Backtrack! +{}", self.bonus);
+            d.draw_text(message.as_str(), 200, 380, 40, Color::ORANGE);
+        }
+
+        if let Some((_, objectives)) = &self.objectives {
+            let goals = [
+                (objectives.completed, "Complete"),
+                (objectives.within_par, "Within par"),
+                (objectives.all_coins, "All coins"),
+            ];
+            for (i, (met, label)) in goals.into_iter().enumerate() {
+                let x = 250 + i as i32 * 130;
+                draw_star(d, Vector2::new(x as f32, 470.0), 35.0, met);
+                let width = d.measure_text(label, 20);
+                d.draw_text(label, x - width / 2, 515, 20, if met { Color::BLACK } else { Color::GRAY });
+            }
+            if self.new_best {
+                d.draw_text("New best!", 600, 460, 25, Color::ORANGE);
+            }
+        }
+
+        if let Some(enemies_alerted) = self.enemies_alerted {
+            let rank = StealthRank::from_alerts(enemies_alerted);
+            let message = format!("Stealth: {} ({} enemies alerted)", rank.label(), enemies_alerted);
+            d.draw_text(message.as_str(), 200, 620, 25, Color::DARKPURPLE);
+            if self.perfect_stealth_unlocked {
+                d.draw_text("Achievement unlocked: Perfect Stealth!", 200, 655, 25, Color::ORANGE);
+            }
+        }
+
+        if self.offer_bigger_maze {
+            d.draw_text("The game is running smoothly. G: bigger maze next level", 200, 580, 20, Color::DARKBLUE);
+        }
     }
 
     fn on_exit(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
 }      
 
 
+/// Shown when the player runs out of health. Ends the win streak.
+pub struct GameOverScene;
+
+impl Scene for GameOverScene {
+    fn on_enter(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) {
+        data.win_streak = 0;
+        data.save_progress();
+    }
+
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ENTER) || _rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            return SceneSwitch::Pop;
+        }
+
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.clear_background(Color::BLACK);
+
+        d.draw_text("Game Over", 460, 300, 70, Color::RED);
+        let message = format!("Final score: {}", data.points);
+        d.draw_text(message.as_str(), 540, 400, 25, Color::WHITE);
+        d.draw_text("Press Enter to return to the menu.", 450, 460, 20, Color::GRAY);
+    }
+}
+
+
+/// The end of a time trial, once the clock runs out or the player dies. Enter goes back to the menu.
+pub struct TimeTrialSummaryScene;
+
+impl Scene for TimeTrialSummaryScene {
+    fn on_enter(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) {
+        data.time_trial = None;
+    }
+
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ENTER) || _rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            return SceneSwitch::Pop;
+        }
+
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.clear_background(data.settings.theme.background);
+
+        d.draw_text("Time's Up!", 470, 250, 70, Color::BLACK);
+        let cleared = format!("Mazes cleared: {}", data.mazes_cleared);
+        d.draw_text(cleared.as_str(), 520, 370, 30, Color::DARKGREEN);
+        let message = format!("Final score: {}", data.points);
+        d.draw_text(message.as_str(), 540, 420, 25, Color::BLACK);
+        d.draw_text("Press Enter to return to the menu.", 450, 480, 20, Color::GRAY);
+    }
+}
+
+
 pub struct PauseScene;
 
 impl Scene for PauseScene {
@@ -155,7 +607,7 @@ impl Scene for PauseScene {
     }
 
     fn draw(&self, d: &mut RaylibDrawHandle, _data: &mut GameData) {
-        d.clear_background(Color::WHITE);
+        d.clear_background(_data.settings.theme.background);
         
         d.draw_rectangle(200, 200, 300, 150, Color::GRAY);
         d.draw_text("Paused", 210, 205, 20, Color::WHITE);
@@ -165,4 +617,59 @@ impl Scene for PauseScene {
     }
 
     fn on_exit(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
-}      
\ No newline at end of file
+}      
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_wraps_around_at_both_ends() {
+        let mut menu = MenuScene::new();
+        menu.select_previous();
+        assert_eq!(menu.selected_item(), MenuItem::Quit);
+        menu.select_next();
+        assert_eq!(menu.selected_item(), MenuItem::Play);
+    }
+
+    #[test]
+    fn play_starts_a_fresh_run_in_a_new_scene() {
+        let mut data = GameData::new(800, 600);
+        data.level = 4;
+        data.points = 300;
+        let menu = MenuScene::new();
+        let SceneSwitch::FadePush(scene) = menu.activate(&mut data) else { panic!("Play did not push a scene") };
+        // the maze is shown from above first, or started straight away
+        let names = [std::any::type_name::<LevelIntroScene>(), std::any::type_name::<MazeScene>()];
+        assert!(names.contains(&scene.name()), "Play pushed {}", scene.name());
+        assert_eq!(data.level, 1);
+        assert_eq!(data.points, 0);
+    }
+
+    #[test]
+    fn each_item_opens_its_own_scene() {
+        let mut menu = MenuScene::new();
+        let mut data = GameData::new(800, 600);
+        let expected = [
+            (MenuItem::TimeTrial, std::any::type_name::<MazeScene>()),
+            (MenuItem::Settings, std::any::type_name::<SettingsScene>()),
+            (MenuItem::HighScores, std::any::type_name::<HighScoresScene>()),
+            (MenuItem::Editor, std::any::type_name::<MazeEditorScene>()),
+        ];
+        for (item, name) in expected {
+            while menu.selected_item() != item {
+                menu.select_next();
+            }
+            match menu.activate(&mut data) {
+                SceneSwitch::Push(scene) | SceneSwitch::FadePush(scene) => assert_eq!(scene.name(), name, "{:?}", item),
+                _ => panic!("{:?} did not open a scene", item),
+            }
+        }
+    }
+
+    #[test]
+    fn quit_quits() {
+        let mut menu = MenuScene::new();
+        menu.select_previous();
+        assert!(matches!(menu.activate(&mut GameData::new(800, 600)), SceneSwitch::Quit));
+    }
+}
@@ -0,0 +1,101 @@
+//! Dungeons of several floors stacked on top of each other.
+//!
+//! Each floor is its own maze and ladders join one floor to the next. The player starts on floor 0
+//! and only the last floor has an exit.
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::maze_gen::{farthest_cell, first_walkable, generate, CellType, FlatGrid, LadderDirection, MazeAlgorithm};
+
+/// A ladder between two floors: the cell it stands on at each end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FloorConnection {
+    pub from_floor: usize,
+    pub from_cell: (usize, usize),
+    pub to_floor: usize,
+    pub to_cell: (usize, usize),
+}
+
+impl FloorConnection {
+    /// Where climbing from `cell` on `floor` takes the player, if this ladder stands there.
+    pub fn other_end(&self, floor: usize, cell: (usize, usize)) -> Option<(usize, (usize, usize))> {
+        if (floor, cell) == (self.from_floor, self.from_cell) {
+            Some((self.to_floor, self.to_cell))
+        } else if (floor, cell) == (self.to_floor, self.to_cell) {
+            Some((self.from_floor, self.from_cell))
+        } else {
+            None
+        }
+    }
+}
+
+/// The floors of a dungeon, each indexed `[y][x]`, and the ladders between them.
+pub struct MultiFloorMaze {
+    pub floors: Vec<Vec<Vec<CellType>>>,
+    pub connections: Vec<FloorConnection>,
+}
+
+impl MultiFloorMaze {
+    /// A dungeon of `floor_count` backtracker mazes from one seed, see [`MultiFloorMaze::generate_with`].
+    pub fn generate(floor_count: usize, width: usize, height: usize, seed: u64) -> Self {
+        Self::generate_with(MazeAlgorithm::Backtracker, floor_count, width, height, seed)
+    }
+
+    /// Generate `floor_count` floors (at least one) with the given algorithm. Floor `i` uses the
+    /// seed plus `i`. The ladder down from each floor stands on the cell furthest from where the
+    /// player arrived that is open on the floor below too, and the ladder back up is on the same
+    /// cell of that floor. Every ladder and the exit are reachable from where the player arrives on
+    /// their floor, so there is always a way from the start to the exit.
+    pub fn generate_with(algorithm: MazeAlgorithm, floor_count: usize, width: usize, height: usize, seed: u64) -> Self {
+        // at least two cells per floor, so a ladder never lands on the start, and at least two rows of
+        // them, or a floor is one straight corridor
+        let (width, height) = (width.max(5), height.max(5));
+        let mut floors: Vec<Vec<Vec<CellType>>> = (0..floor_count.max(1) as u64)
+            .map(|i| generate(algorithm, width, height, &mut StdRng::seed_from_u64(seed.wrapping_add(i))))
+            .collect();
+        let mut connections = Vec::new();
+
+        let mut arrival = floors[0]
+            .iter()
+            .enumerate()
+            .find_map(|(y, row)| row.iter().position(|c| *c == CellType::Start).map(|x| (x, y)))
+            .or(first_walkable(&floors[0]))
+            .unwrap_or((1, 1));
+        for (i, floor) in floors.iter_mut().enumerate() {
+            for cell in floor.iter_mut().flatten() {
+                if *cell == CellType::Exit || (i > 0 && *cell == CellType::Start) {
+                    *cell = CellType::Path;
+                }
+            }
+        }
+
+        for i in 0..floors.len() - 1 {
+            let upper = FlatGrid::from_rows(&floors[i]);
+            let distances = upper.distances_from(arrival);
+            let lower = FlatGrid::from_rows(&floors[i + 1]);
+            let ladder = distances
+                .iter()
+                .enumerate()
+                .filter(|(j, d)| d.is_some_and(|d| d > 0) && upper.cells[*j] == CellType::Path && lower.cells[*j].is_walkable())
+                .max_by_key(|(_, d)| **d)
+                .map(|(j, _)| (j % lower.width, j / lower.width))
+                .unwrap_or(arrival);
+
+            floors[i][ladder.1][ladder.0] = CellType::Ladder(LadderDirection::Down);
+            floors[i + 1][ladder.1][ladder.0] = CellType::Ladder(LadderDirection::Up);
+            connections.push(FloorConnection { from_floor: i, from_cell: ladder, to_floor: i + 1, to_cell: ladder });
+            arrival = ladder;
+        }
+
+        let last = floors.len() - 1;
+        let (exit_x, exit_y) = farthest_cell(&floors[last], arrival);
+        floors[last][exit_y][exit_x] = CellType::Exit;
+
+        Self { floors, connections }
+    }
+
+    /// Where climbing from `cell` on `floor` takes the player, if a ladder stands there.
+    pub fn connection_from(&self, floor: usize, cell: (usize, usize)) -> Option<(usize, (usize, usize))> {
+        self.connections.iter().find_map(|connection| connection.other_end(floor, cell))
+    }
+}
@@ -0,0 +1,65 @@
+//! Flavour text about what the player just did, shown one line at a time in a panel near the bottom
+//! of the maze screen.
+use std::collections::VecDeque;
+
+use raylib::prelude::*;
+
+/// seconds each line stays up before the next one in the queue replaces it.
+pub const NARRATION_TIME: f32 = 3.0;
+// lines waiting behind the current one beyond this are dropped, so the narrator never falls far behind
+const MAX_QUEUED: usize = 3;
+
+#[derive(Default)]
+pub struct Narrator {
+    /// the line showing, empty when the narrator is quiet.
+    pub current_text: String,
+    /// seconds the current line has left.
+    pub display_timer: f32,
+    pub text_queue: VecDeque<String>,
+}
+
+impl Narrator {
+    /// Queue a line, shown straight away if the narrator is quiet. The same line is never queued
+    /// twice in a row.
+    pub fn say(&mut self, text: &str) {
+        let last = self.text_queue.back().unwrap_or(&self.current_text);
+        if last == text || self.text_queue.len() >= MAX_QUEUED {
+            return;
+        }
+        self.text_queue.push_back(text.to_string());
+        if self.current_text.is_empty() {
+            self.next();
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.current_text.is_empty() {
+            return;
+        }
+        self.display_timer -= dt;
+        if self.display_timer <= 0.0 {
+            self.next();
+        }
+    }
+
+    // show the next queued line, or go quiet if there is none
+    fn next(&mut self) {
+        self.current_text = self.text_queue.pop_front().unwrap_or_default();
+        self.display_timer = NARRATION_TIME;
+    }
+
+    /// The current line on a see-through black bar across the screen with its top at `y`, slanted
+    /// a little by drawing it over a shadow shifted sideways.
+    pub fn draw(&self, d: &mut RaylibDrawHandle, screen_width: i32, y: i32) {
+        if self.current_text.is_empty() {
+            return;
+        }
+        // fade out over the last half second
+        let alpha = (self.display_timer / 0.5).clamp(0.0, 1.0);
+        d.draw_rectangle(0, y, screen_width, 34, Color::BLACK.alpha(0.6 * alpha));
+        let width = d.measure_text(&self.current_text, 20);
+        let x = (screen_width - width) / 2;
+        d.draw_text(&self.current_text, x + 2, y + 9, 20, Color::DARKGRAY.alpha(alpha));
+        d.draw_text(&self.current_text, x, y + 7, 20, Color::BEIGE.alpha(alpha));
+    }
+}
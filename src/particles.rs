@@ -0,0 +1,79 @@
+//! Short-lived particles for visual effects such as bursts when something happens in the maze.
+use raylib::prelude::*;
+use rand::Rng;
+
+pub struct Particle {
+    pub position: Vector2,
+    /// pixels per second.
+    pub velocity: Vector2,
+    pub color: Color,
+    pub size: f32,
+    /// seconds left to live, out of `lifetime`.
+    pub remaining: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// move the particle and burn some of its life. It slows down as it goes.
+    pub fn update(&mut self, dt: f32) {
+        self.position += self.velocity * dt;
+        self.velocity *= 1.0 - (3.0 * dt).min(1.0);
+        self.remaining -= dt;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.remaining > 0.0
+    }
+
+    /// draw the particle, fading out over its lifetime.
+    pub fn draw(&self, d: &mut impl RaylibDraw) {
+        let alpha = (self.remaining / self.lifetime).clamp(0.0, 1.0);
+        d.draw_circle_v(self.position, self.size, self.color.alpha(alpha));
+    }
+}
+
+/// `count` particles flying out of `center` in random directions at up to `speed` pixels per second.
+pub fn burst(center: Vector2, count: usize, speed: f32, color: Color, rng: &mut impl Rng) -> Vec<Particle> {
+    (0..count)
+        .map(|_| {
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let velocity = Vector2::new(angle.cos(), angle.sin()) * rng.random_range(speed * 0.3..=speed);
+            let lifetime = rng.random_range(0.4..0.8);
+            Particle {
+                position: center,
+                velocity,
+                color,
+                size: rng.random_range(2.0..4.0),
+                remaining: lifetime,
+                lifetime,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn a_burst_dies_out_after_its_lifetime() {
+        let mut particles = burst(Vector2::new(10.0, 10.0), 12, 100.0, Color::GOLD, &mut StdRng::seed_from_u64(3));
+        assert_eq!(particles.len(), 12);
+
+        for particle in particles.iter_mut() {
+            particle.update(0.3);
+        }
+        particles.retain(|p| p.is_alive());
+        assert_eq!(particles.len(), 12);
+        assert!(particles.iter().all(|p| p.position != Vector2::new(10.0, 10.0)));
+
+        // no particle lives longer than 0.8 seconds
+        for particle in particles.iter_mut() {
+            particle.update(0.5);
+        }
+        particles.retain(|p| p.is_alive());
+        assert!(particles.is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+//! Distance maps that enemies use to find their way around the maze.
+//!
+//! A distance map holds, for every cell of a grid indexed `[y][x]`, how many steps it is from the
+//! nearest source cell, or [`UNREACHABLE`]. It is worked out once with a breadth-first search and
+//! then looked up every time an enemy moves.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::maze_gen::CellType;
+use crate::utils::Direction;
+
+/// the distance of a cell that cannot be reached from any source.
+pub const UNREACHABLE: u32 = u32::MAX;
+
+/// Steps from the nearest of `sources` to every cell, walking only on `passable` cells.
+pub fn dijkstra_from(
+    grid: &[Vec<CellType>],
+    sources: impl IntoIterator<Item = (usize, usize)>,
+    passable: impl Fn(CellType) -> bool,
+) -> Vec<Vec<u32>> {
+    let mut distances: Vec<Vec<u32>> = grid.iter().map(|row| vec![UNREACHABLE; row.len()]).collect();
+    let mut queue = VecDeque::new();
+    for (x, y) in sources {
+        distances[y][x] = 0;
+        queue.push_back((x, y));
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[y][x];
+        for (nx, ny) in neighbours(grid, x, y) {
+            if passable(grid[ny][nx]) && distances[ny][nx] == UNREACHABLE {
+                distances[ny][nx] = distance + 1;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    distances
+}
+
+/// Steps from the exit to every cell through walkable cells. Everything is [`UNREACHABLE`] on a
+/// floor without an exit.
+pub fn dijkstra_from_exit(grid: &[Vec<CellType>]) -> Vec<Vec<u32>> {
+    let exits: Vec<(usize, usize)> = grid
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| row.iter().enumerate().filter(|(_, c)| **c == CellType::Exit).map(move |(x, _)| (x, y)))
+        .collect();
+    dijkstra_from(grid, exits, |cell| cell.is_walkable())
+}
+
+/// the component of a cell that cannot be walked on.
+pub const NO_COMPONENT: usize = usize::MAX;
+
+/// Which walkable cells can be walked between, worked out once so that each question after that is
+/// a lookup instead of a search. A component is a set of cells joined by walkable cells.
+pub struct ReachabilityGraph {
+    /// the cells of each component.
+    components: Vec<Vec<(usize, usize)>>,
+    /// the component of every cell, indexed `[y][x]`, or [`NO_COMPONENT`].
+    cell_component: Vec<Vec<usize>>,
+}
+
+impl ReachabilityGraph {
+    /// label every walkable cell of `grid` with its component, one breadth-first search per component.
+    pub fn build(grid: &[Vec<CellType>]) -> Self {
+        let mut cell_component: Vec<Vec<usize>> = grid.iter().map(|row| vec![NO_COMPONENT; row.len()]).collect();
+        let mut components = Vec::new();
+
+        for (y, row) in grid.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if !cell.is_walkable() || cell_component[y][x] != NO_COMPONENT {
+                    continue;
+                }
+                components.push(flood(grid, &mut cell_component, (x, y), components.len()));
+            }
+        }
+        Self { components, cell_component }
+    }
+
+    /// Note that `cell`, walkable until now, has been shut in `grid`. Its component splits into the
+    /// pieces still joined without it, searching only that component again.
+    pub fn shut(&mut self, grid: &[Vec<CellType>], (x, y): (usize, usize)) {
+        let old = self.component((x, y));
+        if old == NO_COMPONENT {
+            return;
+        }
+        let cells = std::mem::take(&mut self.components[old]);
+        for &(cx, cy) in &cells {
+            self.cell_component[cy][cx] = NO_COMPONENT;
+        }
+        // the first piece keeps the old id
+        let mut id = old;
+        for start in cells.into_iter().filter(|&cell| cell != (x, y)) {
+            if self.component(start) != NO_COMPONENT {
+                continue;
+            }
+            let piece = flood(grid, &mut self.cell_component, start, id);
+            if id == old {
+                self.components[old] = piece;
+            } else {
+                self.components.push(piece);
+            }
+            id = self.components.len();
+        }
+    }
+
+    /// Note that `cell` has been opened in `grid`, joining the components around it into one.
+    pub fn open(&mut self, grid: &[Vec<CellType>], (x, y): (usize, usize)) {
+        if self.component((x, y)) != NO_COMPONENT || !grid[y][x].is_walkable() {
+            return;
+        }
+        let mut around: Vec<usize> = neighbours(grid, x, y).map(|cell| self.component(cell)).filter(|&c| c != NO_COMPONENT).collect();
+        around.sort_unstable();
+        around.dedup();
+        // the biggest takes the others in
+        let id = match around.iter().copied().max_by_key(|&c| self.components[c].len()) {
+            Some(id) => id,
+            None => {
+                self.components.push(Vec::new());
+                self.components.len() - 1
+            }
+        };
+        for other in around.into_iter().filter(|&c| c != id) {
+            for (cx, cy) in std::mem::take(&mut self.components[other]) {
+                self.cell_component[cy][cx] = id;
+                self.components[id].push((cx, cy));
+            }
+        }
+        self.cell_component[y][x] = id;
+        self.components[id].push((x, y));
+    }
+
+    /// the component of the cell at `cell`, or [`NO_COMPONENT`] for a wall or a cell off the grid.
+    pub fn component(&self, (x, y): (usize, usize)) -> usize {
+        self.cell_component.get(y).and_then(|row| row.get(x)).copied().unwrap_or(NO_COMPONENT)
+    }
+
+    /// whether `b` can be walked to from `a`. Never true for a wall.
+    pub fn same_component(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        let component = self.component(a);
+        component != NO_COMPONENT && component == self.component(b)
+    }
+
+    /// the cells that can be walked to from `cell`, itself included. Empty for a wall.
+    pub fn reachable_from(&self, cell: (usize, usize)) -> &[(usize, usize)] {
+        self.components.get(self.component(cell)).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.components.iter().filter(|cells| !cells.is_empty()).count()
+    }
+}
+
+// Label the walkable cells joined to `start` that have no component yet with `id`, returning them.
+fn flood(grid: &[Vec<CellType>], cell_component: &mut [Vec<usize>], start: (usize, usize), id: usize) -> Vec<(usize, usize)> {
+    let mut cells = vec![start];
+    let mut queue = VecDeque::from([start]);
+    cell_component[start.1][start.0] = id;
+    while let Some((x, y)) = queue.pop_front() {
+        for (nx, ny) in neighbours(grid, x, y) {
+            if grid[ny][nx].is_walkable() && cell_component[ny][nx] == NO_COMPONENT {
+                cell_component[ny][nx] = id;
+                cells.push((nx, ny));
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    cells
+}
+
+/// Where an enemy at `from` steps to cut the player off: of the open neighbours that bring it
+/// closer to `player`, the one nearest the goal on `distances`. The player has to pass through
+/// there on their way out, so the enemy waits for them instead of running after them.
+/// `None` if no step brings it closer.
+pub fn intercept_step(
+    grid: &[Vec<CellType>],
+    distances: &[Vec<u32>],
+    from: (usize, usize),
+    player: (usize, usize),
+) -> Option<(usize, usize)> {
+    let gap = |(x, y): (usize, usize)| x.abs_diff(player.0) + y.abs_diff(player.1);
+    neighbours(grid, from.0, from.1)
+        .filter(|&(x, y)| grid[y][x].is_enemy_walkable() && gap((x, y)) < gap(from))
+        .min_by_key(|&(x, y)| distances[y][x])
+}
+
+/// The cells of a shortest walk from `from` to `to` over walkable cells, both ends included, found
+/// with A* guided by the Manhattan distance to `to`. `None` if `to` cannot be reached.
+pub fn solve_astar(grid: &[Vec<CellType>], from: (usize, usize), to: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+    let estimate = |(x, y): (usize, usize)| (x.abs_diff(to.0) + y.abs_diff(to.1)) as u32;
+    let mut cost: Vec<Vec<u32>> = grid.iter().map(|row| vec![UNREACHABLE; row.len()]).collect();
+    let mut came_from: Vec<Vec<Option<(usize, usize)>>> = grid.iter().map(|row| vec![None; row.len()]).collect();
+    let mut open = BinaryHeap::new();
+    cost[from.1][from.0] = 0;
+    open.push(Reverse((estimate(from), 0, from)));
+
+    while let Some(Reverse((_, steps, (x, y)))) = open.pop() {
+        if (x, y) == to {
+            let mut path = vec![to];
+            let mut current = to;
+            while let Some(previous) = came_from[current.1][current.0] {
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // a cheaper way here was found after this entry was queued
+        if steps > cost[y][x] {
+            continue;
+        }
+        for (nx, ny) in neighbours(grid, x, y) {
+            if grid[ny][nx].is_walkable() && steps + 1 < cost[ny][nx] {
+                cost[ny][nx] = steps + 1;
+                came_from[ny][nx] = Some((x, y));
+                open.push(Reverse((steps + 1 + estimate((nx, ny)), steps + 1, (nx, ny))));
+            }
+        }
+    }
+    None
+}
+
+// the cells next to (x, y) that are inside the grid
+fn neighbours(grid: &[Vec<CellType>], x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+    Direction::ALL.into_iter().filter_map(move |direction| {
+        let (dx, dy) = direction.delta();
+        let nx = usize::try_from(x as i32 + dx).ok()?;
+        let ny = usize::try_from(y as i32 + dy).ok()?;
+        (ny < grid.len() && nx < grid[ny].len()).then_some((nx, ny))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_gen::{generate_maze, ButtonId, FlatGrid};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::hint::black_box;
+    use std::time::{Duration, Instant};
+
+    fn position(grid: &[Vec<CellType>], wanted: CellType) -> (usize, usize) {
+        let flat = FlatGrid::from_rows(grid);
+        let i = flat.cells.iter().position(|c| *c == wanted).unwrap();
+        (i % flat.width, i / flat.width)
+    }
+
+    #[test]
+    fn astar_finds_a_shortest_route() {
+        for seed in 0..10 {
+            let grid = generate_maze(31, 21, &mut StdRng::seed_from_u64(seed));
+            let (start, exit) = (position(&grid, CellType::Start), position(&grid, CellType::Exit));
+            let path = solve_astar(&grid, start, exit).unwrap();
+            assert_eq!((path[0], path[path.len() - 1]), (start, exit));
+            assert_eq!(path.len() - 1, FlatGrid::from_rows(&grid).solution_length().unwrap());
+            assert_eq!(dijkstra_from_exit(&grid)[start.1][start.0] as usize, path.len() - 1);
+        }
+    }
+
+    #[test]
+    fn components_split_and_merge_across_gates() {
+        let mut grid: Vec<Vec<CellType>> = ["##########", "#S.=..=.E#", "##########"]
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| match c {
+                        '#' => CellType::Wall,
+                        '=' => CellType::LinkedWall(ButtonId(0)),
+                        'S' => CellType::Start,
+                        'E' => CellType::Exit,
+                        _ => CellType::Path,
+                    })
+                    .collect()
+            })
+            .collect();
+        let (start, middle, exit) = ((1, 1), (4, 1), (8, 1));
+        let mut graph = ReachabilityGraph::build(&grid);
+        assert_eq!(graph.component_count(), 3);
+        assert!(graph.same_component(start, (2, 1)));
+        assert!(!graph.same_component(start, middle));
+        assert!(!graph.same_component((3, 1), (3, 1)));
+
+        // opening the first gate joins the start to the middle, and the second the middle to the exit
+        grid[1][3] = CellType::Path;
+        graph.open(&grid, (3, 1));
+        assert_eq!(graph.component_count(), 2);
+        assert!(graph.same_component(start, middle));
+        assert!(!graph.same_component(start, exit));
+        grid[1][6] = CellType::Path;
+        graph.open(&grid, (6, 1));
+        assert_eq!(graph.component_count(), 1);
+        assert!(graph.same_component(start, exit));
+        assert_eq!(graph.reachable_from(exit).len(), 8);
+
+        // shutting the first again splits it back up, just as a fresh graph sees it
+        grid[1][3] = CellType::LinkedWall(ButtonId(0));
+        graph.shut(&grid, (3, 1));
+        let fresh = ReachabilityGraph::build(&grid);
+        assert_eq!(graph.component_count(), fresh.component_count());
+        assert!(graph.same_component(middle, exit));
+        assert!(!graph.same_component(start, middle));
+        assert_eq!(graph.reachable_from(start).len(), fresh.reachable_from(start).len());
+        assert_eq!(graph.reachable_from(exit).len(), 5);
+    }
+
+    #[test]
+    fn an_interceptor_waits_on_the_way_out() {
+        // the player at the west end, the exit in the east and the enemy in the middle of the room
+        let grid: Vec<Vec<CellType>> = ["#######", "#.....#", "#.....E", "#.....#", "#######"]
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| match c {
+                        '#' => CellType::Wall,
+                        'E' => CellType::Exit,
+                        _ => CellType::Path,
+                    })
+                    .collect()
+            })
+            .collect();
+        let distances = dijkstra_from_exit(&grid);
+        // west and south both close on the player, and the south step is nearer to the exit
+        assert_eq!(intercept_step(&grid, &distances, (3, 1), (1, 3)), Some((3, 2)));
+        assert_eq!(intercept_step(&grid, &distances, (1, 2), (1, 2)), None);
+    }
+
+    // run with `cargo test --release -- --ignored --nocapture` to see the timings
+    #[test]
+    #[ignore]
+    fn bench_interception_against_astar_on_61x61() {
+        const STEPS: usize = 2000;
+        let (mut intercept_time, mut astar_time) = (Duration::ZERO, Duration::ZERO);
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let grid = generate_maze(61, 61, &mut rng);
+            let open: Vec<(usize, usize)> = (0..61)
+                .flat_map(|y| (0..61).map(move |x| (x, y)))
+                .filter(|&(x, y)| grid[y][x].is_walkable())
+                .collect();
+            let pairs: Vec<((usize, usize), (usize, usize))> =
+                (0..STEPS).map(|_| (open[rng.random_range(0..open.len())], open[rng.random_range(0..open.len())])).collect();
+
+            // the distance map is worked out once per maze, as the scene does
+            let started = Instant::now();
+            let distances = dijkstra_from_exit(&grid);
+            for &(enemy, player) in &pairs {
+                black_box(intercept_step(&grid, &distances, enemy, player));
+            }
+            intercept_time += started.elapsed();
+
+            let started = Instant::now();
+            for &(enemy, player) in &pairs {
+                black_box(solve_astar(&grid, enemy, player).and_then(|path| path.get(1).copied()));
+            }
+            astar_time += started.elapsed();
+        }
+        println!("61x61, 20 seeds x {} steps: interception {:?}, A* chase {:?}", STEPS, intercept_time, astar_time);
+    }
+}
@@ -0,0 +1,58 @@
+//! Projectiles fired across the maze.
+//!
+//! A projectile flies in a straight line in pixel space. The maze scene converts its position
+//! to grid coordinates each frame to check for walls and the player.
+use raylib::prelude::*;
+
+use crate::entity::EntityId;
+use crate::utils::Direction;
+
+pub struct Projectile {
+    /// the default id until the maze registers the projectile.
+    pub id: EntityId,
+    /// the enemy that fired the projectile, if any.
+    pub owner: Option<EntityId>,
+    pub x: f32,
+    pub y: f32,
+    pub direction: Direction,
+    pub speed: f32,
+    pub damage: i32,
+    /// seconds left before the projectile fizzles out on its own.
+    pub lifetime: f32,
+}
+
+impl Projectile {
+    /// radius used both for drawing and for the hit test against the player.
+    pub const RADIUS: f32 = 6.0;
+
+    pub fn new(x: f32, y: f32, direction: Direction, speed: f32, damage: i32) -> Self {
+        Self {
+            id: EntityId::default(),
+            owner: None,
+            x,
+            y,
+            direction,
+            speed,
+            damage,
+            lifetime: 5.0,
+        }
+    }
+
+    /// move forward by one time step and burn some of the lifetime.
+    pub fn advance(&mut self, dt: f32) {
+        let step = self.direction.to_vector() * (self.speed * dt);
+        self.x += step.x;
+        self.y += step.y;
+        self.lifetime -= dt;
+    }
+
+    pub fn position(&self) -> Vector2 {
+        Vector2::new(self.x, self.y)
+    }
+
+    /// the grid cell the centre of the projectile is in. Can be negative once it leaves the map.
+    pub fn cell(&self, tile_size: i32) -> (i32, i32) {
+        let size = tile_size as f32;
+        ((self.x / size).floor() as i32, (self.y / size).floor() as i32)
+    }
+}
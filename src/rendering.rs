@@ -0,0 +1,127 @@
+//! Full screen post-processing effects.
+//!
+//! With the `shaders` feature enabled, a frame is drawn into an off-screen texture first and that
+//! texture is then drawn to the screen through the fragment shader of the chosen effect.
+#[cfg(feature = "shaders")]
+use raylib::core::error::Error;
+#[cfg(feature = "shaders")]
+use raylib::prelude::*;
+#[cfg(feature = "shaders")]
+use std::path::Path;
+
+#[cfg(feature = "shaders")]
+use crate::game_data::GameData;
+#[cfg(feature = "shaders")]
+use crate::settings::Settings;
+
+/// A look applied to the whole screen after everything has been drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PostProcessEffect {
+    None,
+    /// darkened lines across the screen, like an old TV.
+    Scanlines,
+    /// scanlines on a curved screen with dark corners.
+    CRT,
+    /// the corners fade to black.
+    Vignette,
+}
+
+impl PostProcessEffect {
+    pub const ALL: [PostProcessEffect; 4] = [
+        PostProcessEffect::None,
+        PostProcessEffect::Scanlines,
+        PostProcessEffect::CRT,
+        PostProcessEffect::Vignette,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PostProcessEffect::None => "Off",
+            PostProcessEffect::Scanlines => "Scanlines",
+            PostProcessEffect::CRT => "CRT",
+            PostProcessEffect::Vignette => "Vignette",
+        }
+    }
+
+    /// the file the effect's fragment shader is normally loaded from.
+    pub fn default_path(&self) -> Option<&'static str> {
+        match self {
+            PostProcessEffect::None => None,
+            PostProcessEffect::Scanlines => Some("assets/shaders/scanlines.fs"),
+            PostProcessEffect::CRT => Some("assets/shaders/crt.fs"),
+            PostProcessEffect::Vignette => Some("assets/shaders/vignette.fs"),
+        }
+    }
+
+    /// the fragment shader built into the game, used when the file cannot be found.
+    pub fn builtin_source(&self) -> Option<&'static str> {
+        match self {
+            PostProcessEffect::None => None,
+            PostProcessEffect::Scanlines => Some(include_str!("../assets/shaders/scanlines.fs")),
+            PostProcessEffect::CRT => Some(include_str!("../assets/shaders/crt.fs")),
+            PostProcessEffect::Vignette => Some(include_str!("../assets/shaders/vignette.fs")),
+        }
+    }
+}
+
+/// The off-screen frame and the shader of the effect currently in use.
+#[cfg(feature = "shaders")]
+pub struct PostProcess {
+    target: RenderTexture2D,
+    width: i32,
+    height: i32,
+    shader: Option<(PostProcessEffect, Shader)>,
+}
+
+#[cfg(feature = "shaders")]
+impl PostProcess {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, width: i32, height: i32) -> Result<Self, Error> {
+        Ok(Self {
+            target: rl.load_render_texture(thread, width as u32, height as u32)?,
+            width,
+            height,
+            shader: None,
+        })
+    }
+
+    /// Load the shader for the effect picked in the settings if it is not the one already loaded.
+    /// The shader file named in the settings is used when it exists, otherwise the built-in one.
+    pub fn sync(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, settings: &Settings) {
+        let effect = settings.post_process;
+        if self.shader.as_ref().map(|(loaded, _)| *loaded) == Some(effect) {
+            return;
+        }
+        if effect == PostProcessEffect::None {
+            self.shader = None;
+            return;
+        }
+
+        let shader = match settings.shader_path(effect) {
+            Some(path) if Path::new(path).exists() => rl.load_shader(thread, None, Some(path)),
+            _ => rl.load_shader_from_memory(thread, None, effect.builtin_source()),
+        };
+        self.shader = Some((effect, shader));
+    }
+
+    /// Draw a frame with `scene`, through the current effect when there is one.
+    pub fn draw(&mut self, d: &mut RaylibDrawHandle, data: &mut GameData, scene: impl FnOnce(&mut RaylibDrawHandle, &mut GameData)) {
+        let (Some((_, shader)), Some(thread)) = (self.shader.as_mut(), data.thread.as_ref()) else {
+            scene(d, data);
+            return;
+        };
+
+        {
+            let mut frame = d.begin_texture_mode(thread, &mut self.target);
+            scene(&mut frame, data);
+        }
+
+        // render textures are stored upside down, so the source is flipped back
+        let mut d = d.begin_shader_mode(shader);
+        d.draw_texture_rec(
+            self.target.texture(),
+            Rectangle::new(0.0, 0.0, self.width as f32, -self.height as f32),
+            Vector2::zero(),
+            Color::WHITE,
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! Replays of a run, as bytes that can be sent to someone else and watched there.
+//!
+//! Layout (all integers little-endian):
+//!
+//! | bytes | contents                                                     |
+//! |-------|--------------------------------------------------------------|
+//! | 3     | magic `DDR`                                                  |
+//! | 1     | format version, currently 2                                  |
+//! | 8     | seed (`u64`)                                                 |
+//! | 16    | maze width, height, spawn safe radius, floors (`u32` each)   |
+//! | 1     | maze style, its place in [`MazeAlgorithm::ALL`]              |
+//! | 4     | number of inputs (`u32`)                                     |
+//! | 5 × n | each input: the frame it happened on (`u32`) and its code    |
+//!
+//! Input codes are 0 to 3 for a move north, south, east or west, 4 for a hint, 5 to 8 for a sprint
+//! north, south, east or west and 9 for creeping being turned on or off.
+use std::fmt;
+
+use raylib::prelude::*;
+
+use crate::difficulty::DifficultyParams;
+use crate::game_data::GameData;
+use crate::maze_gen::MazeAlgorithm;
+use crate::maze_scene::MazeScene;
+use crate::scenes::{Scene, SceneSwitch};
+use crate::utils::Direction;
+
+pub const MAGIC: &[u8; 3] = b"DDR";
+pub const VERSION: u8 = 2;
+const HEADER_LEN: usize = 4 + 8 + 16 + 1 + 4;
+const INPUT_LEN: usize = 5;
+/// seconds of play each frame of a replay moves the maze on by, whatever the watcher's frame rate,
+/// so the inputs land on the same moments they were made on.
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+/// where the replay of the last generated dungeon played is kept, and watched from the menu.
+pub const REPLAY_PATH: &str = "last_run.ddr";
+
+/// Something the player did, as a replay records it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputEvent {
+    Move(Direction),
+    Hint,
+    /// a move made holding shift.
+    Sprint(Direction),
+    /// creeping turned on or off.
+    Creep,
+}
+
+impl InputEvent {
+    fn code(&self) -> u8 {
+        match self {
+            InputEvent::Move(Direction::North) => 0,
+            InputEvent::Move(Direction::South) => 1,
+            InputEvent::Move(Direction::East) => 2,
+            InputEvent::Move(Direction::West) => 3,
+            InputEvent::Hint => 4,
+            InputEvent::Sprint(Direction::North) => 5,
+            InputEvent::Sprint(Direction::South) => 6,
+            InputEvent::Sprint(Direction::East) => 7,
+            InputEvent::Sprint(Direction::West) => 8,
+            InputEvent::Creep => 9,
+        }
+    }
+
+    fn from_code(code: u8) -> Result<Self, ReplayError> {
+        match code {
+            0 => Ok(InputEvent::Move(Direction::North)),
+            1 => Ok(InputEvent::Move(Direction::South)),
+            2 => Ok(InputEvent::Move(Direction::East)),
+            3 => Ok(InputEvent::Move(Direction::West)),
+            4 => Ok(InputEvent::Hint),
+            5 => Ok(InputEvent::Sprint(Direction::North)),
+            6 => Ok(InputEvent::Sprint(Direction::South)),
+            7 => Ok(InputEvent::Sprint(Direction::East)),
+            8 => Ok(InputEvent::Sprint(Direction::West)),
+            9 => Ok(InputEvent::Creep),
+            other => Err(ReplayError::BadInput(other)),
+        }
+    }
+}
+
+/// A run: the maze it was played on and every input, with the frame it happened on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayData {
+    pub seed: u64,
+    pub difficulty: DifficultyParams,
+    pub algorithm: MazeAlgorithm,
+    /// how many floors the dungeon had, see [`MazeScene::with_floors`].
+    pub floors: usize,
+    pub inputs: Vec<(u32, InputEvent)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// the data does not start with `DDR`.
+    BadMagic,
+    UnsupportedVersion(u8),
+    /// the data ends before all the inputs were read.
+    Truncated,
+    /// a code that is not an input.
+    BadInput(u8),
+    /// a maze style that is not in [`MazeAlgorithm::ALL`].
+    BadAlgorithm(u8),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::BadMagic => write!(f, "not a replay"),
+            ReplayError::UnsupportedVersion(v) => write!(f, "unsupported replay version {}", v),
+            ReplayError::Truncated => write!(f, "replay data is truncated"),
+            ReplayError::BadInput(code) => write!(f, "invalid input code {}", code),
+            ReplayError::BadAlgorithm(code) => write!(f, "invalid maze style {}", code),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Encode a replay as bytes for [`deserialise_replay`].
+pub fn serialise_replay(replay: &ReplayData) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + replay.inputs.len() * INPUT_LEN);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&replay.seed.to_le_bytes());
+    let difficulty = &replay.difficulty;
+    for value in [difficulty.maze_width, difficulty.maze_height, difficulty.spawn_safe_radius, replay.floors] {
+        bytes.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+    bytes.push(MazeAlgorithm::ALL.iter().position(|a| *a == replay.algorithm).unwrap_or(0) as u8);
+    bytes.extend_from_slice(&(replay.inputs.len() as u32).to_le_bytes());
+    for (frame, input) in &replay.inputs {
+        bytes.extend_from_slice(&frame.to_le_bytes());
+        bytes.push(input.code());
+    }
+    bytes
+}
+
+/// Decode a replay written by [`serialise_replay`], perhaps on someone else's machine.
+pub fn deserialise_replay(bytes: &[u8]) -> Result<ReplayData, ReplayError> {
+    if bytes.len() < 4 || &bytes[..3] != MAGIC {
+        return Err(ReplayError::BadMagic);
+    }
+    if bytes[3] != VERSION {
+        return Err(ReplayError::UnsupportedVersion(bytes[3]));
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(ReplayError::Truncated);
+    }
+
+    let mut seed = [0; 8];
+    seed.copy_from_slice(&bytes[4..12]);
+    let difficulty = DifficultyParams {
+        maze_width: read_u32(bytes, 12) as usize,
+        maze_height: read_u32(bytes, 16) as usize,
+        spawn_safe_radius: read_u32(bytes, 20) as usize,
+    };
+    let floors = read_u32(bytes, 24) as usize;
+    let algorithm = *MazeAlgorithm::ALL.get(bytes[28] as usize).ok_or(ReplayError::BadAlgorithm(bytes[28]))?;
+    let count = read_u32(bytes, 29) as usize;
+    let packed = &bytes[HEADER_LEN..];
+    if packed.len() < count * INPUT_LEN {
+        return Err(ReplayError::Truncated);
+    }
+
+    let inputs = packed
+        .chunks_exact(INPUT_LEN)
+        .take(count)
+        .map(|input| Ok((read_u32(input, 0), InputEvent::from_code(input[4])?)))
+        .collect::<Result<_, _>>()?;
+    Ok(ReplayData { seed: u64::from_le_bytes(seed), difficulty, algorithm, floors, inputs })
+}
+
+/// Read a replay file, such as the one kept at [`REPLAY_PATH`].
+pub fn load_replay(path: &str) -> Result<ReplayData, Box<dyn std::error::Error>> {
+    Ok(deserialise_replay(&std::fs::read(path)?)?)
+}
+
+pub fn save_replay(path: &str, replay: &ReplayData) -> std::io::Result<()> {
+    std::fs::write(path, serialise_replay(replay))
+}
+
+/// Plays a replay back: the same maze from the same seed, with the recorded inputs fed to it on the
+/// frames they happened on instead of the keyboard, and every frame [`FRAME_TIME`] long. Moves go
+/// through the same cooldowns and penalties as the keyboard's. Esc stops watching.
+pub struct ReplayPlayer {
+    scene: MazeScene,
+    difficulty: DifficultyParams,
+    inputs: Vec<(u32, InputEvent)>,
+    /// the next input to play.
+    next: usize,
+    /// frames played so far.
+    frame: u32,
+    /// the watcher's own difficulty, put back once the replay is over.
+    saved_difficulty: Option<DifficultyParams>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: ReplayData) -> Self {
+        let scene = MazeScene::generated(replay.difficulty.maze_width, replay.difficulty.maze_height, replay.algorithm, replay.seed)
+            .with_floors(replay.floors);
+        Self {
+            scene,
+            difficulty: replay.difficulty,
+            inputs: replay.inputs,
+            next: 0,
+            frame: 0,
+            saved_difficulty: None,
+        }
+    }
+}
+
+impl Scene for ReplayPlayer {
+    fn on_enter(&mut self, rl: &mut RaylibHandle, data: &mut GameData) {
+        self.saved_difficulty = Some(std::mem::replace(&mut data.difficulty, self.difficulty));
+        self.scene.on_enter(rl, data);
+    }
+
+    fn handle_input(&mut self, rl: &mut RaylibHandle, _data: &mut GameData) -> SceneSwitch {
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return SceneSwitch::Pop;
+        }
+        SceneSwitch::None
+    }
+
+    fn update(&mut self, _dt: f32, data: &mut GameData) -> SceneSwitch {
+        while let Some(&(frame, input)) = self.inputs.get(self.next)
+            && frame <= self.frame
+        {
+            match input {
+                InputEvent::Move(direction) => {
+                    self.scene.request_move(0, direction, false, data);
+                }
+                InputEvent::Sprint(direction) => {
+                    self.scene.request_move(0, direction, true, data);
+                }
+                InputEvent::Hint => {
+                    self.scene.show_hint();
+                }
+                InputEvent::Creep => self.scene.toggle_creeping(),
+            }
+            self.next += 1;
+        }
+        self.frame += 1;
+        self.scene.update(FRAME_TIME, data)
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        self.scene.draw(d, data);
+        d.draw_text("Replay (Esc to stop)", 10, 50, 20, Color::MAROON);
+    }
+
+    fn on_exit(&mut self, rl: &mut RaylibHandle, data: &mut GameData) {
+        self.scene.on_exit(rl, data);
+        if let Some(difficulty) = self.saved_difficulty.take() {
+            data.difficulty = difficulty;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::seq::IndexedRandom;
+    use rand::SeedableRng;
+
+    const DIFFICULTY: DifficultyParams = DifficultyParams { maze_width: 21, maze_height: 15, spawn_safe_radius: 5 };
+
+    #[test]
+    fn a_replay_round_trips_through_bytes() {
+        let moves = [Direction::North, Direction::South, Direction::East, Direction::West];
+        let inputs = (0..12)
+            .map(|i| {
+                let input = match i % 6 {
+                    4 => InputEvent::Hint,
+                    5 => InputEvent::Creep,
+                    n if i > 6 => InputEvent::Sprint(moves[n as usize]),
+                    n => InputEvent::Move(moves[n as usize]),
+                };
+                (i * 7, input)
+            })
+            .collect();
+        let replay = ReplayData { seed: 0xDEAD_BEEF_1234, difficulty: DIFFICULTY, algorithm: MazeAlgorithm::Mirror, floors: 1, inputs };
+        let bytes = serialise_replay(&replay);
+        assert_eq!(bytes.len(), HEADER_LEN + 12 * INPUT_LEN);
+        assert_eq!(deserialise_replay(&bytes), Ok(replay));
+
+        assert_eq!(deserialise_replay(&bytes[..bytes.len() - 1]), Err(ReplayError::Truncated));
+        assert_eq!(deserialise_replay(b"DDX\x01"), Err(ReplayError::BadMagic));
+        let mut future = bytes.clone();
+        future[3] = VERSION + 1;
+        assert_eq!(deserialise_replay(&future), Err(ReplayError::UnsupportedVersion(VERSION + 1)));
+        let mut unknown_style = bytes.clone();
+        unknown_style[28] = MazeAlgorithm::ALL.len() as u8;
+        assert_eq!(deserialise_replay(&unknown_style), Err(ReplayError::BadAlgorithm(MazeAlgorithm::ALL.len() as u8)));
+    }
+
+    #[test]
+    fn playing_a_recorded_run_back_ends_where_it_did() {
+        let seed = 42;
+        let mut data = GameData::new(800, 600);
+        data.difficulty = DIFFICULTY;
+        data.settings.no_backtrack_challenge = true;
+        data.points = 10_000;
+
+        // record a wander, asking for a step in a random direction every other frame, sometimes
+        // sprinting, so the cooldown drops some of them
+        let mut scene = MazeScene::generated(DIFFICULTY.maze_width, DIFFICULTY.maze_height, MazeAlgorithm::DungeonRooms, seed).with_floors(1);
+        scene.build(&mut data);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut inputs = Vec::new();
+        for frame in 0..600 {
+            if frame % 2 == 0
+                && let Some(&direction) = Direction::ALL.choose(&mut rng)
+            {
+                let sprinting = frame % 10 == 0;
+                if scene.request_move(0, direction, sprinting, &mut data) {
+                    inputs.push((frame, if sprinting { InputEvent::Sprint(direction) } else { InputEvent::Move(direction) }));
+                }
+            }
+            scene.update(FRAME_TIME, &mut data);
+        }
+        assert!(inputs.len() > 10, "only {} moves were made", inputs.len());
+        let points = data.points;
+        assert!(points < 10_000, "walking back over the maze was never charged for");
+
+        let replay = ReplayData { seed, difficulty: DIFFICULTY, algorithm: MazeAlgorithm::DungeonRooms, floors: 1, inputs };
+        let replay = deserialise_replay(&serialise_replay(&replay)).unwrap();
+        let mut player = ReplayPlayer::new(replay);
+        data.points = 10_000;
+        player.scene.build(&mut data);
+        for _ in 0..600 {
+            player.update(0.0, &mut data);
+        }
+        assert_eq!(player.next, player.inputs.len());
+        assert_eq!(player.scene.player_cell(), scene.player_cell());
+        assert_eq!(data.points, points);
+    }
+}
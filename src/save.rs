@@ -0,0 +1,68 @@
+//! Progress that is kept between runs of the game, stored as JSON next to the executable.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::difficulty::DifficultyParams;
+
+pub const SAVE_PATH: &str = "save.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SaveSlot {
+    pub win_streak: u32,
+    /// the most stars earned on each level, keyed by map name or seed.
+    pub stars: HashMap<String, u8>,
+    /// levels in a row finished without alerting an enemy.
+    pub consecutive_stealth: u32,
+    /// whether the Perfect Stealth achievement has been earned.
+    pub perfect_stealth: bool,
+    pub high_score: u32,
+    /// the size of the next generated dungeon, as the last run left it.
+    pub difficulty: DifficultyParams,
+}
+
+impl SaveSlot {
+    /// read the save slot from disk. A missing or unreadable file gives a fresh slot.
+    pub fn load(path: &str) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_save_slot_round_trips_through_a_file() {
+        let slot = SaveSlot {
+            win_streak: 3,
+            stars: HashMap::from([("level1.json".to_string(), 2), ("seed 42".to_string(), 3)]),
+            consecutive_stealth: 1,
+            perfect_stealth: true,
+            high_score: 1234,
+            difficulty: DifficultyParams { maze_width: 45, maze_height: 33, spawn_safe_radius: 5 },
+        };
+        let path = std::env::temp_dir().join("dungeon_diver_save_slot.json");
+        let path = path.to_str().unwrap();
+        slot.save(path).unwrap();
+        assert_eq!(SaveSlot::load(path), slot);
+    }
+
+    #[test]
+    fn a_missing_or_old_save_gives_defaults() {
+        assert_eq!(SaveSlot::load("no/such/save.json"), SaveSlot::default());
+        // a save from before the high score and difficulty were kept
+        let old: SaveSlot = serde_json::from_str(r#"{"win_streak": 2, "stars": {}}"#).unwrap();
+        assert_eq!(old.win_streak, 2);
+        assert_eq!(old.difficulty, DifficultyParams::default());
+    }
+}
@@ -11,10 +11,31 @@ pub enum SceneSwitch {
     None,
     Push(Box<dyn Scene>),
     Replace(Box<dyn Scene>),
+    /// like [`SceneSwitch::Push`], fading to black and back in on the new scene.
+    FadePush(Box<dyn Scene>),
+    /// like [`SceneSwitch::Replace`], fading to black and back in on the new scene.
+    FadeReplace(Box<dyn Scene>),
     Pop,
     Quit,
 }
 
+/// seconds each half of a fade (out to black, then back in) takes.
+pub const FADE_DURATION: f32 = 0.3;
+
+// A fade running between two scenes. The switch it hides is applied once the screen is fully black.
+struct Transition {
+    switch: Option<SceneSwitch>,
+    elapsed: f32,
+}
+
+impl Transition {
+    // how dark the black overlay is, from 0 (clear) to 1 (black)
+    fn overlay_alpha(&self) -> f32 {
+        let progress = self.elapsed / FADE_DURATION;
+        if progress < 1.0 { progress } else { (2.0 - progress).max(0.0) }
+    }
+}
+
 ///
 /// The Scene trait was conceived with the help of ChatGPT 5.2
 /// 
@@ -39,6 +60,16 @@ pub trait Scene {
     /// draw the scene elements. This should be very simple code that only draws using the RaylibDrawHandle
     fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData);
 
+    /// the scene's type, e.g. `dungeon_diver::maze_scene::MazeScene`, to tell scenes apart behind a `Box<dyn Scene>`.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// whether the scene is drawn over the one below it, like a dialog, instead of on its own.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
     /// called when the scene is finished. Do any clean up that is needed when the game ends (free textures or other data).
     /// Rust may take care of most of the memory clean up, but releasing GPU memory might go here.
     fn on_exit(&mut self, _rl: &mut RaylibHandle, _data: &mut GameData) {}
@@ -51,7 +82,7 @@ pub trait Scene {
 pub struct SceneManager {
     scenes: Vec<Box<dyn Scene>>,
     quit: bool,
-
+    transition: Option<Transition>,
 }
 
 impl SceneManager {
@@ -59,29 +90,54 @@ impl SceneManager {
         let mut mgr = Self {
             scenes: vec![initial],
             quit: false,
+            transition: None,
         };
         mgr.scenes.last_mut().unwrap().on_enter(rl, data);
         mgr
     }
 
-    /// handles collecting user input by calling the scene's [`Scene::handle_input`] and does time step updating with [update]
+    /// handles collecting user input by calling the scene's [`Scene::handle_input`] and does time step updating with [update].
+    /// Scenes are updated with `dt` scaled by the game speed setting, fades always run in real time.
     pub fn update(&mut self, rl: &mut RaylibHandle, dt: f32, data: &mut GameData) {
+        // the scenes on either side of a fade are frozen until it finishes
+        if let Some(mut transition) = self.transition.take() {
+            transition.elapsed += dt;
+            if transition.elapsed >= FADE_DURATION
+                && let Some(switch) = transition.switch.take()
+            {
+                self.apply_switch(switch, rl, data);
+            }
+            if transition.elapsed < FADE_DURATION * 2.0 {
+                self.transition = Some(transition);
+            }
+            return;
+        }
+
         if let Some(scene) = self.scenes.last_mut() {
             let switch = scene.handle_input(rl, data);
             self.apply_switch(switch, rl, data);
         }
+        if self.transition.is_some() {
+            return;
+        }
 
         if let Some(scene) = self.scenes.last_mut() {
-            let switch = scene.update(dt, data);
+            let switch = scene.update(data.scaled_dt(dt), data);
             self.apply_switch(switch, rl, data);
         }
     }
 
-    // calls the current scene's [draw] method
+    // calls the current scene's [draw] method, drawing the scene under it first if it is an overlay
     pub fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
         if let Some(scene) = self.scenes.last() {
+            if scene.is_overlay() && self.scenes.len() >= 2 {
+                self.scenes[self.scenes.len() - 2].draw(d, data);
+            }
             scene.draw(d, data);
         }
+        if let Some(transition) = &self.transition {
+            d.draw_rectangle(0, 0, data.screen_width, data.screen_height, Color::BLACK.alpha(transition.overlay_alpha()));
+        }
     }
 
     // applies a switch returned by either the [handle_input] method or the [update] method.
@@ -99,6 +155,12 @@ impl SceneManager {
                 scene.on_enter(rl, data);
                 self.scenes.push(scene);
             }
+            SceneSwitch::FadePush(scene) => {
+                self.transition = Some(Transition { switch: Some(SceneSwitch::Push(scene)), elapsed: 0.0 });
+            }
+            SceneSwitch::FadeReplace(scene) => {
+                self.transition = Some(Transition { switch: Some(SceneSwitch::Replace(scene)), elapsed: 0.0 });
+            }
             SceneSwitch::Pop => {
                 if let Some(mut old_scene) = self.scenes.pop() {
                     old_scene.on_exit(rl, data);
@@ -0,0 +1,68 @@
+//! Captures the whole maze to a PNG, drawn off screen so the HUD and camera are left out.
+use raylib::prelude::*;
+use raylib::core::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// seconds the "Screenshot saved!" notice stays in the HUD.
+pub const NOTICE_DURATION: f32 = 2.0;
+
+/// Draws a scene into an off-screen render texture and writes it out as a PNG.
+pub struct ScreenshotMode;
+
+impl ScreenshotMode {
+    /// render `draw` into a `width` by `height` texture and export it to `screenshot_<time>.png`
+    /// in the current directory. Returns the path that was written.
+    pub fn capture(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        width: u32,
+        height: u32,
+        draw: impl FnOnce(&mut RaylibTextureMode<RaylibHandle>),
+    ) -> Result<String, Error> {
+        let mut target = rl.load_render_texture(thread, width, height)?;
+        {
+            let mut d = rl.begin_texture_mode(thread, &mut target);
+            d.clear_background(Color::WHITE);
+            draw(&mut d);
+        }
+
+        // render textures are stored bottom row first
+        let mut image = target.texture().load_image()?;
+        image.flip_vertical();
+
+        let path = file_name(SystemTime::now());
+        image.export_image(&path);
+        Ok(path)
+    }
+}
+
+/// `screenshot_YYYY-MM-DD_HH-MM-SS.png` for the given time, in UTC.
+pub fn file_name(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let time_of_day = seconds % 86_400;
+    format!(
+        "screenshot_{:04}-{:02}-{:02}_{:02}-{:02}-{:02}.png",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+    )
+}
+
+/// the (year, month, day) of a count of days since 1970-01-01, using Howard Hinnant's
+/// `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
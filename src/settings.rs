@@ -0,0 +1,326 @@
+//! Player preferences, edited from the settings scene.
+use raylib::prelude::Color;
+use std::collections::HashMap;
+
+use crate::difficulty::Difficulty;
+use crate::game_data::GameData;
+use crate::maze_gen::MazeAlgorithm;
+use crate::maze_scene::DUNGEON_FLOORS;
+use crate::rendering::PostProcessEffect;
+use crate::theme::Theme;
+
+/// the most checkpoints that can be asked for.
+pub const MAX_CHECKPOINTS: usize = 5;
+/// the trail fade durations to choose from, in seconds. 0 turns the trail off.
+pub const TRAIL_FADE_OPTIONS: [f32; 4] = [0.0, 2.0, 5.0, 10.0];
+/// the shortest times allowed between two moves to choose from, in seconds. 0 allows a move every frame.
+pub const MOVE_COOLDOWN_OPTIONS: [f32; 4] = [0.0, 0.08, 0.15, 0.25];
+/// the game speeds to choose from, as multiples of normal speed.
+pub const GAME_SPEED_OPTIONS: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+/// Two players on one keyboard, the first on WASD and the second on the arrow keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoOpMode {
+    Off,
+    /// the maze is done as soon as either player reaches the exit.
+    Race,
+    /// both players have to reach the exit.
+    Together,
+}
+
+impl CoOpMode {
+    pub const ALL: [CoOpMode; 3] = [CoOpMode::Off, CoOpMode::Race, CoOpMode::Together];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CoOpMode::Off => "Off",
+            CoOpMode::Race => "Race",
+            CoOpMode::Together => "Together",
+        }
+    }
+}
+
+/// the grid line colours to choose from, with their names for the settings screen.
+pub const GRID_LINE_OPTIONS: [(&str, Color); 3] = [("Gray", Color::GRAY), ("Light", Color::LIGHTGRAY), ("Bold", Color::BLACK)];
+
+pub struct Settings {
+    /// skip purely decorative motion such as the wall bump nudge.
+    pub reduce_motion: bool,
+    /// numbered goals to visit in order before the exit opens. 0 turns checkpoint mode off.
+    pub checkpoints: usize,
+    /// seconds it takes the trail of recently visited cells to fade out. 0 hides the trail.
+    pub trail_fade: f32,
+    /// water floods the maze from the bottom up and the exit moves to the top.
+    pub rising_water: bool,
+    /// Z and X turn the view of the maze in quarter turns, with the controls following the view.
+    pub maze_rotation: bool,
+    /// the exit stays locked until the player has been into all four corners of its floor.
+    pub four_corners: bool,
+    /// seconds after a move before the next one is accepted, so mashing keys is no faster than a steady pace.
+    pub move_cooldown: f32,
+    /// how hard the game is, which decides among other things whether health carries over from one
+    /// level to the next.
+    pub difficulty: Difficulty,
+    /// practice mode: dying starts the same maze again straight away instead of ending the run.
+    pub instant_restart: bool,
+    /// stepping back onto a cell already visited costs points.
+    pub no_backtrack_challenge: bool,
+    /// a second player on the arrow keys.
+    pub co_op: CoOpMode,
+    /// a tone for each side of the player there is a wall on, played after every step.
+    pub wall_audio_cues: bool,
+    /// the exit shows through the fog from the start, for a gentler game.
+    pub reveal_exit: bool,
+    /// a small map in the corner of the cells the player has been next to.
+    pub auto_map: bool,
+    /// a rival walks the shortest way to the exit, and getting there after it ends the run.
+    pub race_rival: bool,
+    /// a small maze solving itself behind the main menu. Can be turned off on slow machines.
+    pub menu_preview: bool,
+    /// how generated dungeons are laid out.
+    pub maze_algorithm: MazeAlgorithm,
+    /// how many floors generated dungeons have, one on its own or two linked by a ladder.
+    pub dungeon_floors: usize,
+    /// outline every cell of the maze.
+    pub show_grid_lines: bool,
+    /// the colour of the outlines when they are shown.
+    pub grid_line_color: Color,
+    /// the colours and tileset the game is drawn with.
+    pub theme: Theme,
+    /// draw particle effects such as pickup sparkles and explosions.
+    pub particles: bool,
+    /// the look drawn over the whole screen. Only has an effect with the `shaders` feature.
+    pub post_process: PostProcessEffect,
+    /// fragment shader files to load instead of the usual ones, by effect.
+    pub shader_paths: HashMap<PostProcessEffect, String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            reduce_motion: false,
+            checkpoints: 0,
+            trail_fade: 5.0,
+            rising_water: false,
+            maze_rotation: false,
+            four_corners: false,
+            move_cooldown: 0.08,
+            difficulty: Difficulty::Normal,
+            instant_restart: false,
+            menu_preview: true,
+            no_backtrack_challenge: false,
+            co_op: CoOpMode::Off,
+            wall_audio_cues: false,
+            reveal_exit: true,
+            auto_map: false,
+            race_rival: false,
+            show_grid_lines: true,
+            grid_line_color: Color::GRAY,
+            theme: Theme::default(),
+            maze_algorithm: MazeAlgorithm::Backtracker,
+            dungeon_floors: DUNGEON_FLOORS,
+            particles: true,
+            post_process: PostProcessEffect::None,
+            shader_paths: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// the fragment shader file for an effect: the one set in `shader_paths`, or the effect's usual file.
+    pub fn shader_path(&self, effect: PostProcessEffect) -> Option<&str> {
+        self.shader_paths.get(&effect).map(String::as_str).or(effect.default_path())
+    }
+}
+
+/// The rows of the settings screen, in the order they are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingItem {
+    ReduceMotion,
+    Checkpoints,
+    TrailFade,
+    RisingWater,
+    MazeRotation,
+    FourCorners,
+    MazeStyle,
+    DungeonFloors,
+    Theme,
+    MoveCooldown,
+    GameSpeed,
+    Difficulty,
+    GridLines,
+    InstantRestart,
+    MenuPreview,
+    NoBacktrack,
+    CoOp,
+    WallCues,
+    RevealExit,
+    AutoMap,
+    RaceRival,
+    PostProcess,
+}
+
+impl SettingItem {
+    /// the post-processing row is only shown when the game is built with shaders.
+    pub const ALL: &'static [SettingItem] = &[
+        SettingItem::ReduceMotion,
+        SettingItem::Checkpoints,
+        SettingItem::TrailFade,
+        SettingItem::RisingWater,
+        SettingItem::MazeRotation,
+        SettingItem::FourCorners,
+        SettingItem::MazeStyle,
+        SettingItem::DungeonFloors,
+        SettingItem::Theme,
+        SettingItem::MoveCooldown,
+        SettingItem::GameSpeed,
+        SettingItem::Difficulty,
+        SettingItem::GridLines,
+        SettingItem::InstantRestart,
+        SettingItem::MenuPreview,
+        SettingItem::NoBacktrack,
+        SettingItem::CoOp,
+        SettingItem::WallCues,
+        SettingItem::RevealExit,
+        SettingItem::AutoMap,
+        SettingItem::RaceRival,
+        #[cfg(feature = "shaders")]
+        SettingItem::PostProcess,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingItem::ReduceMotion => "Reduce motion",
+            SettingItem::Checkpoints => "Checkpoints",
+            SettingItem::TrailFade => "Trail fade",
+            SettingItem::RisingWater => "Rising water",
+            SettingItem::MazeRotation => "Maze rotation",
+            SettingItem::FourCorners => "Four corners",
+            SettingItem::MazeStyle => "Maze style",
+            SettingItem::DungeonFloors => "Dungeon floors",
+            SettingItem::Theme => "Theme",
+            SettingItem::MoveCooldown => "Move cooldown",
+            SettingItem::GameSpeed => "Game speed",
+            SettingItem::Difficulty => "Difficulty",
+            SettingItem::GridLines => "Grid lines",
+            SettingItem::InstantRestart => "Practice mode: instant restart",
+            SettingItem::MenuPreview => "Menu maze preview",
+            SettingItem::NoBacktrack => "No-backtrack challenge",
+            SettingItem::CoOp => "Co-op (player 2 on arrows)",
+            SettingItem::WallCues => "Audio cues for nearby walls",
+            SettingItem::RevealExit => "Show the exit through fog",
+            SettingItem::AutoMap => "Auto-map",
+            SettingItem::RaceRival => "Race against A*",
+            SettingItem::PostProcess => "Screen effect",
+        }
+    }
+
+    /// the current value, formatted for the settings screen. Most live in the settings, and the game
+    /// speed in the game data.
+    pub fn value(&self, data: &GameData) -> String {
+        let settings = &data.settings;
+        match self {
+            SettingItem::ReduceMotion => on_off(settings.reduce_motion).to_string(),
+            SettingItem::Checkpoints => match settings.checkpoints {
+                0 => "Off".to_string(),
+                n => n.to_string(),
+            },
+            SettingItem::TrailFade => match settings.trail_fade {
+                0.0 => "Off".to_string(),
+                seconds => format!("{}s", seconds),
+            },
+            SettingItem::RisingWater => on_off(settings.rising_water).to_string(),
+            SettingItem::MazeRotation => on_off(settings.maze_rotation).to_string(),
+            SettingItem::FourCorners => on_off(settings.four_corners).to_string(),
+            SettingItem::MazeStyle => settings.maze_algorithm.label().to_string(),
+            SettingItem::DungeonFloors => settings.dungeon_floors.to_string(),
+            SettingItem::Theme => settings.theme.name.to_string(),
+            SettingItem::MoveCooldown => match settings.move_cooldown {
+                0.0 => "Off".to_string(),
+                seconds => format!("{}s", seconds),
+            },
+            SettingItem::GameSpeed => format!("{}x", data.game_speed),
+            SettingItem::Difficulty => format!("{} (health: {})", settings.difficulty.label(), settings.difficulty.health_policy().label().to_lowercase()),
+            SettingItem::GridLines => match GRID_LINE_OPTIONS.iter().find(|(_, c)| *c == settings.grid_line_color) {
+                Some((name, _)) if settings.show_grid_lines => name.to_string(),
+                _ => "Off".to_string(),
+            },
+            SettingItem::InstantRestart => on_off(settings.instant_restart).to_string(),
+            SettingItem::MenuPreview => on_off(settings.menu_preview).to_string(),
+            SettingItem::NoBacktrack => on_off(settings.no_backtrack_challenge).to_string(),
+            SettingItem::CoOp => settings.co_op.label().to_string(),
+            SettingItem::WallCues => on_off(settings.wall_audio_cues).to_string(),
+            SettingItem::RevealExit => on_off(settings.reveal_exit).to_string(),
+            SettingItem::AutoMap => on_off(settings.auto_map).to_string(),
+            SettingItem::RaceRival => on_off(settings.race_rival).to_string(),
+            SettingItem::PostProcess => settings.post_process.label().to_string(),
+        }
+    }
+
+    /// change the setting to its next value.
+    pub fn toggle(&self, data: &mut GameData) {
+        let settings = &mut data.settings;
+        match self {
+            SettingItem::ReduceMotion => settings.reduce_motion = !settings.reduce_motion,
+            SettingItem::Checkpoints => settings.checkpoints = (settings.checkpoints + 1) % (MAX_CHECKPOINTS + 1),
+            SettingItem::RisingWater => settings.rising_water = !settings.rising_water,
+            SettingItem::MazeRotation => settings.maze_rotation = !settings.maze_rotation,
+            SettingItem::FourCorners => settings.four_corners = !settings.four_corners,
+            SettingItem::MazeStyle => {
+                let current = MazeAlgorithm::ALL.iter().position(|a| *a == settings.maze_algorithm).unwrap_or(0);
+                settings.maze_algorithm = MazeAlgorithm::ALL[(current + 1) % MazeAlgorithm::ALL.len()];
+            }
+            SettingItem::DungeonFloors => settings.dungeon_floors = settings.dungeon_floors % DUNGEON_FLOORS + 1,
+            SettingItem::Theme => {
+                let current = Theme::ALL.iter().position(|t| *t == settings.theme).unwrap_or(0);
+                settings.theme = Theme::ALL[(current + 1) % Theme::ALL.len()];
+            }
+            SettingItem::MoveCooldown => {
+                let current = MOVE_COOLDOWN_OPTIONS.iter().position(|s| *s == settings.move_cooldown).unwrap_or(0);
+                settings.move_cooldown = MOVE_COOLDOWN_OPTIONS[(current + 1) % MOVE_COOLDOWN_OPTIONS.len()];
+            }
+            SettingItem::GameSpeed => {
+                let current = GAME_SPEED_OPTIONS.iter().position(|s| *s == data.game_speed).unwrap_or(0);
+                data.game_speed = GAME_SPEED_OPTIONS[(current + 1) % GAME_SPEED_OPTIONS.len()];
+            }
+            SettingItem::Difficulty => {
+                let current = Difficulty::ALL.iter().position(|d| *d == settings.difficulty).unwrap_or(0);
+                settings.difficulty = Difficulty::ALL[(current + 1) % Difficulty::ALL.len()];
+            }
+            SettingItem::InstantRestart => settings.instant_restart = !settings.instant_restart,
+            SettingItem::MenuPreview => settings.menu_preview = !settings.menu_preview,
+            SettingItem::NoBacktrack => settings.no_backtrack_challenge = !settings.no_backtrack_challenge,
+            SettingItem::WallCues => settings.wall_audio_cues = !settings.wall_audio_cues,
+            SettingItem::RevealExit => settings.reveal_exit = !settings.reveal_exit,
+            SettingItem::AutoMap => settings.auto_map = !settings.auto_map,
+            SettingItem::RaceRival => settings.race_rival = !settings.race_rival,
+            SettingItem::CoOp => {
+                let current = CoOpMode::ALL.iter().position(|m| *m == settings.co_op).unwrap_or(0);
+                settings.co_op = CoOpMode::ALL[(current + 1) % CoOpMode::ALL.len()];
+            }
+            // Off, then each colour in turn, then back to off
+            SettingItem::GridLines => {
+                let current = GRID_LINE_OPTIONS.iter().position(|(_, c)| *c == settings.grid_line_color);
+                match (settings.show_grid_lines, current) {
+                    (true, Some(i)) if i + 1 < GRID_LINE_OPTIONS.len() => settings.grid_line_color = GRID_LINE_OPTIONS[i + 1].1,
+                    (true, _) => settings.show_grid_lines = false,
+                    (false, _) => {
+                        settings.show_grid_lines = true;
+                        settings.grid_line_color = GRID_LINE_OPTIONS[0].1;
+                    }
+                }
+            }
+            SettingItem::PostProcess => {
+                let current = PostProcessEffect::ALL.iter().position(|e| *e == settings.post_process).unwrap_or(0);
+                settings.post_process = PostProcessEffect::ALL[(current + 1) % PostProcessEffect::ALL.len()];
+            }
+            SettingItem::TrailFade => {
+                let current = TRAIL_FADE_OPTIONS.iter().position(|s| *s == settings.trail_fade).unwrap_or(0);
+                settings.trail_fade = TRAIL_FADE_OPTIONS[(current + 1) % TRAIL_FADE_OPTIONS.len()];
+            }
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "On" } else { "Off" }
+}
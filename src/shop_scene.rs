@@ -0,0 +1,94 @@
+//! The shop a vendor opens, shown over the maze.
+//!
+//! Stepping on a vendor pushes a [`ShopModal`] with three items for sale. The player buys with
+//! the number keys and leaves with Esc, and either way the vendor packs up.
+use raylib::prelude::*;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::game_data::GameData;
+use crate::items::Item;
+use crate::scenes::{Scene, SceneSwitch};
+
+/// how many items a vendor has for sale.
+pub const SHOP_SIZE: usize = 3;
+
+/// An item for sale and what it costs in points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShopItem {
+    pub item: Item,
+    pub price: u32,
+}
+
+impl ShopItem {
+    /// What a vendor on `level` has for sale: [`SHOP_SIZE`] items picked at random (the same item
+    /// can come up more than once), priced at the item's base price per level give or take a quarter.
+    pub fn random_stock(level: u32, rng: &mut impl Rng) -> Vec<ShopItem> {
+        let mut stock = Vec::with_capacity(SHOP_SIZE);
+        for _ in 0..SHOP_SIZE {
+            let Some(&item) = Item::ALL.choose(rng) else { break };
+            let price = item.base_price() as f32 * level.max(1) as f32 * rng.random_range(0.75..=1.25);
+            stock.push(ShopItem { item, price: (price / 5.0).round() as u32 * 5 });
+        }
+        stock
+    }
+}
+
+/// A vendor's stock, drawn over the maze. 1, 2 and 3 buy an item, Esc leaves.
+pub struct ShopModal {
+    stock: Vec<ShopItem>,
+    /// which items have been bought already.
+    sold: Vec<bool>,
+}
+
+impl ShopModal {
+    pub fn new(stock: Vec<ShopItem>) -> Self {
+        let sold = vec![false; stock.len()];
+        Self { stock, sold }
+    }
+}
+
+impl Scene for ShopModal {
+    fn handle_input(&mut self, _rl: &mut RaylibHandle, data: &mut GameData) -> SceneSwitch {
+        if _rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return SceneSwitch::Pop;
+        }
+
+        let keys = [KeyboardKey::KEY_ONE, KeyboardKey::KEY_TWO, KeyboardKey::KEY_THREE];
+        for (i, key) in keys.into_iter().enumerate() {
+            let Some(offer) = self.stock.get(i) else { continue };
+            if _rl.is_key_pressed(key) && !self.sold[i] && data.points >= offer.price {
+                data.points -= offer.price;
+                data.inventory.push(offer.item);
+                self.sold[i] = true;
+            }
+        }
+
+        SceneSwitch::None
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle, data: &mut GameData) {
+        d.draw_rectangle(0, 0, data.screen_width, data.screen_height, Color::BLACK.alpha(0.5));
+        d.draw_rectangle(340, 250, 600, 380, Color::BLACK.alpha(0.85));
+        d.draw_rectangle_lines(340, 250, 600, 380, Color::GOLD);
+        d.draw_text("Vendor", 380, 275, 35, Color::GOLD);
+        d.draw_text(&format!("You have {} points", data.points), 600, 285, 20, Color::LIGHTGRAY);
+
+        for (i, offer) in self.stock.iter().enumerate() {
+            let y = 360 + i as i32 * 60;
+            let (text, color) = if self.sold[i] {
+                (format!("{}. Sold", i + 1), Color::DARKGRAY)
+            } else {
+                let color = if data.points >= offer.price { Color::WHITE } else { Color::GRAY };
+                (format!("{}. {} for {} points", i + 1, offer.item.map_kind(), offer.price), color)
+            };
+            d.draw_text(&text, 380, y, 25, color);
+        }
+
+        d.draw_text("1-3 to buy, Esc to leave.", 380, 590, 20, Color::LIGHTGRAY);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,145 @@
+//! Named looks for the game, picked in the settings: the colours a maze is drawn in, the style of
+//! its grid lines and the tileset hand-made maps use.
+use raylib::prelude::Color;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Saved by its name, and read back as the built-in theme of that name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// behind the maze and the menus.
+    pub background: Color,
+    /// walls fresh into view. They wear lighter the longer they have been seen.
+    pub wall: Color,
+    /// how thick the grid lines are when they are turned on, in pixels.
+    pub grid_line_thickness: f32,
+    /// the tileset hand-made maps are drawn with. Their walls are drawn as plain blocks of `wall`
+    /// when there is none or it cannot be loaded.
+    pub tileset: Option<&'static str>,
+}
+
+impl Theme {
+    pub const DUNGEON: Theme = Theme {
+        name: "Dungeon",
+        background: Color::WHITE,
+        wall: Color::new(80, 80, 80, 255),
+        grid_line_thickness: 1.0,
+        tileset: Some("assets/tileset0.png"),
+    };
+    pub const ICE_CAVE: Theme = Theme {
+        name: "Ice Cave",
+        background: Color::new(228, 242, 250, 255),
+        wall: Color::new(70, 110, 150, 255),
+        grid_line_thickness: 1.0,
+        tileset: Some("assets/tileset_ice.png"),
+    };
+    pub const FOREST: Theme = Theme {
+        name: "Forest",
+        background: Color::new(218, 232, 200, 255),
+        wall: Color::new(55, 90, 45, 255),
+        grid_line_thickness: 2.0,
+        tileset: Some("assets/tileset_forest.png"),
+    };
+
+    /// the themes to choose from in the settings, the default first.
+    pub const ALL: [Theme; 3] = [Theme::DUNGEON, Theme::ICE_CAVE, Theme::FOREST];
+}
+
+/// A palette a map file brings along under its `"theme"` key, each colour as `[r, g, b, a]`. It
+/// takes over from the chosen theme's colours while that map is played.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomTheme {
+    #[serde(with = "rgba")]
+    pub wall_color: Color,
+    /// behind the maze.
+    #[serde(with = "rgba")]
+    pub floor_color: Color,
+    #[serde(with = "rgba")]
+    pub start_color: Color,
+    #[serde(with = "rgba")]
+    pub exit_color: Color,
+    /// the grid lines, when they are turned on.
+    #[serde(with = "rgba")]
+    pub accent_color: Color,
+    /// the bar behind the HUD.
+    #[serde(with = "rgba")]
+    pub hud_color: Color,
+}
+
+impl CustomTheme {
+    /// A palette that looks like `theme`, to start making one from.
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            wall_color: theme.wall,
+            floor_color: theme.background,
+            start_color: Color::GREEN,
+            exit_color: Color::GOLD,
+            accent_color: Color::GRAY,
+            hud_color: Color::new(0, 0, 0, 153),
+        }
+    }
+}
+
+// a colour as `[r, g, b, a]`
+mod rgba {
+    use raylib::prelude::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.r, color.g, color.b, color.a].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let [r, g, b, a] = <[u8; 4]>::deserialize(deserializer)?;
+        Ok(Color::new(r, g, b, a))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DUNGEON
+    }
+}
+
+impl Serialize for Theme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.name.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Theme {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Theme::ALL.into_iter().find(|theme| theme.name == name).ok_or_else(|| de::Error::custom(format!("unknown theme {:?}", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_custom_theme_round_trips_through_json() {
+        let theme = CustomTheme {
+            wall_color: Color::new(10, 20, 30, 255),
+            floor_color: Color::WHITE,
+            start_color: Color::GREEN,
+            exit_color: Color::GOLD,
+            accent_color: Color::new(1, 2, 3, 4),
+            hud_color: Color::new(0, 0, 0, 128),
+        };
+        let json = serde_json::to_string(&theme).unwrap();
+        assert!(json.contains(r#""wall_color":[10,20,30,255]"#), "{}", json);
+        assert_eq!(serde_json::from_str::<CustomTheme>(&json).unwrap(), theme);
+    }
+
+    #[test]
+    fn every_theme_round_trips_through_json() {
+        for theme in Theme::ALL {
+            let json = serde_json::to_string(&theme).unwrap();
+            assert_eq!(json, format!("{:?}", theme.name));
+            assert_eq!(serde_json::from_str::<Theme>(&json).unwrap(), theme);
+        }
+        assert!(serde_json::from_str::<Theme>(r#""Lava Lake""#).is_err());
+    }
+}
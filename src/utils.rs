@@ -1,6 +1,38 @@
 //! Raylib helper functions
+use std::collections::VecDeque;
+use std::io::Write;
+
 use raylib::prelude::*;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::maze_gen::{CellType, FlatGrid};
+
+/// A maze's `(width, height)`, checked at compile time to be odd and at least 5 so the carving has
+/// walls all the way round. Both have to be constant `usize`s.
+#[macro_export]
+macro_rules! maze_size {
+    ($w:expr, $h:expr) => {{
+        const WIDTH: usize = $w;
+        const HEIGHT: usize = $h;
+        const {
+            assert!(WIDTH >= 5 && WIDTH % 2 == 1, "Maze width must be odd and ≥ 5");
+            assert!(HEIGHT >= 5 && HEIGHT % 2 == 1, "Maze height must be odd and ≥ 5");
+        };
+        (WIDTH, HEIGHT)
+    }};
+}
+
+/// `eprintln!` in debug builds only, for notes that help while developing but would only clutter a
+/// player's terminal.
+#[macro_export]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if cfg!(debug_assertions) {
+            eprintln!($($arg)*);
+        }
+    };
+}
 
 pub fn check_collision_point_rect(point: &Vector2, rect: &Rectangle) -> bool {
     let in_x = point.x >= rect.x && point.x <= rect.x + rect.width;
@@ -9,12 +41,130 @@ pub fn check_collision_point_rect(point: &Vector2, rect: &Rectangle) -> bool {
     return in_x && in_y;
 }
 
-pub fn random_point(width: i32, height: i32) -> Vector2 {
-    let mut rng = rand::rng();
-
+pub fn random_point(width: i32, height: i32, rng: &mut impl Rng) -> Vector2 {
     let x = rng.random_range(0..width);
     let y = rng.random_range(0..height);
 
     Vector2{x: x as f32, y: y as f32}
 }
 
+/// Print a grid indexed `[y][x]` to stdout, one line per row, with each cell shown as its
+/// [`CellType`] character.
+pub fn print_grid(grid: &[Vec<CellType>]) {
+    // there is nothing useful to do about a closed stdout while debugging
+    let _ = write_grid(&mut std::io::stdout(), grid);
+}
+
+/// Like [`print_grid`], to any writer.
+pub fn write_grid(out: &mut impl Write, grid: &[Vec<CellType>]) -> std::io::Result<()> {
+    write!(out, "{}", FlatGrid::from_rows(grid).to_ascii())
+}
+
+/// how many frames a [`PerformanceMonitor`] averages over.
+pub const PERFORMANCE_WINDOW: usize = 60;
+
+/// The frame times of the last `window` frames, to tell whether the game is keeping up.
+pub struct PerformanceMonitor {
+    frame_times: VecDeque<f32>,
+    window: usize,
+}
+
+impl PerformanceMonitor {
+    pub fn new(window: usize) -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(window),
+            window,
+        }
+    }
+
+    /// add how long the last frame took, in seconds, dropping the oldest once the window is full.
+    pub fn record(&mut self, frame_time: f32) {
+        if self.frame_times.len() == self.window {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// The average frames per second over the window, or `None` until the window has filled up.
+    pub fn average_fps(&self) -> Option<f32> {
+        if self.frame_times.len() < self.window {
+            return None;
+        }
+        let total: f32 = self.frame_times.iter().sum();
+        (total > 0.0).then(|| self.frame_times.len() as f32 / total)
+    }
+}
+
+impl Default for PerformanceMonitor {
+    fn default() -> Self {
+        Self::new(PERFORMANCE_WINDOW)
+    }
+}
+
+/// The four grid directions something can move or face in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+    /// the (dx, dy) step of one cell in this direction. North is up the screen (negative y).
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+        }
+    }
+
+    /// this direction turned clockwise (as seen on screen) by the given number of quarter turns.
+    pub fn rotated_clockwise(&self, quarter_turns: u8) -> Direction {
+        let mut direction = *self;
+        for _ in 0..quarter_turns % 4 {
+            direction = match direction {
+                Direction::North => Direction::East,
+                Direction::East => Direction::South,
+                Direction::South => Direction::West,
+                Direction::West => Direction::North,
+            };
+        }
+        direction
+    }
+
+    /// the unit vector for this direction in screen space.
+    pub fn to_vector(&self) -> Vector2 {
+        let (dx, dy) = self.delta();
+        Vector2::new(dx as f32, dy as f32)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions_round_trip_through_json() {
+        let json = serde_json::to_string(&Direction::ALL).unwrap();
+        assert_eq!(json, r#"["North","South","East","West"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Direction>>(&json).unwrap(), Direction::ALL);
+    }
+
+    #[test]
+    fn a_small_grid_prints_one_line_per_row() {
+        let grid = vec![
+            vec![CellType::Wall, CellType::Wall, CellType::Wall],
+            vec![CellType::Start, CellType::Path, CellType::Exit],
+            vec![CellType::Wall, CellType::Water, CellType::Wall],
+        ];
+        let mut out = Vec::new();
+        write_grid(&mut out, &grid).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "###\nS.E\n#~#\n");
+    }
+}